@@ -0,0 +1,129 @@
+// Command line options, mostly for developers and QA testing a game without going through it
+// from the start.
+
+use std::path::PathBuf;
+
+use anyhow::anyhow;
+
+/// The environment variable that points the runtime at a package, like `--eupak`.
+pub const EUPAK_ENV: &str = "REDBACK_EUPAK";
+
+/// Options the runtime was launched with.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LaunchOptions {
+    /// The scene to start in instead of the default one, from `--scene <name>`.
+    pub scene: Option<String>,
+    /// The package to load instead of the one next to the executable, from `--eupak <path>`.
+    pub eupak: Option<PathBuf>,
+    /// Whether to render in low-power mode whatever the package says, from `--low-power`.
+    pub low_power: bool,
+}
+
+impl LaunchOptions {
+    /// Parses the arguments that come after the executable's name. Arguments the runtime doesn't
+    /// know about are logged and skipped, as some platforms pass their own.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut options = Self::default();
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            if let Some(scene) = flag_value(&arg, "--scene", &mut args)? {
+                options.scene = Some(scene);
+            } else if let Some(path) = flag_value(&arg, "--eupak", &mut args)? {
+                options.eupak = Some(PathBuf::from(path));
+            } else if arg == "--low-power" {
+                options.low_power = true;
+            } else {
+                log::warn!("Ignoring unknown argument '{}'", arg);
+            }
+        }
+        Ok(options)
+    }
+
+    /// The package to load instead of the derived one, from `--eupak` or else [`EUPAK_ENV`].
+    pub fn eupak_override(&self) -> Option<PathBuf> {
+        self.eupak
+            .clone()
+            .or_else(|| std::env::var_os(EUPAK_ENV).map(PathBuf::from))
+    }
+}
+
+/// The value of `flag` if `arg` is it, taken from either `flag=value` or the next argument.
+fn flag_value(
+    arg: &str,
+    flag: &str,
+    rest: &mut impl Iterator<Item = String>,
+) -> anyhow::Result<Option<String>> {
+    if arg == flag {
+        return rest
+            .next()
+            .map(Some)
+            .ok_or_else(|| anyhow!("{} needs a value after it", flag));
+    }
+    Ok(arg
+        .strip_prefix(flag)
+        .and_then(|value| value.strip_prefix('='))
+        .map(str::to_string))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> anyhow::Result<LaunchOptions> {
+        LaunchOptions::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn no_arguments_change_nothing() {
+        assert_eq!(parse(&[]).unwrap(), LaunchOptions::default());
+    }
+
+    #[test]
+    fn scenes_can_be_given_either_way() {
+        assert_eq!(
+            parse(&["--scene", "Level 2"]).unwrap().scene.as_deref(),
+            Some("Level 2")
+        );
+        assert_eq!(
+            parse(&["--scene=Boss"]).unwrap().scene.as_deref(),
+            Some("Boss")
+        );
+    }
+
+    #[test]
+    fn flags_without_their_value_are_an_error() {
+        assert!(parse(&["--scene"]).is_err());
+    }
+
+    #[test]
+    fn unknown_arguments_are_skipped() {
+        let options = parse(&["-psn_0_12345", "--scene", "Menu", "--sceneless"]).unwrap();
+        assert_eq!(options.scene.as_deref(), Some("Menu"));
+    }
+
+    #[test]
+    fn packages_can_be_given_either_way() {
+        assert_eq!(
+            parse(&["--eupak", "builds/game.eupak"]).unwrap().eupak,
+            Some(PathBuf::from("builds/game.eupak"))
+        );
+        assert_eq!(
+            parse(&["--eupak=game.eupak", "--scene", "Menu"]).unwrap(),
+            LaunchOptions {
+                scene: Some("Menu".to_string()),
+                eupak: Some(PathBuf::from("game.eupak")),
+                ..Default::default()
+            }
+        );
+        assert!(parse(&["--eupak"]).is_err());
+    }
+
+    #[test]
+    fn the_package_argument_wins_over_the_environment() {
+        let options = parse(&["--eupak", "from-args.eupak"]).unwrap();
+        assert_eq!(
+            options.eupak_override(),
+            Some(PathBuf::from("from-args.eupak"))
+        );
+    }
+}
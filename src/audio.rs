@@ -0,0 +1,385 @@
+// Sound playback through rodio.
+//
+// Sounds are given names in the package config, pointing at files relative to the working
+// directory like scene models. Each file is read the first time it's played and kept in memory
+// after that, so sounds played over and over don't go back to the disk. Without an audio device
+// the game runs on silently.
+//
+// Scenes can name a sound as their background music, which loops for as long as the scene
+// is loaded. Switching to a scene with different music fades the old track out while the new one
+// fades in, rather than cutting between them.
+//
+// Models given an [`AudioSource`] in their scene's settings play their sound from where they
+// are. Each frame the sound is panned by which side of the active camera the entity is on and
+// attenuated by how far away it is. Removing the component or despawning the entity stops the
+// sound.
+
+use std::{collections::HashMap, io::Cursor, path::PathBuf, sync::Arc};
+
+use bincode::{Decode, Encode};
+use dropbear_engine::entity::Transform;
+use glam::DVec3;
+use hecs::World;
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink, Source, SpatialSink};
+
+use crate::{
+    config::AudioSettings,
+    hierarchy::{self, GlobalTransform},
+    preferences::VolumeSettings,
+};
+
+/// How far apart the listener's ears are, in world units.
+pub const EAR_SPACING: f64 = 0.2;
+
+/// Plays a sound from an entity's position.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct AudioSource {
+    /// The name of the sound in the package.
+    pub sound: String,
+    pub volume: f32,
+    /// Whether the sound starts over when it ends.
+    pub looping: bool,
+    /// Up to this distance the sound plays at full volume, dropping off further away.
+    pub reference_distance: f64,
+    /// The distance past which the sound can't be heard at all.
+    pub max_distance: f64,
+}
+
+/// Where sounds are heard from, which is the active camera.
+#[derive(Debug, Clone, Copy)]
+pub struct Listener {
+    pub position: DVec3,
+    /// The direction to the listener's right, for panning.
+    pub right: DVec3,
+}
+
+impl Listener {
+    /// A listener at `eye` looking at `target`.
+    pub fn looking_at(eye: DVec3, target: DVec3, up: DVec3) -> Self {
+        let forward = (target - eye).normalize_or(DVec3::NEG_Z);
+        Self {
+            position: eye,
+            right: forward.cross(up).normalize_or(DVec3::X),
+        }
+    }
+}
+
+/// How loud a sound `distance` away is, from 1 up to `reference_distance` falling off with
+/// the inverse of the distance, down to 0 at `max_distance`.
+pub fn attenuation(distance: f64, reference_distance: f64, max_distance: f64) -> f32 {
+    let reference_distance = reference_distance.max(f64::EPSILON);
+    if distance >= max_distance {
+        return 0.0;
+    }
+    if distance <= reference_distance {
+        return 1.0;
+    }
+    (reference_distance / distance) as f32
+}
+
+/// A positional sound that's playing for an entity.
+struct PlayingSource {
+    sound: String,
+    sink: SpatialSink,
+}
+
+/// The crossfade between music tracks when the package doesn't set one, in seconds.
+pub const DEFAULT_CROSSFADE: f32 = 1.0;
+
+/// Plays the package's sounds on the default output device.
+pub struct Audio {
+    /// Dropping the stream stops all sound, so it's kept for as long as the handle is used.
+    output: Option<(OutputStream, OutputStreamHandle)>,
+    sounds: HashMap<String, PathBuf>,
+    clips: HashMap<String, Arc<[u8]>>,
+    music: Music,
+    sources: HashMap<hecs::Entity, PlayingSource>,
+    volumes: VolumeSettings,
+}
+
+/// A looping music track and how far it has faded in.
+struct Track {
+    name: String,
+    sink: Sink,
+    /// From 0 (silent) to 1 (fully faded in).
+    fade: f32,
+}
+
+/// The music that's playing, and any tracks still fading out.
+struct Music {
+    current: Option<Track>,
+    fading_out: Vec<Track>,
+    /// The volume of the music once faded in, which carries over between scenes.
+    volume: f32,
+    /// How long a crossfade takes, in seconds.
+    crossfade: f32,
+}
+
+impl Audio {
+    pub fn new(settings: &AudioSettings, volumes: VolumeSettings) -> Self {
+        let output = match OutputStream::try_default() {
+            Ok(output) => Some(output),
+            Err(e) => {
+                log::warn!(
+                    "No audio device is available, so the game will be silent: {}",
+                    e
+                );
+                None
+            }
+        };
+
+        Self {
+            output,
+            sounds: settings.sounds.clone(),
+            clips: HashMap::new(),
+            music: Music {
+                current: None,
+                fading_out: Vec::new(),
+                volume: volumes.music_gain(),
+                crossfade: settings.crossfade(),
+            },
+            sources: HashMap::new(),
+            volumes,
+        }
+    }
+
+    /// Starts, moves and stops the sounds of entities with an [`AudioSource`], as heard by
+    /// `listener`.
+    pub fn update_sources(&mut self, world: &World, listener: Option<Listener>) {
+        let mut present = Vec::new();
+        let mut query = world.query::<(&AudioSource, &Transform, Option<&GlobalTransform>)>();
+        for (entity, (source, transform, global)) in query.iter() {
+            present.push(entity);
+
+            let restarted = self
+                .sources
+                .get(&entity)
+                .is_none_or(|playing| playing.sound != source.sound);
+            if restarted {
+                self.sources.remove(&entity);
+                if let Some(sink) = self.spatial_sink(source) {
+                    self.sources.insert(
+                        entity,
+                        PlayingSource {
+                            sound: source.sound.clone(),
+                            sink,
+                        },
+                    );
+                }
+            }
+
+            if let (Some(playing), Some(listener)) = (self.sources.get(&entity), listener) {
+                // a child entity's sound comes from where it's drawn
+                let position = hierarchy::world_transform(transform, global).position;
+                let offset = position - listener.position;
+                let gain = attenuation(
+                    offset.length(),
+                    source.reference_distance,
+                    source.max_distance,
+                );
+                // rodio attenuates by the distance to each ear too, so the emitter is kept at a
+                // fixed distance and only used for panning
+                let direction = offset.normalize_or_zero();
+                let emitter = [
+                    direction.dot(listener.right) as f32,
+                    0.0,
+                    -(direction.reject_from(listener.right).length() as f32),
+                ];
+                playing.sink.set_emitter_position(emitter);
+                playing
+                    .sink
+                    .set_volume(gain * source.volume * self.volumes.sfx_gain());
+            }
+        }
+
+        // finished one shot sounds are kept so they aren't started again, and dropped along
+        // with their entity or component
+        self.sources.retain(|entity, playing| {
+            let keep = present.contains(entity);
+            if !keep {
+                playing.sink.stop();
+            }
+            keep
+        });
+    }
+
+    /// Stops every positional sound, for when the world they belong to is torn down.
+    pub fn stop_sources(&mut self) {
+        for (_, playing) in self.sources.drain() {
+            playing.sink.stop();
+        }
+    }
+
+    fn spatial_sink(&mut self, source: &AudioSource) -> Option<SpatialSink> {
+        self.output.as_ref()?;
+        let clip = self.clip(&source.sound)?;
+        let (_, handle) = self.output.as_ref()?;
+
+        let half_spacing = (EAR_SPACING / 2.0) as f32;
+        let sink = SpatialSink::try_new(
+            handle,
+            [0.0, 0.0, -1.0],
+            [-half_spacing, 0.0, 0.0],
+            [half_spacing, 0.0, 0.0],
+        )
+        .map_err(anyhow::Error::from)
+        .and_then(|sink| {
+            let decoder = Decoder::new(Cursor::new(clip))?;
+            if source.looping {
+                sink.append(decoder.repeat_infinite());
+            } else {
+                sink.append(decoder);
+            }
+            Ok(sink)
+        });
+        match sink {
+            Ok(sink) => Some(sink),
+            Err(e) => {
+                log::warn!("Unable to play sound '{}': {}", source.sound, e);
+                None
+            }
+        }
+    }
+
+    /// Fades over to looping the sound named `name` as music. Asking for the track that's
+    /// already playing leaves it be, so scenes sharing music carry on without a break.
+    pub fn play_music(&mut self, name: &str) {
+        if self
+            .music
+            .current
+            .as_ref()
+            .is_some_and(|track| track.name == name)
+        {
+            return;
+        }
+        self.stop_music();
+
+        let Some(clip) = self.clip(name) else {
+            return;
+        };
+        let Some((_, handle)) = &self.output else {
+            return;
+        };
+        let sink = Sink::try_new(handle)
+            .map_err(anyhow::Error::from)
+            .and_then(|sink| {
+                sink.append(Decoder::new_looped(Cursor::new(clip))?);
+                Ok(sink)
+            });
+        match sink {
+            Ok(sink) => {
+                let fade = if self.music.crossfade > 0.0 { 0.0 } else { 1.0 };
+                sink.set_volume(fade * self.music.volume);
+                self.music.current = Some(Track {
+                    name: name.to_string(),
+                    sink,
+                    fade,
+                });
+            }
+            Err(e) => log::warn!("Unable to play music '{}': {}", name, e),
+        }
+    }
+
+    /// Fades out the music that's playing.
+    pub fn stop_music(&mut self) {
+        if let Some(track) = self.music.current.take() {
+            self.music.fading_out.push(track);
+        }
+    }
+
+    pub fn volumes(&self) -> VolumeSettings {
+        self.volumes
+    }
+
+    /// Changes the player's volumes. Music and positional sounds pick them up on their next
+    /// update.
+    pub fn set_volumes(&mut self, volumes: VolumeSettings) {
+        self.volumes = volumes;
+        self.music.volume = volumes.music_gain();
+    }
+
+    /// Moves crossfades along by `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        self.music.update(dt);
+    }
+
+    /// The contents of the sound file named `name`, read from disk the first time.
+    fn clip(&mut self, name: &str) -> Option<Arc<[u8]>> {
+        if let Some(clip) = self.clips.get(name) {
+            return Some(clip.clone());
+        }
+
+        let Some(path) = self.sounds.get(name) else {
+            log::warn!("There is no sound called '{}' in the package", name);
+            return None;
+        };
+        match std::fs::read(path) {
+            Ok(bytes) => {
+                let clip: Arc<[u8]> = bytes.into();
+                self.clips.insert(name.to_string(), clip.clone());
+                Some(clip)
+            }
+            Err(e) => {
+                log::warn!(
+                    "Unable to read sound '{}' from {}: {}",
+                    name,
+                    path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+impl Music {
+    fn update(&mut self, dt: f32) {
+        let step = if self.crossfade > 0.0 {
+            dt / self.crossfade
+        } else {
+            1.0
+        };
+
+        if let Some(track) = &mut self.current {
+            track.fade = (track.fade + step).min(1.0);
+            track.sink.set_volume(track.fade * self.volume);
+        }
+
+        for track in &mut self.fading_out {
+            track.fade = (track.fade - step).max(0.0);
+            track.sink.set_volume(track.fade * self.volume);
+        }
+        self.fading_out.retain(|track| {
+            if track.fade > 0.0 {
+                return true;
+            }
+            track.sink.stop();
+            false
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sounds_fall_off_past_the_reference_distance() {
+        assert_eq!(attenuation(0.5, 2.0, 50.0), 1.0);
+        assert_eq!(attenuation(2.0, 2.0, 50.0), 1.0);
+        assert_eq!(attenuation(8.0, 2.0, 50.0), 0.25);
+        assert_eq!(attenuation(50.0, 2.0, 50.0), 0.0);
+    }
+
+    #[test]
+    fn listener_right_is_perpendicular_to_where_it_looks() {
+        let listener = Listener::looking_at(DVec3::ZERO, DVec3::NEG_Z, DVec3::Y);
+        assert!(
+            listener.right.abs_diff_eq(DVec3::X, 1e-9),
+            "{}",
+            listener.right
+        );
+        let turned = Listener::looking_at(DVec3::ZERO, DVec3::X, DVec3::Y);
+        assert!(turned.right.abs_diff_eq(DVec3::Z, 1e-9), "{}", turned.right);
+    }
+}
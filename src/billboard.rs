@@ -0,0 +1,77 @@
+// Entities that always turn to face the camera, such as sprites and health bars.
+//
+// Scenes make a model a billboard through its model settings. A billboard's rotation is
+// overwritten every frame, once the cameras have moved, so that the
+// side of its model facing +Z points at the active camera.
+
+use bincode::{Decode, Encode};
+use dropbear_engine::entity::Transform;
+use glam::{DMat3, DQuat, DVec3};
+use hecs::World;
+
+/// The direction a model faces before it's rotated.
+pub const FORWARD: DVec3 = DVec3::Z;
+
+/// Turns the entity to face the active camera every frame.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode)]
+pub enum Billboard {
+    /// Face the camera straight on, tilting up and down to follow it.
+    #[default]
+    Full,
+    /// Only turn around the Y axis, staying upright. Suits trees and characters.
+    Cylindrical,
+}
+
+/// Turns every billboard in `world` to face `eye`.
+pub fn face_camera(world: &mut World, eye: DVec3) {
+    for (_, (transform, billboard)) in world.query_mut::<(&mut Transform, &Billboard)>() {
+        if let Some(rotation) = facing(transform.position, eye, *billboard) {
+            transform.rotation = rotation;
+        }
+    }
+}
+
+/// The rotation that points [`FORWARD`] from `position` towards `eye`, or `None` if they're in
+/// the same place (or straight above each other, for cylindrical billboards).
+pub fn facing(position: DVec3, eye: DVec3, billboard: Billboard) -> Option<DQuat> {
+    let mut direction = eye - position;
+    if billboard == Billboard::Cylindrical {
+        direction.y = 0.0;
+    }
+    let forward = direction.try_normalize()?;
+
+    let Some(right) = DVec3::Y.cross(forward).try_normalize() else {
+        // looking straight up or down, where there's no telling which way is right
+        return Some(DQuat::from_rotation_arc(FORWARD, forward));
+    };
+    let up = forward.cross(right);
+    Some(DQuat::from_mat3(&DMat3::from_cols(right, up, forward)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn billboards_point_forward_at_the_eye() {
+        let eye = DVec3::new(3.0, 4.0, -5.0);
+        let rotation = facing(DVec3::ONE, eye, Billboard::Full).unwrap();
+        let forward = rotation * FORWARD;
+        assert!(forward.abs_diff_eq((eye - DVec3::ONE).normalize(), 1e-9));
+    }
+
+    #[test]
+    fn cylindrical_billboards_stay_upright() {
+        let eye = DVec3::new(0.0, 10.0, 5.0);
+        let rotation = facing(DVec3::ZERO, eye, Billboard::Cylindrical).unwrap();
+        assert!((rotation * DVec3::Y).abs_diff_eq(DVec3::Y, 1e-9));
+        assert!((rotation * FORWARD).abs_diff_eq(DVec3::Z, 1e-9));
+    }
+
+    #[test]
+    fn billboards_at_the_eye_keep_their_rotation() {
+        assert_eq!(facing(DVec3::ONE, DVec3::ONE, Billboard::Full), None);
+        assert_eq!(facing(DVec3::ZERO, DVec3::Y, Billboard::Cylindrical), None);
+        assert!(facing(DVec3::ZERO, DVec3::Y, Billboard::Full).is_some());
+    }
+}
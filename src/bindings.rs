@@ -0,0 +1,110 @@
+// Named actions that keys are bound to, so developers (and eventually players) can rebind them
+// without the runtime caring which physical key is used.
+
+use std::collections::HashMap;
+
+use winit::keyboard::KeyCode;
+
+/// Quits the game.
+pub const QUIT: &str = "quit";
+/// Locks or unlocks the cursor to the window.
+pub const TOGGLE_CURSOR_LOCK: &str = "toggle_cursor_lock";
+/// Pauses or resumes the game.
+pub const PAUSE: &str = "pause";
+/// Shows or hides the debug overlay.
+pub const TOGGLE_DEBUG_OVERLAY: &str = "toggle_debug_overlay";
+/// Switches in and out of fullscreen. Only triggers while Alt is held.
+pub const TOGGLE_FULLSCREEN: &str = "toggle_fullscreen";
+/// Saves a screenshot of the next frame.
+pub const SCREENSHOT: &str = "screenshot";
+/// Reloads the current scene from the package, for development.
+pub const RELOAD_SCENE: &str = "reload_scene";
+/// Switches entities between being drawn normally and as wireframes, for development.
+pub const TOGGLE_WIREFRAME: &str = "toggle_wireframe";
+/// Switches to the next scene in alphabetical order, for testing.
+pub const NEXT_SCENE: &str = "next_scene";
+/// Switches to the previous scene in alphabetical order, for testing.
+pub const PREVIOUS_SCENE: &str = "previous_scene";
+/// Moves the pause menu's selection up.
+pub const MENU_UP: &str = "menu_up";
+/// Moves the pause menu's selection down.
+pub const MENU_DOWN: &str = "menu_down";
+/// Presses the pause menu's selected button.
+pub const MENU_SELECT: &str = "menu_select";
+/// Turns the pause menu's selected volume down.
+pub const MENU_LEFT: &str = "menu_left";
+/// Turns the pause menu's selected volume up.
+pub const MENU_RIGHT: &str = "menu_right";
+
+/// Maps action names to the keys that trigger them.
+#[derive(Debug, Clone)]
+pub struct KeyBindings {
+    actions: HashMap<String, KeyCode>,
+}
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        let mut actions = HashMap::new();
+        actions.insert(QUIT.to_string(), KeyCode::Escape);
+        actions.insert(TOGGLE_CURSOR_LOCK.to_string(), KeyCode::F1);
+        actions.insert(PAUSE.to_string(), KeyCode::KeyP);
+        actions.insert(TOGGLE_DEBUG_OVERLAY.to_string(), KeyCode::F3);
+        actions.insert(TOGGLE_FULLSCREEN.to_string(), KeyCode::Enter);
+        actions.insert(SCREENSHOT.to_string(), KeyCode::F12);
+        actions.insert(RELOAD_SCENE.to_string(), KeyCode::F5);
+        actions.insert(TOGGLE_WIREFRAME.to_string(), KeyCode::F4);
+        actions.insert(NEXT_SCENE.to_string(), KeyCode::PageDown);
+        actions.insert(PREVIOUS_SCENE.to_string(), KeyCode::PageUp);
+        actions.insert(MENU_UP.to_string(), KeyCode::ArrowUp);
+        actions.insert(MENU_DOWN.to_string(), KeyCode::ArrowDown);
+        actions.insert(MENU_SELECT.to_string(), KeyCode::Enter);
+        actions.insert(MENU_LEFT.to_string(), KeyCode::ArrowLeft);
+        actions.insert(MENU_RIGHT.to_string(), KeyCode::ArrowRight);
+        Self { actions }
+    }
+}
+
+impl KeyBindings {
+    /// The default bindings with the package's bindings layered on top.
+    pub fn with_overrides(overrides: &HashMap<String, KeyCode>) -> Self {
+        let mut bindings = Self::default();
+        for (action, key) in overrides {
+            bindings.actions.insert(action.clone(), *key);
+        }
+        bindings
+    }
+
+    /// Whether `key` triggers `action`.
+    pub fn is_bound(&self, key: KeyCode, action: &str) -> bool {
+        self.actions.get(action) == Some(&key)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn defaults_are_bound() {
+        let bindings = KeyBindings::default();
+        assert!(bindings.is_bound(KeyCode::Escape, QUIT));
+        assert!(bindings.is_bound(KeyCode::KeyP, PAUSE));
+        assert!(!bindings.is_bound(KeyCode::KeyP, QUIT));
+    }
+
+    #[test]
+    fn overrides_replace_only_their_action() {
+        let overrides = HashMap::from([(PAUSE.to_string(), KeyCode::Backspace)]);
+        let bindings = KeyBindings::with_overrides(&overrides);
+        assert!(bindings.is_bound(KeyCode::Backspace, PAUSE));
+        assert!(!bindings.is_bound(KeyCode::KeyP, PAUSE));
+        assert!(bindings.is_bound(KeyCode::Escape, QUIT));
+    }
+
+    #[test]
+    fn custom_actions_can_be_bound() {
+        let overrides = HashMap::from([("jump".to_string(), KeyCode::Space)]);
+        let bindings = KeyBindings::with_overrides(&overrides);
+        assert!(bindings.is_bound(KeyCode::Space, "jump"));
+    }
+}
@@ -0,0 +1,378 @@
+// Runtime-side camera helpers that sit on top of the engine's `Camera`.
+
+use std::collections::HashSet;
+
+use bincode::{Decode, Encode};
+use dropbear_engine::{camera::Camera, entity::Transform, graphics::Graphics};
+use glam::{DMat4, DVec3};
+use hecs::World;
+
+use crate::{
+    hierarchy::{self, GlobalTransform},
+    labels,
+};
+
+/// Marks the camera the scene is rendered from. Cameras are switched by moving this marker to
+/// another entity with a `Camera` (see [`set_active_camera`]).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ActiveCamera;
+
+/// Makes `camera` the active camera on the next update.
+pub fn set_active_camera(
+    world: &mut World,
+    camera: hecs::Entity,
+) -> Result<(), hecs::NoSuchEntity> {
+    world.insert_one(camera, ActiveCamera)
+}
+
+/// Works out which camera is active after this frame's updates, leaving exactly one camera with
+/// the [`ActiveCamera`] marker.
+///
+/// A marker on any camera other than `current` wins, as that means something asked for a switch.
+/// If several cameras were newly marked, the one with the lowest entity id is picked so the
+/// result doesn't depend on iteration order. If nothing is marked, `current` stays active.
+pub fn resolve_active_camera(
+    world: &mut World,
+    current: Option<hecs::Entity>,
+) -> Option<hecs::Entity> {
+    let mut marked: Vec<hecs::Entity> = world
+        .query::<(&Camera, &ActiveCamera)>()
+        .iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    marked.sort();
+
+    let active = marked
+        .iter()
+        .copied()
+        .find(|entity| Some(*entity) != current)
+        .or(current.filter(|current| world.satisfies::<&Camera>(*current).unwrap_or(false)))
+        .or(marked.first().copied());
+
+    for entity in marked {
+        if Some(entity) != active {
+            let _ = world.remove_one::<ActiveCamera>(entity);
+        }
+    }
+    if let Some(active) = active
+        && !world.satisfies::<&ActiveCamera>(active).unwrap_or(false)
+    {
+        let _ = world.insert_one(active, ActiveCamera);
+    }
+
+    active
+}
+
+/// How a camera projects the scene. Cameras without one use the engine's perspective projection.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub enum Projection {
+    Perspective,
+    /// A projection without perspective, for 2D and isometric games. `size` is half the height
+    /// of the view in world units, with the width following the camera's aspect ratio.
+    Orthographic {
+        size: f64,
+        near: f64,
+        far: f64,
+    },
+}
+
+/// The narrowest and widest vertical field of view, in degrees, a camera can be given. Outside
+/// of this the image is either all but a point or turned inside out.
+pub const FOV_RANGE: (f64, f64) = (10.0, 150.0);
+
+/// Overrides a perspective camera's vertical field of view, in degrees.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FieldOfView(pub f64);
+
+impl FieldOfView {
+    /// The field of view kept inside [`FOV_RANGE`].
+    pub fn degrees(&self) -> f64 {
+        self.0.clamp(FOV_RANGE.0, FOV_RANGE.1)
+    }
+}
+
+/// The aspect ratio of a surface `width` by `height` pixels.
+pub fn aspect_ratio(width: u32, height: u32) -> f64 {
+    width.max(1) as f64 / height.max(1) as f64
+}
+
+/// Matches `camera` to the surface's aspect ratio and its field of view, if it has one, so the
+/// image isn't stretched after a resize.
+pub fn apply_lens(camera: &mut Camera, aspect: f64, fov: Option<&FieldOfView>) {
+    camera.aspect = aspect;
+    if let Some(fov) = fov {
+        camera.fov_y = fov.degrees();
+    }
+}
+
+/// An orthographic projection `size` units from the middle to the top of the view.
+pub fn orthographic(size: f64, aspect: f64, near: f64, far: f64) -> DMat4 {
+    DMat4::orthographic_rh(-size * aspect, size * aspect, -size, size, near, far)
+}
+
+/// The view projection of `camera` with `projection` applied.
+pub fn view_proj(camera: &Camera, projection: Option<&Projection>) -> DMat4 {
+    match projection {
+        Some(Projection::Orthographic { size, near, far }) => {
+            orthographic(*size, camera.aspect, *near, *far)
+                * DMat4::look_at_rh(camera.eye, camera.target, camera.up)
+        }
+        Some(Projection::Perspective) | None => camera.build_vp(),
+    }
+}
+
+/// Overwrites the camera's uniform with an orthographic view projection, after the engine's
+/// `Camera::update` has filled it with its perspective one. Perspective cameras are left as they
+/// are.
+pub fn upload_projection(graphics: &Graphics, camera: &Camera, projection: Option<&Projection>) {
+    if !matches!(projection, Some(Projection::Orthographic { .. })) {
+        return;
+    }
+
+    // laid out like the CameraUniform in shader.wgsl
+    let mut bytes: Vec<u8> = camera
+        .eye
+        .as_vec3()
+        .extend(1.0)
+        .to_array()
+        .iter()
+        .flat_map(|value| value.to_ne_bytes())
+        .collect();
+    bytes.extend(
+        view_proj(camera, projection)
+            .as_mat4()
+            .to_cols_array()
+            .iter()
+            .flat_map(|value| value.to_ne_bytes()),
+    );
+    graphics
+        .state
+        .queue
+        .write_buffer(camera.buffer(), 0, &bytes);
+}
+
+/// Smooths out a `CameraFollowTarget` camera, which otherwise snaps straight to its target.
+///
+/// `lerp_speed` is roughly how many times per second the camera closes the gap to where it wants
+/// to be. A speed of 0 keeps the instant snap.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FollowSmoothing {
+    pub lerp_speed: f64,
+}
+
+/// Moves `current` towards `desired` for a frame of `dt` seconds. The interpolation is
+/// exponential, so the motion looks the same regardless of frame rate.
+pub fn follow_step(current: DVec3, desired: DVec3, lerp_speed: f64, dt: f64) -> DVec3 {
+    if lerp_speed <= 0.0 {
+        return desired;
+    }
+
+    let t = 1.0 - (-lerp_speed * dt).exp();
+    current.lerp(desired, t)
+}
+
+/// Remembers which follow labels have already been warned about, so the warnings only show up
+/// once rather than every frame.
+#[derive(Debug, Default)]
+pub struct FollowWarnings {
+    ambiguous: HashSet<String>,
+    /// Labels that currently match nothing. A label is taken out again once it's found, so
+    /// losing the target a second time is reported again.
+    missing: HashSet<String>,
+}
+
+impl FollowWarnings {
+    pub fn clear(&mut self) {
+        self.ambiguous.clear();
+        self.missing.clear();
+    }
+}
+
+/// Where a follow camera goes while its target can't be found. Without this, the camera stays
+/// wherever it was last.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct FollowFallback {
+    pub eye: DVec3,
+    pub target: DVec3,
+}
+
+/// Finds the world position of the entity a camera follows by its label, through the scene's
+/// [`LabelIndex`](crate::labels::LabelIndex). A child entity is followed where it's drawn, not
+/// where its transform puts it relative to its parent. The index only has the entities the scene
+/// loaded with, so a camera can't follow an entity spawned after that.
+///
+/// When several entities share the label, the one with the lowest entity id is followed. A
+/// warning is logged the first time a label turns out to be ambiguous, and when a label stops
+/// matching anything.
+pub fn find_follow_target(
+    world: &World,
+    label: &str,
+    warnings: &mut FollowWarnings,
+) -> Option<DVec3> {
+    let matches: Vec<(hecs::Entity, DVec3)> = labels::find_all_by_label(world, label)
+        .into_iter()
+        .filter_map(|entity| {
+            let transform = *world.get::<&Transform>(entity).ok()?;
+            let global = world.get::<&GlobalTransform>(entity).ok();
+            let position = hierarchy::world_transform(&transform, global.as_deref()).position;
+            Some((entity, position))
+        })
+        .collect();
+
+    if matches.len() > 1 && warnings.ambiguous.insert(label.to_string()) {
+        log::warn!(
+            "{} entities are labelled '{}', following the one with the lowest id ({:?})",
+            matches.len(),
+            label,
+            matches[0].0
+        );
+    }
+
+    if matches.is_empty() {
+        if warnings.missing.insert(label.to_string()) {
+            log::warn!(
+                "No entity is labelled '{}', so the camera following it has nothing to follow",
+                label
+            );
+        }
+    } else {
+        warnings.missing.remove(label);
+    }
+
+    matches.first().map(|(_, position)| *position)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn followers_without_smoothing_snap() {
+        let desired = DVec3::new(4.0, 2.0, -1.0);
+        assert_eq!(follow_step(DVec3::ZERO, desired, 0.0, 1.0 / 60.0), desired);
+    }
+
+    #[test]
+    fn smoothed_followers_lag_behind_then_catch_up() {
+        let desired = DVec3::new(10.0, 0.0, 0.0);
+        let mut eye = DVec3::ZERO;
+        let mut last_gap = eye.distance(desired);
+        for _ in 0..30 {
+            eye = follow_step(eye, desired, 5.0, 1.0 / 60.0);
+            let gap = eye.distance(desired);
+            assert!(gap > 0.0 && gap < last_gap, "{} after {}", gap, last_gap);
+            last_gap = gap;
+        }
+        for _ in 0..600 {
+            eye = follow_step(eye, desired, 5.0, 1.0 / 60.0);
+        }
+        assert!(eye.distance(desired) < 1e-6);
+    }
+
+    #[test]
+    fn smoothing_doesnt_depend_on_the_frame_rate() {
+        let desired = DVec3::new(0.0, 3.0, 0.0);
+        let mut fast = DVec3::ZERO;
+        for _ in 0..4 {
+            fast = follow_step(fast, desired, 2.0, 0.025);
+        }
+        let slow = follow_step(DVec3::ZERO, desired, 2.0, 0.1);
+        assert!(fast.distance(slow) < 1e-9);
+    }
+
+    /// A world with the label index and every `(label, position)` entity in `entities`.
+    fn labelled_world(entities: &[(&str, DVec3)]) -> (World, Vec<hecs::Entity>) {
+        let mut world = World::new();
+        let mut index = labels::LabelIndex::default();
+        let spawned = entities
+            .iter()
+            .map(|(label, position)| {
+                let entity = world.spawn((Transform {
+                    position: *position,
+                    ..Default::default()
+                },));
+                index.insert(label, entity);
+                entity
+            })
+            .collect();
+        world.spawn((index,));
+        (world, spawned)
+    }
+
+    #[test]
+    fn shared_labels_follow_the_lowest_entity_id() {
+        let (world, _) = labelled_world(&[
+            ("player", DVec3::new(1.0, 0.0, 0.0)),
+            ("player", DVec3::new(2.0, 0.0, 0.0)),
+        ]);
+        let mut warnings = FollowWarnings::default();
+        for _ in 0..3 {
+            assert_eq!(
+                find_follow_target(&world, "player", &mut warnings),
+                Some(DVec3::new(1.0, 0.0, 0.0))
+            );
+        }
+        assert!(warnings.ambiguous.contains("player"));
+    }
+
+    #[test]
+    fn children_are_followed_in_world_space() {
+        let (mut world, spawned) = labelled_world(&[
+            ("ship", DVec3::new(10.0, 0.0, 0.0)),
+            ("turret", DVec3::new(0.0, 1.0, 0.0)),
+        ]);
+        hierarchy::set_parent(&mut world, spawned[1], spawned[0]).unwrap();
+        hierarchy::update_global_transforms(&mut world);
+        let mut warnings = FollowWarnings::default();
+        assert_eq!(
+            find_follow_target(&world, "turret", &mut warnings),
+            Some(DVec3::new(10.0, 1.0, 0.0))
+        );
+    }
+
+    #[test]
+    fn missing_targets_are_warned_about_until_found() {
+        let (mut world, spawned) = labelled_world(&[("player", DVec3::ONE)]);
+        let mut warnings = FollowWarnings::default();
+        assert_eq!(find_follow_target(&world, "playr", &mut warnings), None);
+        assert!(warnings.missing.contains("playr"));
+        // already warned about, so it's still only there the once
+        assert_eq!(find_follow_target(&world, "playr", &mut warnings), None);
+        assert_eq!(warnings.missing.len(), 1);
+
+        assert_eq!(
+            find_follow_target(&world, "player", &mut warnings),
+            Some(DVec3::ONE)
+        );
+        world.despawn(spawned[0]).unwrap();
+        assert_eq!(find_follow_target(&world, "player", &mut warnings), None);
+        assert!(warnings.missing.contains("player"));
+    }
+
+    #[test]
+    fn orthographic_views_are_size_high_and_follow_the_aspect() {
+        let projection = orthographic(5.0, 2.0, 0.1, 100.0);
+        let corner = projection.project_point3(DVec3::new(10.0, 5.0, -50.0));
+        assert!(
+            (corner.x - 1.0).abs() < 1e-9 && (corner.y - 1.0).abs() < 1e-9,
+            "{}",
+            corner
+        );
+        // there's no perspective, so distance doesn't move anything towards the middle
+        let far_corner = projection.project_point3(DVec3::new(10.0, 5.0, -99.0));
+        assert!((far_corner.x - corner.x).abs() < 1e-9, "{}", far_corner);
+    }
+
+    #[test]
+    fn fields_of_view_are_kept_in_range() {
+        assert_eq!(FieldOfView(70.0).degrees(), 70.0);
+        assert_eq!(FieldOfView(1.0).degrees(), FOV_RANGE.0);
+        assert_eq!(FieldOfView(720.0).degrees(), FOV_RANGE.1);
+    }
+
+    #[test]
+    fn empty_surfaces_still_have_an_aspect_ratio() {
+        assert_eq!(aspect_ratio(1920, 1080), 1920.0 / 1080.0);
+        assert_eq!(aspect_ratio(800, 0), 800.0);
+    }
+}
@@ -0,0 +1,76 @@
+// Compressed `.eupak` packages. The whole package is compressed as one stream, and the codec is
+// worked out from the stream's magic bytes, so an uncompressed package still loads as-is.
+
+use std::io::Read;
+
+/// How a package's bytes are compressed: a zstd or gzip stream with its standard framing, or not
+/// at all.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Codec {
+    None,
+    Zstd,
+    Gzip,
+}
+
+const ZSTD_MAGIC: [u8; 4] = [0x28, 0xB5, 0x2F, 0xFD];
+const GZIP_MAGIC: [u8; 2] = [0x1F, 0x8B];
+
+impl Codec {
+    /// Works out the codec from the start of a package.
+    pub fn detect(bytes: &[u8]) -> Self {
+        if bytes.starts_with(&ZSTD_MAGIC) {
+            Self::Zstd
+        } else if bytes.starts_with(&GZIP_MAGIC) {
+            Self::Gzip
+        } else {
+            Self::None
+        }
+    }
+
+    pub fn decompress(self, bytes: &[u8]) -> std::io::Result<Vec<u8>> {
+        match self {
+            Self::None => Ok(bytes.to_vec()),
+            Self::Zstd => zstd::decode_all(bytes),
+            Self::Gzip => {
+                let mut decompressed = Vec::new();
+                flate2::read::GzDecoder::new(bytes).read_to_end(&mut decompressed)?;
+                Ok(decompressed)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PACKAGE: &[u8] = b"not really a package, but any bytes will do";
+
+    fn gzip(bytes: &[u8]) -> Vec<u8> {
+        let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+        std::io::Write::write_all(&mut encoder, bytes).unwrap();
+        encoder.finish().unwrap()
+    }
+
+    #[test]
+    fn uncompressed_packages_are_left_alone() {
+        assert_eq!(Codec::detect(PACKAGE), Codec::None);
+        assert_eq!(Codec::None.decompress(PACKAGE).unwrap(), PACKAGE);
+    }
+
+    #[test]
+    fn zstd_packages_round_trip() {
+        let compressed = zstd::encode_all(PACKAGE, 0).unwrap();
+        let codec = Codec::detect(&compressed);
+        assert_eq!(codec, Codec::Zstd);
+        assert_eq!(codec.decompress(&compressed).unwrap(), PACKAGE);
+    }
+
+    #[test]
+    fn gzip_packages_round_trip() {
+        let compressed = gzip(PACKAGE);
+        let codec = Codec::detect(&compressed);
+        assert_eq!(codec, Codec::Gzip);
+        assert_eq!(codec.decompress(&compressed).unwrap(), PACKAGE);
+    }
+}
@@ -0,0 +1,897 @@
+// Project settings that the game developer ships inside the `.eupak` package, next to the
+// `RuntimeData`. Every field is optional so the runtime can fall back to its defaults.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use bincode::{Decode, Encode};
+use dropbear_engine::{WindowConfiguration, WindowedModes, wgpu::Color};
+use glam::DVec3;
+use winit::keyboard::KeyCode;
+
+use crate::{
+    APP_INFO,
+    audio::{AudioSource, DEFAULT_CROSSFADE},
+    billboard::Billboard,
+    camera::{FieldOfView, FollowFallback, FollowSmoothing, Projection},
+    draw_order::RenderLayer,
+    input::{DEFAULT_STICK_DEAD_ZONE, DEFAULT_STICK_LOOK_SPEED, LookSettings},
+    lights::{LightAttenuation, LightKind},
+    materials::{DEFAULT_METALLIC, DEFAULT_ROUGHNESS, Material},
+    model_cache::DEFAULT_MODEL_CACHE_BUDGET,
+    particles::{DEFAULT_MAX_PARTICLES, ParticleEmitter},
+    pipeline::{CullMode, Culling, Winding},
+    raycast::BoundingBox,
+    text::TextRequest,
+    time::DEFAULT_MAX_DELTA_TIME,
+    transition::DEFAULT_FADE_DURATION,
+    ui::UiElement,
+};
+
+/// Cornflower blue, which scenes are cleared to unless they say otherwise.
+pub const DEFAULT_CLEAR_COLOR: [f64; 4] = [100.0 / 255.0, 149.0 / 255.0, 237.0 / 255.0, 1.0];
+
+/// How bright the ambient light lighting every surface is unless the scene says otherwise.
+pub const DEFAULT_AMBIENT_INTENSITY: f32 = 0.1;
+
+/// How many entities a scene can have before a warning is logged, when the package doesn't say.
+pub const DEFAULT_ENTITY_WARNING: u32 = 10_000;
+
+/// How many entities a scene can have at all, when the package doesn't say.
+pub const DEFAULT_MAX_ENTITIES: u32 = 100_000;
+
+/// The frame rate used when the package doesn't ask for one.
+pub const DEFAULT_MAX_FPS: u32 = 60;
+
+/// The runtime-specific section of a `.eupak` package.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct PackageConfig {
+    pub window: WindowSettings,
+    pub loading_screen: LoadingScreenSettings,
+    pub input: InputSettings,
+    pub timing: TimingSettings,
+    pub debug: DebugSettings,
+    pub post_processing: PostProcessSettings,
+    pub graphics: GraphicsSettings,
+    pub audio: AudioSettings,
+    pub transition: TransitionSettings,
+    pub limits: LimitSettings,
+    pub scripts: ScriptSettings,
+    pub text: TextSettings,
+    pub pause_menu: PauseMenuSettings,
+    pub saves: SaveSettings,
+    /// Action name to key overrides, layered over [`crate::bindings::KeyBindings::default`].
+    #[bincode(with_serde)]
+    pub bindings: HashMap<String, KeyCode>,
+    /// Per-scene settings, keyed by scene name.
+    pub scenes: HashMap<String, SceneSettings>,
+}
+
+impl PackageConfig {
+    /// The settings for `scene_name`, or the defaults if the package has none for it.
+    pub fn scene(&self, scene_name: &str) -> SceneSettings {
+        self.scenes.get(scene_name).cloned().unwrap_or_default()
+    }
+}
+
+/// How the game window should be created.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct WindowSettings {
+    /// The window title, defaulting to the project name.
+    pub title: Option<String>,
+    pub mode: Option<WindowMode>,
+    /// The target frame rate. `Some(0)` means uncapped.
+    pub max_fps: Option<u32>,
+    /// How frames are paced. When unset, the engine's own present mode is left alone and frames
+    /// are capped to `max_fps`.
+    pub frame_pacing: Option<FramePacing>,
+    /// The size of the window in windowed mode.
+    pub resolution: Option<(u32, u32)>,
+    /// Whether only one copy of the game can run at a time, see [`crate::single_instance`].
+    pub single_instance: Option<bool>,
+}
+
+/// How the runtime paces frames, trading tearing against latency.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum FramePacing {
+    /// Present frames as fast as they are rendered, for the lowest latency.
+    Uncapped,
+    /// Limit frames to `max_fps` without vsync.
+    Capped,
+    /// Wait for the display's vertical blank, so there's no tearing.
+    Vsync,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum WindowMode {
+    Windowed,
+    Maximised,
+    Fullscreen,
+}
+
+impl WindowSettings {
+    /// Builds the engine's window configuration, falling back to the defaults for anything that
+    /// was not set.
+    pub fn to_window_configuration(&self, project_name: &str) -> WindowConfiguration {
+        WindowConfiguration {
+            windowed_mode: self.windowed_mode(),
+            title: self
+                .title
+                .clone()
+                .unwrap_or_else(|| project_name.to_string()),
+            max_fps: self.max_fps(),
+            app_info: APP_INFO,
+        }
+    }
+
+    /// The mode the window starts in.
+    pub fn mode(&self) -> WindowMode {
+        self.mode.unwrap_or(WindowMode::Maximised)
+    }
+
+    /// The size of the window in windowed mode.
+    pub fn resolution(&self) -> (u32, u32) {
+        self.resolution.unwrap_or((1280, 720))
+    }
+
+    /// Whether a second copy of the game is stopped from starting. Off unless the package asks.
+    pub fn single_instance(&self) -> bool {
+        self.single_instance.unwrap_or(false)
+    }
+
+    fn windowed_mode(&self) -> WindowedModes {
+        match self.mode() {
+            WindowMode::Windowed => {
+                let (width, height) = self.resolution();
+                WindowedModes::Windowed(width, height)
+            }
+            WindowMode::Maximised => WindowedModes::Maximised,
+            WindowMode::Fullscreen => WindowedModes::Fullscreen,
+        }
+    }
+
+    /// The frame cap handed to the engine. A cap of 0 would end up as a division by zero when
+    /// the engine works out the frame time, so it's treated as uncapped instead.
+    fn max_fps(&self) -> u32 {
+        // vsync paces frames by itself, so there's no need to cap on top of it
+        if matches!(
+            self.frame_pacing,
+            Some(FramePacing::Uncapped | FramePacing::Vsync)
+        ) {
+            return u32::MAX;
+        }
+
+        match self.max_fps {
+            Some(0) => u32::MAX,
+            Some(fps) => fps,
+            None => DEFAULT_MAX_FPS,
+        }
+    }
+}
+
+/// Settings for a single scene that `SceneConfig` has no room for.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct SceneSettings {
+    /// The RGBA colour the scene is cleared to before anything is drawn.
+    pub clear_color: Option<[f64; 4]>,
+    /// Whether the cursor is locked when the scene loads. When unset, the cursor stays however
+    /// it was in the previous scene.
+    pub cursor_locked: Option<bool>,
+    /// A cubemap drawn behind the scene's geometry.
+    pub skybox: Option<SkyboxSettings>,
+    /// The RGB colour of the ambient light every surface gets, however far it is from the
+    /// scene's lights. Defaults to white.
+    pub ambient_color: Option<[f32; 3]>,
+    /// How bright the ambient light is, scaling `ambient_color`.
+    pub ambient_intensity: Option<f32>,
+    /// Per-light settings, keyed by the light's label.
+    pub lights: HashMap<String, LightSettings>,
+    /// Per-entity settings for how their models are drawn, keyed by the entity's label.
+    pub models: HashMap<String, ModelSettings>,
+    /// The name of the sound looped as music while the scene is loaded. Scenes without music
+    /// fade out whatever was playing.
+    pub music: Option<String>,
+    /// Settings for the camera the scene is rendered from.
+    pub camera: CameraSettings,
+    /// The HUD drawn over the scene while it's loaded.
+    pub hud: Vec<UiElement>,
+    /// Text drawn over the HUD while the scene is loaded.
+    pub text: Vec<TextRequest>,
+}
+
+/// Settings for a scene's camera that `CameraComponent` has no room for.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct CameraSettings {
+    /// Roughly how many times a second a following camera closes the gap to its target.
+    /// Defaults to 0, which snaps straight to it.
+    pub follow_smoothing: Option<f64>,
+    /// Where a following camera's eye goes while its target can't be found. The camera stays
+    /// wherever it was last unless both this and `fallback_target` are set.
+    pub fallback_eye: Option<[f64; 3]>,
+    /// Where a following camera looks while its target can't be found.
+    pub fallback_target: Option<[f64; 3]>,
+    /// How the camera projects the scene. Defaults to the engine's perspective projection.
+    pub projection: Option<Projection>,
+    /// The camera's vertical field of view, in degrees, kept within [`crate::camera::FOV_RANGE`].
+    /// Defaults to the one the scene's camera was saved with.
+    pub field_of_view: Option<f64>,
+}
+
+impl CameraSettings {
+    pub fn follow_smoothing(&self) -> Option<FollowSmoothing> {
+        self.follow_smoothing
+            .map(|lerp_speed| FollowSmoothing { lerp_speed })
+    }
+
+    pub fn field_of_view(&self) -> Option<FieldOfView> {
+        self.field_of_view.map(FieldOfView)
+    }
+
+    pub fn follow_fallback(&self) -> Option<FollowFallback> {
+        Some(FollowFallback {
+            eye: self.fallback_eye?.into(),
+            target: self.fallback_target?.into(),
+        })
+    }
+}
+
+/// Settings for a single light that `LightComponent` has no room for.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct LightSettings {
+    /// Whether the light casts shadows. Defaults to `false`.
+    pub casts_shadows: Option<bool>,
+    /// How far the light reaches, fading out on the way there. Lights without a range or
+    /// `falloff` don't fade with distance.
+    pub range: Option<f64>,
+    /// The constant, linear and quadratic terms of how the light fades with distance, for when
+    /// the ones `range` picks aren't wanted.
+    pub falloff: Option<[f64; 3]>,
+    /// What sort of light it is. Defaults to a point light.
+    pub kind: Option<LightKindSettings>,
+}
+
+/// A [`LightKind`] as it's written in the package.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub enum LightKindSettings {
+    Directional {
+        direction: [f64; 3],
+    },
+    Point,
+    /// A spot light, with its cone angles in degrees.
+    Spot {
+        direction: [f64; 3],
+        inner_angle: f64,
+        outer_angle: f64,
+    },
+}
+
+impl From<LightKindSettings> for LightKind {
+    fn from(kind: LightKindSettings) -> Self {
+        match kind {
+            LightKindSettings::Directional { direction } => Self::Directional {
+                direction: direction.into(),
+            },
+            LightKindSettings::Point => Self::Point,
+            LightKindSettings::Spot {
+                direction,
+                inner_angle,
+                outer_angle,
+            } => Self::Spot {
+                direction: direction.into(),
+                inner_angle: inner_angle.to_radians(),
+                outer_angle: outer_angle.to_radians(),
+            },
+        }
+    }
+}
+
+impl LightSettings {
+    /// How the light fades with distance, or `None` when it doesn't.
+    pub fn attenuation(&self) -> Option<LightAttenuation> {
+        match (self.falloff, self.range) {
+            (Some([constant, linear, quadratic]), range) => Some(LightAttenuation {
+                constant,
+                linear,
+                quadratic,
+                range,
+            }),
+            (None, Some(range)) => Some(LightAttenuation::with_range(range)),
+            (None, None) => None,
+        }
+    }
+}
+
+/// Settings for drawing a single entity's model that the model itself doesn't carry.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct ModelSettings {
+    /// Which faces aren't drawn. Defaults to back faces.
+    pub cull_mode: Option<CullMode>,
+    /// Which way round the vertices of front faces go. Defaults to counter-clockwise.
+    pub front_face: Option<Winding>,
+    /// The layer the entity is drawn in. Defaults to the world layer.
+    pub layer: Option<RenderLayer>,
+    /// A tangent-space normal map for the model, relative to the working directory.
+    pub normal_map: Option<PathBuf>,
+    /// How metallic the model's surface is, from 0 to 1. Defaults to 0.
+    pub metallic: Option<f32>,
+    /// How rough the model's surface is, from 0 to 1. Defaults to 0.5.
+    pub roughness: Option<f32>,
+    /// A texture scaling `metallic` by its blue channel and `roughness` by its green, relative
+    /// to the working directory.
+    pub metallic_roughness_map: Option<PathBuf>,
+    /// Whether the model is alpha blended, so it's drawn after the opaque models in its layer,
+    /// back to front. Defaults to `false`.
+    pub transparent: Option<bool>,
+    /// Whether the entity starts out drawn. Defaults to `true`.
+    pub visible: Option<bool>,
+    /// Whether the entity starts out taking part in the game at all. Defaults to `true`.
+    pub enabled: Option<bool>,
+    /// A sound the entity plays from where it is.
+    pub audio_source: Option<AudioSource>,
+    /// The label of the entity this one is attached to, making its transform relative to that
+    /// entity's, see [`crate::hierarchy`].
+    pub parent: Option<String>,
+    /// Turns the entity to face the camera every frame.
+    pub billboard: Option<Billboard>,
+    /// The lowest and highest corners of the box rays hit the entity with, in the model's local
+    /// space. Defaults to the unit cube around its origin.
+    pub bounds: Option<([f64; 3], [f64; 3])>,
+    /// Particles spawned from wherever the entity is.
+    pub particles: Option<ParticleSettings>,
+    /// Whether the entity's script runs every update even when the scripts run per update are
+    /// capped. Defaults to `false`.
+    pub critical_script: Option<bool>,
+}
+
+impl ModelSettings {
+    pub fn culling(&self) -> Culling {
+        Culling {
+            mode: self.cull_mode.unwrap_or_default(),
+            front_face: self.front_face.unwrap_or_default(),
+        }
+    }
+
+    pub fn layer(&self) -> RenderLayer {
+        self.layer.unwrap_or_default()
+    }
+
+    pub fn transparent(&self) -> bool {
+        self.transparent.unwrap_or(false)
+    }
+
+    pub fn bounding_box(&self) -> Option<BoundingBox> {
+        self.bounds.map(|(min, max)| BoundingBox {
+            min: DVec3::from_array(min),
+            max: DVec3::from_array(max),
+        })
+    }
+
+    /// The material the entity is drawn with, or `None` if the model's own is enough.
+    pub fn material(&self) -> Option<Material> {
+        let material = Material {
+            normal_map: self.normal_map.clone(),
+            metallic: self.metallic.unwrap_or(DEFAULT_METALLIC),
+            roughness: self.roughness.unwrap_or(DEFAULT_ROUGHNESS),
+            metallic_roughness_map: self.metallic_roughness_map.clone(),
+        };
+        (material != Material::default()).then_some(material)
+    }
+}
+
+/// A particle emitter on a model, see [`crate::particles`]. Anything unset takes the emitter's
+/// default.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct ParticleSettings {
+    /// How many particles are spawned a second.
+    pub rate: Option<f64>,
+    /// How long each particle lives, in seconds.
+    pub lifetime: Option<f64>,
+    /// The velocity particles start with, in world units a second.
+    pub velocity: Option<[f64; 3]>,
+    /// How far each axis of a particle's starting velocity can stray from `velocity`.
+    pub spread: Option<f64>,
+    /// The width and height of each particle's quad, in world units.
+    pub size: Option<f32>,
+    /// The RGBA colour particles are drawn with.
+    pub color: Option<[f32; 4]>,
+}
+
+impl ParticleSettings {
+    pub fn emitter(&self) -> ParticleEmitter {
+        let mut emitter = ParticleEmitter::default();
+        if let Some(rate) = self.rate {
+            emitter.rate = rate;
+        }
+        if let Some(lifetime) = self.lifetime {
+            emitter.lifetime = lifetime;
+        }
+        if let Some(velocity) = self.velocity {
+            emitter.velocity = DVec3::from_array(velocity);
+        }
+        if let Some(spread) = self.spread {
+            emitter.spread = spread;
+        }
+        if let Some(size) = self.size {
+            emitter.size = size;
+        }
+        if let Some(color) = self.color {
+            emitter.color = color;
+        }
+        emitter
+    }
+}
+
+/// The six faces of a skybox cubemap, relative to the working directory like scene models.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct SkyboxSettings {
+    /// The faces in the order +X, -X, +Y, -Y, +Z, -Z.
+    pub faces: [PathBuf; 6],
+}
+
+impl SceneSettings {
+    pub fn clear_color(&self) -> Color {
+        to_color(self.clear_color.unwrap_or(DEFAULT_CLEAR_COLOR))
+    }
+
+    /// The ambient light's colour scaled by its intensity.
+    pub fn ambient(&self) -> [f32; 3] {
+        let intensity = self
+            .ambient_intensity
+            .filter(|intensity| *intensity >= 0.0)
+            .unwrap_or(DEFAULT_AMBIENT_INTENSITY);
+        self.ambient_color
+            .unwrap_or([1.0; 3])
+            .map(|channel| channel * intensity)
+    }
+}
+
+/// Tuning for player input.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct InputSettings {
+    /// The radial dead zone applied to both gamepad sticks, from 0 to 1.
+    pub stick_dead_zone: Option<f32>,
+    /// What mouse deltas are multiplied by for camera look. Defaults to `1.0`.
+    pub mouse_sensitivity: Option<f32>,
+    /// Whether moving the mouse or the right stick up looks down. Defaults to `false`.
+    pub invert_y: Option<bool>,
+    /// How many pixels of mouse movement a second the right stick stands in for when pushed all
+    /// the way, before `mouse_sensitivity` is applied. 0 turns looking with the stick off.
+    pub stick_look_speed: Option<f32>,
+}
+
+impl InputSettings {
+    pub fn look_settings(&self) -> LookSettings {
+        LookSettings {
+            sensitivity: self
+                .mouse_sensitivity
+                .filter(|sensitivity| *sensitivity > 0.0)
+                .unwrap_or(1.0) as f64,
+            invert_y: self.invert_y.unwrap_or(false),
+            stick_speed: self
+                .stick_look_speed
+                .filter(|speed| *speed >= 0.0)
+                .map_or(DEFAULT_STICK_LOOK_SPEED, f64::from),
+        }
+    }
+
+    pub fn stick_dead_zone(&self) -> f32 {
+        self.stick_dead_zone
+            .unwrap_or(DEFAULT_STICK_DEAD_ZONE)
+            .clamp(0.0, 0.99)
+    }
+}
+
+/// How frame timing is handed to gameplay.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct TimingSettings {
+    /// The longest delta time, in seconds, scripts are updated with.
+    pub max_delta_time: Option<f32>,
+    /// When set, scripts are updated in constant steps of this many seconds instead of once a
+    /// frame, which keeps their logic the same at any frame rate.
+    pub fixed_timestep: Option<f32>,
+}
+
+impl TimingSettings {
+    pub fn max_delta_time(&self) -> f32 {
+        self.max_delta_time
+            .filter(|max| *max > 0.0)
+            .unwrap_or(DEFAULT_MAX_DELTA_TIME)
+    }
+
+    pub fn fixed_timestep(&self) -> Option<f32> {
+        self.fixed_timestep.filter(|step| *step > 0.0)
+    }
+}
+
+/// Which debugging tools players can reach in a shipped game.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct DebugSettings {
+    /// Whether the debug overlay can be toggled. Defaults to `true`.
+    pub overlay: Option<bool>,
+    /// Whether the current scene can be reloaded with a key. Defaults to `true` in debug builds
+    /// only.
+    pub scene_reload: Option<bool>,
+    /// Whether entities can be switched to wireframes with a key. Defaults to `true` in debug
+    /// builds only.
+    pub wireframe: Option<bool>,
+    /// Whether keys can step through every scene in the package in debug builds. Defaults to
+    /// `true`. Release builds never allow it.
+    pub scene_cycling: Option<bool>,
+}
+
+impl DebugSettings {
+    pub fn overlay_allowed(&self) -> bool {
+        self.overlay.unwrap_or(true)
+    }
+
+    pub fn scene_reload_allowed(&self) -> bool {
+        self.scene_reload.unwrap_or(cfg!(debug_assertions))
+    }
+
+    pub fn wireframe_allowed(&self) -> bool {
+        self.wireframe.unwrap_or(cfg!(debug_assertions))
+    }
+
+    pub fn scene_cycling_allowed(&self) -> bool {
+        cfg!(debug_assertions) && self.scene_cycling.unwrap_or(true)
+    }
+}
+
+/// Rendering quality settings.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct GraphicsSettings {
+    /// The MSAA sample count: 1, 2, 4 or 8. Defaults to 1, which turns MSAA off.
+    pub msaa_samples: Option<u32>,
+    /// How much GPU memory models no entity is using can keep taking up, in MiB, so switching
+    /// back to a scene doesn't have to load them again.
+    pub model_cache_budget: Option<u32>,
+    /// Whether to fill the depth buffer before the scene's colours are drawn, so hidden
+    /// fragments are skipped. It helps scenes with a lot of overdraw on fill-rate bound devices,
+    /// but costs an extra pass over the geometry. Defaults to `false`.
+    pub depth_prepass: Option<bool>,
+    /// Whether lights the scene has casting shadows do cast them. Defaults to `true`.
+    pub shadows: Option<bool>,
+    /// Whether to render the cheap way for weak devices, see [`crate::low_power`]. Defaults to
+    /// `false`.
+    pub low_power: Option<bool>,
+    /// Per-layer settings, for the layers that aren't drawn the default way.
+    pub layers: HashMap<RenderLayer, LayerSettings>,
+}
+
+/// Settings for drawing a single render layer.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct LayerSettings {
+    /// Whether the layer's entities are hidden behind what's nearer the camera. Layers that
+    /// aren't depth tested don't hide anything either, and are drawn over whatever came before
+    /// them. Defaults to `true` for every layer but the UI.
+    pub depth_test: Option<bool>,
+}
+
+impl GraphicsSettings {
+    pub fn msaa_samples(&self) -> u32 {
+        self.msaa_samples.unwrap_or(1).max(1)
+    }
+
+    pub fn model_cache_budget(&self) -> u32 {
+        self.model_cache_budget
+            .unwrap_or(DEFAULT_MODEL_CACHE_BUDGET)
+    }
+
+    pub fn depth_prepass(&self) -> bool {
+        self.depth_prepass.unwrap_or(false)
+    }
+
+    pub fn shadows(&self) -> bool {
+        self.shadows.unwrap_or(true)
+    }
+
+    pub fn low_power(&self) -> bool {
+        self.low_power.unwrap_or(false)
+    }
+
+    pub fn depth_test(&self, layer: RenderLayer) -> bool {
+        self.layers
+            .get(&layer)
+            .and_then(|settings| settings.depth_test)
+            .unwrap_or_else(|| layer.depth_tested_by_default())
+    }
+}
+
+/// How the runtime treats the game's scripts.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct ScriptSettings {
+    /// How many times in a row a script can fail to update before it's turned off. When unset,
+    /// failing scripts keep running.
+    pub disable_after_failures: Option<u32>,
+    /// How many scripts run per update at most, with the rest taking their turn on later
+    /// updates. Scripts marked critical always run. When unset, every script runs every update.
+    pub max_per_update: Option<u32>,
+}
+
+impl ScriptSettings {
+    pub fn disable_after_failures(&self) -> Option<u32> {
+        self.disable_after_failures.filter(|failures| *failures > 0)
+    }
+
+    pub fn max_per_update(&self) -> Option<usize> {
+        self.max_per_update
+            .filter(|max| *max > 0)
+            .map(|max| max as usize)
+    }
+}
+
+/// What the runtime keeps in save slots, see [`crate::saves`].
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct SaveSettings {
+    /// The slot the scene the player is in is saved to whenever one loads, so the next run
+    /// starts there instead of in the default scene. Nothing is saved when unset, and a scene
+    /// asked for on the command line always wins.
+    pub resume_slot: Option<String>,
+}
+
+/// How the scenes' text is drawn, see [`crate::text`].
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct TextSettings {
+    /// The TrueType or OpenType font text is drawn in, relative to the working directory like
+    /// scene models. Without one, no text is drawn.
+    pub font: Option<PathBuf>,
+}
+
+/// How the runtime's pause menu looks, see [`crate::pause`]. Colours are RGBA.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct PauseMenuSettings {
+    /// Whether the menu is shown while paused. Games drawing a pause menu of their own can turn
+    /// it off. Defaults to `true`.
+    pub enabled: Option<bool>,
+    /// The colour the scene is dimmed with behind the menu.
+    pub backdrop_color: Option<[f32; 4]>,
+    pub button_color: Option<[f32; 4]>,
+    /// The colour of the selected button.
+    pub selected_color: Option<[f32; 4]>,
+    pub text_color: Option<[f32; 4]>,
+    /// The size of the buttons' labels in pixels.
+    pub text_size: Option<f32>,
+}
+
+impl PauseMenuSettings {
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(true)
+    }
+
+    pub fn backdrop_color(&self) -> [f32; 4] {
+        self.backdrop_color.unwrap_or([0.0, 0.0, 0.0, 0.6])
+    }
+
+    pub fn button_color(&self) -> [f32; 4] {
+        self.button_color.unwrap_or([0.15, 0.15, 0.15, 0.9])
+    }
+
+    pub fn selected_color(&self) -> [f32; 4] {
+        self.selected_color.unwrap_or([0.35, 0.45, 0.75, 0.9])
+    }
+
+    pub fn text_color(&self) -> [f32; 4] {
+        self.text_color.unwrap_or([1.0; 4])
+    }
+
+    pub fn text_size(&self) -> f32 {
+        self.text_size.filter(|size| *size > 0.0).unwrap_or(28.0)
+    }
+}
+
+/// Caps on how big the game's scenes can get.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct LimitSettings {
+    /// How many entities a scene can have before a warning is logged.
+    pub entity_warning: Option<u32>,
+    /// How many entities a scene can have at all. Packages with a scene over it aren't loaded.
+    pub max_entities: Option<u32>,
+    /// How many particles can be alive at once across every emitter in the world.
+    pub max_particles: Option<u32>,
+}
+
+impl LimitSettings {
+    pub fn entity_warning(&self) -> usize {
+        self.entity_warning.unwrap_or(DEFAULT_ENTITY_WARNING) as usize
+    }
+
+    pub fn max_entities(&self) -> usize {
+        self.max_entities.unwrap_or(DEFAULT_MAX_ENTITIES) as usize
+    }
+
+    pub fn max_particles(&self) -> usize {
+        self.max_particles.unwrap_or(DEFAULT_MAX_PARTICLES) as usize
+    }
+}
+
+/// The fade between scenes, see [`crate::transition`].
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct TransitionSettings {
+    /// How long each of the fade out and the fade in take, in seconds. 0 cuts straight to the
+    /// loading screen.
+    pub duration: Option<f32>,
+    /// The RGB colour faded through. Defaults to black.
+    pub color: Option<[f32; 3]>,
+}
+
+impl TransitionSettings {
+    pub fn duration(&self) -> f32 {
+        self.duration
+            .filter(|duration| *duration >= 0.0)
+            .unwrap_or(DEFAULT_FADE_DURATION)
+    }
+
+    pub fn color(&self) -> [f32; 3] {
+        self.color.unwrap_or([0.0; 3])
+    }
+}
+
+/// The sounds the game can play, see [`crate::audio`].
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct AudioSettings {
+    /// Sound files keyed by the name scenes and sound sources refer to them by, relative to the
+    /// working directory like scene models.
+    pub sounds: HashMap<String, PathBuf>,
+    /// How long switching between scene music takes, in seconds. 0 cuts straight over.
+    pub crossfade: Option<f32>,
+}
+
+impl AudioSettings {
+    pub fn crossfade(&self) -> f32 {
+        self.crossfade
+            .filter(|crossfade| *crossfade >= 0.0)
+            .unwrap_or(DEFAULT_CROSSFADE)
+    }
+}
+
+/// Tonemapping of the rendered scene, see [`crate::post`].
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct PostProcessSettings {
+    /// Whether the scene is rendered in HDR and tonemapped. Defaults to `false`.
+    pub enabled: Option<bool>,
+    /// How much the scene is brightened before tonemapping. Defaults to `1.0`.
+    pub exposure: Option<f32>,
+}
+
+impl PostProcessSettings {
+    pub fn enabled(&self) -> bool {
+        self.enabled.unwrap_or(false)
+    }
+
+    pub fn exposure(&self) -> f32 {
+        self.exposure
+            .filter(|exposure| *exposure > 0.0)
+            .unwrap_or(1.0)
+    }
+}
+
+/// How the loading screen looks while a scene is loading.
+#[derive(Debug, Clone, Default, Encode, Decode)]
+pub struct LoadingScreenSettings {
+    /// The RGBA colour the loading screen is cleared to, defaulting to black.
+    pub clear_color: Option<[f64; 4]>,
+    /// An image shown on the loading screen, relative to the working directory like scene
+    /// models. It's scaled to fit the window, with the clear colour around it.
+    pub texture: Option<PathBuf>,
+}
+
+impl LoadingScreenSettings {
+    pub fn clear_color(&self) -> Color {
+        to_color(self.clear_color.unwrap_or([0.0, 0.0, 0.0, 1.0]))
+    }
+}
+
+/// Converts an RGBA colour from the package into a wgpu [`Color`].
+pub fn to_color([r, g, b, a]: [f64; 4]) -> Color {
+    Color { r, g, b, a }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn window_falls_back_to_the_defaults() {
+        let window = WindowSettings::default().to_window_configuration("my-game");
+        assert_eq!(window.title, "my-game");
+        assert_eq!(window.max_fps, DEFAULT_MAX_FPS);
+        assert!(matches!(window.windowed_mode, WindowedModes::Maximised));
+    }
+
+    #[test]
+    fn windowed_mode_uses_the_resolution() {
+        let settings = WindowSettings {
+            title: Some("My Game".to_string()),
+            mode: Some(WindowMode::Windowed),
+            resolution: Some((800, 600)),
+            ..Default::default()
+        };
+        let window = settings.to_window_configuration("my-game");
+        assert_eq!(window.title, "My Game");
+        assert!(matches!(
+            window.windowed_mode,
+            WindowedModes::Windowed(800, 600)
+        ));
+    }
+
+    #[test]
+    fn zero_max_fps_is_uncapped() {
+        let settings = WindowSettings {
+            max_fps: Some(0),
+            ..Default::default()
+        };
+        assert_eq!(settings.max_fps(), u32::MAX);
+    }
+
+    #[test]
+    fn follow_fallback_needs_an_eye_and_a_target() {
+        let mut camera = CameraSettings {
+            fallback_eye: Some([0.0, 5.0, 10.0]),
+            ..Default::default()
+        };
+        assert_eq!(camera.follow_fallback(), None);
+        camera.fallback_target = Some([0.0; 3]);
+        assert_eq!(
+            camera.follow_fallback(),
+            Some(FollowFallback {
+                eye: glam::DVec3::new(0.0, 5.0, 10.0),
+                target: glam::DVec3::ZERO,
+            })
+        );
+    }
+
+    #[test]
+    fn models_only_get_a_material_when_they_change_it() {
+        assert_eq!(ModelSettings::default().material(), None);
+        let model = ModelSettings {
+            metallic: Some(1.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            model.material(),
+            Some(Material {
+                metallic: 1.0,
+                ..Default::default()
+            })
+        );
+    }
+
+    #[test]
+    fn spot_angles_are_read_in_degrees() {
+        let kind = LightKind::from(LightKindSettings::Spot {
+            direction: [0.0, -1.0, 0.0],
+            inner_angle: 90.0,
+            outer_angle: 180.0,
+        });
+        assert_eq!(
+            kind,
+            LightKind::Spot {
+                direction: glam::DVec3::NEG_Y,
+                inner_angle: std::f64::consts::FRAC_PI_2,
+                outer_angle: std::f64::consts::PI,
+            }
+        );
+    }
+
+    #[test]
+    fn falloff_overrides_the_range_terms() {
+        assert_eq!(LightSettings::default().attenuation(), None);
+        let mut light = LightSettings {
+            range: Some(10.0),
+            ..Default::default()
+        };
+        assert_eq!(
+            light.attenuation(),
+            Some(LightAttenuation::with_range(10.0))
+        );
+        light.falloff = Some([1.0, 0.0, 0.5]);
+        assert_eq!(
+            light.attenuation(),
+            Some(LightAttenuation {
+                constant: 1.0,
+                linear: 0.0,
+                quadratic: 0.5,
+                range: Some(10.0),
+            })
+        );
+    }
+}
@@ -0,0 +1,124 @@
+// View frustum culling, so entities that are entirely off screen don't get a draw call.
+
+use dropbear_engine::entity::Transform;
+use glam::{DMat4, DVec3, DVec4};
+
+/// A bounding sphere around an entity's model, in the model's local space.
+///
+/// Entities without one are treated as fitting inside a unit sphere around their origin, which
+/// holds for the default cube and most props that haven't been scaled up in the model itself.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingSphere {
+    pub center: DVec3,
+    pub radius: f64,
+}
+
+impl Default for BoundingSphere {
+    fn default() -> Self {
+        Self {
+            center: DVec3::ZERO,
+            radius: 1.0,
+        }
+    }
+}
+
+impl BoundingSphere {
+    /// Moves the sphere into world space. Non-uniform scales grow the sphere by the largest
+    /// axis, so it still contains the whole model.
+    pub fn to_world(self, transform: &Transform) -> (DVec3, f64) {
+        let center = transform.position + transform.rotation * (transform.scale * self.center);
+        let radius = self.radius * transform.scale.abs().max_element();
+        (center, radius)
+    }
+}
+
+/// The six planes of a camera's view frustum, pointing inwards.
+#[derive(Debug, Clone, Copy)]
+pub struct Frustum {
+    planes: [DVec4; 6],
+}
+
+impl Frustum {
+    /// Extracts the frustum planes from a view-projection matrix, using wgpu's 0 to 1 depth
+    /// range.
+    pub fn from_view_proj(view_proj: DMat4) -> Self {
+        let row0 = view_proj.row(0);
+        let row1 = view_proj.row(1);
+        let row2 = view_proj.row(2);
+        let row3 = view_proj.row(3);
+
+        let planes = [
+            row3 + row0,
+            row3 - row0,
+            row3 + row1,
+            row3 - row1,
+            row2,
+            row3 - row2,
+        ]
+        .map(|plane| plane / plane.truncate().length());
+
+        Self { planes }
+    }
+
+    /// Whether any part of the sphere is inside the frustum.
+    pub fn intersects_sphere(&self, center: DVec3, radius: f64) -> bool {
+        self.planes
+            .iter()
+            .all(|plane| plane.truncate().dot(center) + plane.w >= -radius)
+    }
+}
+
+/// How many entities were drawn and culled in a frame, shown in the debug overlay.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct CullStats {
+    pub drawn: usize,
+    pub culled: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A camera at the origin looking down -Z, with a 90 degree field of view.
+    fn frustum() -> Frustum {
+        let proj = DMat4::perspective_rh(std::f64::consts::FRAC_PI_2, 1.0, 0.1, 100.0);
+        let view = DMat4::look_at_rh(DVec3::ZERO, DVec3::NEG_Z, DVec3::Y);
+        Frustum::from_view_proj(proj * view)
+    }
+
+    #[test]
+    fn spheres_in_front_of_the_camera_are_kept() {
+        assert!(frustum().intersects_sphere(DVec3::new(0.0, 0.0, -10.0), 1.0));
+    }
+
+    #[test]
+    fn spheres_behind_or_past_the_far_plane_are_culled() {
+        let frustum = frustum();
+        assert!(!frustum.intersects_sphere(DVec3::new(0.0, 0.0, 10.0), 1.0));
+        assert!(!frustum.intersects_sphere(DVec3::new(0.0, 0.0, -200.0), 1.0));
+    }
+
+    #[test]
+    fn spheres_poking_into_the_frustum_are_kept() {
+        let frustum = frustum();
+        // the centre is just outside the right plane, at x = -z
+        assert!(frustum.intersects_sphere(DVec3::new(11.0, 0.0, -10.0), 1.0));
+        assert!(!frustum.intersects_sphere(DVec3::new(20.0, 0.0, -10.0), 1.0));
+    }
+
+    #[test]
+    fn bounds_follow_the_transform() {
+        let transform = Transform {
+            position: DVec3::new(1.0, 2.0, 3.0),
+            scale: DVec3::new(1.0, 4.0, 2.0),
+            ..Default::default()
+        };
+        let sphere = BoundingSphere {
+            center: DVec3::X,
+            radius: 0.5,
+        };
+        let (center, radius) = sphere.to_world(&transform);
+        assert_eq!(center, DVec3::new(2.0, 2.0, 3.0));
+        assert_eq!(radius, 2.0);
+    }
+}
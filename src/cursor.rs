@@ -0,0 +1,23 @@
+// Grabbing the cursor for mouse look.
+//
+// The engine only passes on where the cursor is, not raw device motion, so camera look uses the
+// distance the cursor moved from the middle of the window, warping it back there after every
+// move. Where the windowing backend can confine the cursor to the window, it's confined too, so a
+// fast flick can't carry it off the window before it's warped back.
+
+use winit::window::{CursorGrabMode, Window};
+
+/// Confines or releases the cursor.
+pub fn grab(window: &Window, locked: bool) {
+    let mode = if locked {
+        CursorGrabMode::Confined
+    } else {
+        CursorGrabMode::None
+    };
+    if let Err(e) = window.set_cursor_grab(mode) {
+        log::debug!(
+            "Unable to confine the cursor ({}), relying on warping it alone",
+            e
+        );
+    }
+}
@@ -0,0 +1,126 @@
+// A small debug overlay with frame timing and scene statistics, for looking into performance in
+// shipped builds without any external tools.
+//
+// There's no text rendering in the runtime yet, so the overlay is shown in the window title.
+
+use std::sync::Arc;
+
+use winit::window::Window;
+
+use crate::culling::CullStats;
+
+/// How often, in seconds, the shown numbers are refreshed. Refreshing every frame makes them
+/// impossible to read.
+pub const REFRESH_INTERVAL: f32 = 0.5;
+
+#[derive(Debug, Default)]
+pub struct DebugOverlay {
+    visible: bool,
+    /// The window title from before the overlay took it over.
+    base_title: Option<String>,
+    frames: u32,
+    elapsed: f32,
+    pub fps: f32,
+    pub frame_time_ms: f32,
+    pub entity_count: u32,
+    pub draw_calls: usize,
+    /// How many entities were drawn and culled in the last frame.
+    pub cull_stats: CullStats,
+    /// How many times scripts have failed to update since the scene loaded.
+    pub script_failures: u32,
+    /// The label of the entity last clicked on, and how far from the camera it was.
+    pub picked: Option<(String, f64)>,
+}
+
+impl DebugOverlay {
+    pub fn is_visible(&self) -> bool {
+        self.visible
+    }
+
+    /// Shows or hides the overlay, giving the window its own title back when hidden.
+    pub fn toggle(&mut self, window: Option<&Arc<Window>>) {
+        self.visible = !self.visible;
+
+        if let Some(window) = window {
+            if self.visible {
+                self.base_title = Some(window.title());
+            } else if let Some(title) = self.base_title.take() {
+                window.set_title(&title);
+            }
+        }
+    }
+
+    /// Accumulates a frame of `dt` seconds. Returns `true` when the numbers were refreshed.
+    pub fn record_frame(&mut self, dt: f32, entity_count: u32) -> bool {
+        self.frames += 1;
+        self.elapsed += dt;
+        self.entity_count = entity_count;
+
+        if self.elapsed < REFRESH_INTERVAL {
+            return false;
+        }
+
+        self.fps = self.frames as f32 / self.elapsed;
+        self.frame_time_ms = self.elapsed * 1000.0 / self.frames as f32;
+        self.frames = 0;
+        self.elapsed = 0.0;
+        true
+    }
+
+    /// The overlay's text, as shown to the player.
+    pub fn text(&self) -> String {
+        let mut text = format!(
+            "{:.0} FPS | {:.2} ms | {} entities ({} drawn, {} culled) | {} draw calls | {} script errors",
+            self.fps,
+            self.frame_time_ms,
+            self.entity_count,
+            self.cull_stats.drawn,
+            self.cull_stats.culled,
+            self.draw_calls,
+            self.script_failures
+        );
+        if let Some((label, distance)) = &self.picked {
+            text.push_str(&format!(" | picked '{}' {:.1} away", label, distance));
+        }
+        text
+    }
+
+    /// Draws the overlay, if it's visible.
+    pub fn show(&self, window: Option<&Arc<Window>>) {
+        if !self.visible {
+            return;
+        }
+
+        if let Some(window) = window {
+            let base_title = self.base_title.as_deref().unwrap_or_default();
+            window.set_title(&format!("{} [{}]", base_title, self.text()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn numbers_refresh_every_interval() {
+        let mut overlay = DebugOverlay::default();
+        assert!(!overlay.record_frame(0.2, 10));
+        assert!(overlay.record_frame(0.3, 12));
+        assert_eq!(overlay.fps, 4.0);
+        assert_eq!(overlay.frame_time_ms, 250.0);
+        assert_eq!(overlay.entity_count, 12);
+    }
+
+    #[test]
+    fn the_picked_entity_is_shown() {
+        let mut overlay = DebugOverlay::default();
+        assert!(!overlay.text().contains("picked"));
+        overlay.picked = Some(("crate".to_string(), 4.0));
+        assert!(
+            overlay.text().ends_with(" | picked 'crate' 4.0 away"),
+            "{}",
+            overlay.text()
+        );
+    }
+}
@@ -0,0 +1,47 @@
+// Noticing when the GPU device is lost.
+//
+// Some drivers, Android's in particular, can lose the GPU device while the app is running, after
+// which nothing created against it can be used again. The engine creates the device once at
+// startup and owns it, so the runtime has no way to create a new one and carry on. What it does
+// instead is stop updating and rendering as soon as the loss is reported, let go of everything it
+// created on the GPU, and tell the player what happened before quitting, rather than crashing
+// partway through a frame.
+
+use std::sync::{Arc, Mutex};
+
+use dropbear_engine::wgpu;
+
+/// Listens for the device being lost, reported by wgpu from whichever thread notices.
+#[derive(Debug, Default)]
+pub struct DeviceLostSignal {
+    /// The message the device was lost with, once it has been.
+    lost: Arc<Mutex<Option<String>>>,
+    watching: bool,
+}
+
+impl DeviceLostSignal {
+    /// Starts listening for `device` being lost. The device stays the same for the whole run, so
+    /// only the first call does anything.
+    pub fn watch(&mut self, device: &wgpu::Device) {
+        if self.watching {
+            return;
+        }
+        self.watching = true;
+
+        let lost = self.lost.clone();
+        device.set_device_lost_callback(move |reason, message| {
+            // the device is also destroyed when the app quits, which isn't something to report
+            if reason == wgpu::DeviceLostReason::Destroyed {
+                return;
+            }
+            if let Ok(mut lost) = lost.lock() {
+                lost.get_or_insert(message);
+            }
+        });
+    }
+
+    /// The message the device was lost with, if it's been lost since the last call.
+    pub fn take(&self) -> Option<String> {
+        self.lost.lock().ok()?.take()
+    }
+}
@@ -0,0 +1,63 @@
+// Works out the order entities are drawn in, so blended geometry composites correctly.
+//
+// Entities are drawn a layer at a time, from the background to the UI, whichever order they sit
+// in the world. Within a layer opaque entities come first, then transparent ones back to front.
+
+use bincode::{Decode, Encode};
+
+/// Which group an entity is drawn in. Layers are drawn in the order they're declared here, so
+/// later layers composite over earlier ones.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Encode, Decode)]
+pub enum RenderLayer {
+    Background,
+    #[default]
+    World,
+    Foreground,
+    Ui,
+}
+
+impl RenderLayer {
+    /// Whether the layer is depth tested when the package doesn't say. Only the UI isn't, so it
+    /// always ends up over the scene.
+    pub fn depth_tested_by_default(&self) -> bool {
+        *self != Self::Ui
+    }
+}
+
+/// Marks an entity whose model is alpha blended, which the package says as the model's materials
+/// don't.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Transparent;
+
+/// Sorts transparent draws (paired with their squared distance to the camera eye) so the
+/// furthest one is drawn first. Entities at the same depth keep their relative order.
+pub fn sort_back_to_front<T>(draws: &mut [(T, f64)]) {
+    draws.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn furthest_draws_come_first() {
+        let mut draws = [("near", 1.0), ("far", 9.0), ("middle", 4.0)];
+        sort_back_to_front(&mut draws);
+        assert_eq!(draws.map(|(name, _)| name), ["far", "middle", "near"]);
+    }
+
+    #[test]
+    fn draws_at_the_same_depth_keep_their_order() {
+        let mut draws = [("first", 4.0), ("far", 9.0), ("second", 4.0)];
+        sort_back_to_front(&mut draws);
+        assert_eq!(draws.map(|(name, _)| name), ["far", "first", "second"]);
+    }
+
+    #[test]
+    fn layers_are_drawn_background_first() {
+        assert!(RenderLayer::Background < RenderLayer::World);
+        assert!(RenderLayer::World < RenderLayer::Foreground);
+        assert!(RenderLayer::Foreground < RenderLayer::Ui);
+        assert!(!RenderLayer::Ui.depth_tested_by_default());
+    }
+}
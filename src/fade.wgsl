@@ -0,0 +1,19 @@
+// Shader for the fullscreen colour faded over the scene during scene transitions
+
+struct Fade {
+    color: vec4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> fade: Fade;
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> @builtin(position) vec4<f32> {
+    // a single triangle that covers the whole screen
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    return vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+}
+
+@fragment
+fn fs_main() -> @location(0) vec4<f32> {
+    return fade.color;
+}
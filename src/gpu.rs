@@ -0,0 +1,54 @@
+// Checks on the GPU made by the runtime around the engine's own setup.
+//
+// The engine picks the adapter and creates the device itself, and panics deep in its setup if
+// there's no adapter to create it on, such as on a headless server or with broken drivers.
+// Before handing over to the engine, the runtime asks wgpu for an adapter the same way, so it can
+// tell the player what's wrong instead.
+
+use std::{
+    pin::pin,
+    task::{Context, Poll, Waker},
+};
+
+use dropbear_engine::wgpu;
+
+/// What looking for a GPU adapter found.
+#[derive(Debug, Clone)]
+pub enum AdapterCheck {
+    Found(wgpu::AdapterInfo),
+    Missing,
+    /// The answer didn't come straight away, which is the case on the web, so it's left to the
+    /// engine.
+    Unknown,
+}
+
+/// Looks for an adapter the way the engine does, with wgpu's default backends, preferring
+/// `power_preference`.
+pub fn check_adapter(power_preference: wgpu::PowerPreference) -> AdapterCheck {
+    let instance = wgpu::Instance::default();
+    let options = wgpu::RequestAdapterOptions {
+        power_preference,
+        ..Default::default()
+    };
+    match ready(instance.request_adapter(&options)) {
+        Some(Ok(adapter)) => AdapterCheck::Found(adapter.get_info()),
+        Some(Err(e)) => {
+            log::error!("No GPU adapter found: {}", e);
+            AdapterCheck::Missing
+        }
+        None => AdapterCheck::Unknown,
+    }
+}
+
+/// The output of `future` if it's already finished. wgpu's futures finish as soon as they're
+/// created on native backends, but not on the web.
+pub fn ready<T>(future: impl Future<Output = T>) -> Option<T> {
+    let mut future = pin!(future);
+    match future
+        .as_mut()
+        .poll(&mut Context::from_waker(Waker::noop()))
+    {
+        Poll::Ready(output) => Some(output),
+        Poll::Pending => None,
+    }
+}
@@ -0,0 +1,242 @@
+// Parenting entities to each other, so a child moves along with its parent.
+//
+// A child's `Transform` is relative to its parent. Every frame, once scripts have moved things
+// around, each child's transform is composed with its parents' into a `GlobalTransform`, which is
+// what it's drawn with. Entities without a parent are drawn with their `Transform` as before.
+//
+// Scenes attach a model to another through its `parent` label in the model settings. `Parent` and
+// `Children` are kept in step by `set_parent`.
+
+use std::collections::HashMap;
+
+use dropbear_engine::entity::Transform;
+use hecs::World;
+
+/// How deep parenting can go. Anything deeper is treated as a cycle and left where it is.
+pub const MAX_DEPTH: usize = 64;
+
+/// The entity this one is attached to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Parent(pub hecs::Entity);
+
+/// The entities attached to this one.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Children(pub Vec<hecs::Entity>);
+
+/// A child's transform in world space, worked out from its parents each frame.
+#[derive(Debug, Clone, Copy)]
+pub struct GlobalTransform(pub Transform);
+
+/// Attaches `child` to `parent`, detaching it from any parent it had. Attaching an entity to
+/// itself or to one of its own children is refused, as it would never settle on a position.
+pub fn set_parent(
+    world: &mut World,
+    child: hecs::Entity,
+    parent: hecs::Entity,
+) -> anyhow::Result<()> {
+    if !world.contains(child) || !world.contains(parent) {
+        return Err(anyhow::anyhow!(
+            "can't parent {:?} to {:?} as one of them doesn't exist",
+            child,
+            parent
+        ));
+    }
+    if child == parent || is_ancestor(world, child, parent) {
+        return Err(anyhow::anyhow!(
+            "can't parent {:?} to {:?} as it would make a cycle",
+            child,
+            parent
+        ));
+    }
+
+    remove_parent(world, child);
+    world.insert_one(child, Parent(parent))?;
+    let added = world
+        .get::<&mut Children>(parent)
+        .map(|mut children| children.0.push(child))
+        .is_ok();
+    if !added {
+        world.insert_one(parent, Children(vec![child]))?;
+    }
+    Ok(())
+}
+
+/// Detaches `child` from its parent, leaving its transform as it is. Since that was relative to
+/// the parent, the child ends up wherever its transform puts it in world space.
+fn remove_parent(world: &mut World, child: hecs::Entity) {
+    let Ok(Parent(parent)) = world.remove_one::<Parent>(child) else {
+        return;
+    };
+    let _ = world.remove_one::<GlobalTransform>(child);
+    if let Ok(mut children) = world.get::<&mut Children>(parent) {
+        children.0.retain(|entity| *entity != child);
+    }
+}
+
+/// Works out the `GlobalTransform` of every entity with a parent. Children of a parent that's
+/// been despawned are detached, keeping their transform.
+pub fn update_global_transforms(world: &mut World) {
+    let orphans: Vec<hecs::Entity> = world
+        .query::<&Parent>()
+        .iter()
+        .filter(|(_, parent)| !world.contains(parent.0))
+        .map(|(entity, _)| entity)
+        .collect();
+    for orphan in orphans {
+        let _ = world.remove_one::<Parent>(orphan);
+        let _ = world.remove_one::<GlobalTransform>(orphan);
+    }
+
+    let mut globals = HashMap::new();
+    let children: Vec<hecs::Entity> = world
+        .query::<&Parent>()
+        .iter()
+        .map(|(entity, _)| entity)
+        .collect();
+    for child in &children {
+        global_transform(world, *child, &mut globals, 0);
+    }
+    for child in children {
+        if let Some(global) = globals.get(&child) {
+            let _ = world.insert_one(child, GlobalTransform(*global));
+        }
+    }
+}
+
+/// The transform `entity` is drawn with: its global transform if it has a parent, otherwise
+/// its own.
+pub fn world_transform(transform: &Transform, global: Option<&GlobalTransform>) -> Transform {
+    global.map_or(*transform, |global| global.0)
+}
+
+/// Puts `local`, relative to `parent`, into the space `parent` is in.
+pub fn compose(parent: &Transform, local: &Transform) -> Transform {
+    let mut transform = *local;
+    transform.position = parent.position + parent.rotation * (parent.scale * local.position);
+    transform.rotation = parent.rotation * local.rotation;
+    transform.scale = parent.scale * local.scale;
+    transform
+}
+
+fn global_transform(
+    world: &World,
+    entity: hecs::Entity,
+    globals: &mut HashMap<hecs::Entity, Transform>,
+    depth: usize,
+) -> Option<Transform> {
+    if let Some(global) = globals.get(&entity) {
+        return Some(*global);
+    }
+    let local = *world.get::<&Transform>(entity).ok()?;
+    let parent = world.get::<&Parent>(entity).ok().map(|parent| parent.0);
+
+    let global = match parent {
+        Some(parent) if depth < MAX_DEPTH => {
+            match global_transform(world, parent, globals, depth + 1) {
+                Some(parent) => compose(&parent, &local),
+                None => local,
+            }
+        }
+        _ => local,
+    };
+    globals.insert(entity, global);
+    Some(global)
+}
+
+/// Whether `ancestor` is `entity`'s parent, or its parent's parent and so on.
+fn is_ancestor(world: &World, ancestor: hecs::Entity, entity: hecs::Entity) -> bool {
+    let mut current = entity;
+    for _ in 0..MAX_DEPTH {
+        match world.get::<&Parent>(current) {
+            Ok(parent) if parent.0 == ancestor => return true,
+            Ok(parent) => current = parent.0,
+            Err(_) => return false,
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use glam::{DQuat, DVec3};
+
+    use super::*;
+
+    fn at(world: &mut World, x: f64) -> hecs::Entity {
+        world.spawn((Transform {
+            position: DVec3::new(x, 0.0, 0.0),
+            ..Default::default()
+        },))
+    }
+
+    #[test]
+    fn children_move_rotate_and_scale_with_their_parent() {
+        let parent = Transform {
+            position: DVec3::new(1.0, 0.0, 0.0),
+            rotation: DQuat::from_rotation_y(std::f64::consts::FRAC_PI_2),
+            scale: DVec3::splat(2.0),
+        };
+        let local = Transform {
+            position: DVec3::new(1.0, 0.0, 0.0),
+            ..Default::default()
+        };
+        let global = compose(&parent, &local);
+        assert!(
+            global
+                .position
+                .abs_diff_eq(DVec3::new(1.0, 0.0, -2.0), 1e-9)
+        );
+        assert_eq!(global.scale, DVec3::splat(2.0));
+    }
+
+    #[test]
+    fn grandchildren_are_composed_through_every_parent() {
+        let mut world = World::new();
+        let root = at(&mut world, 1.0);
+        let child = at(&mut world, 2.0);
+        let grandchild = at(&mut world, 4.0);
+        set_parent(&mut world, child, root).unwrap();
+        set_parent(&mut world, grandchild, child).unwrap();
+        update_global_transforms(&mut world);
+
+        let global = world.get::<&GlobalTransform>(grandchild).unwrap().0;
+        assert_eq!(global.position, DVec3::new(7.0, 0.0, 0.0));
+        assert!(world.get::<&GlobalTransform>(root).is_err());
+    }
+
+    #[test]
+    fn cycles_are_refused() {
+        let mut world = World::new();
+        let a = at(&mut world, 0.0);
+        let b = at(&mut world, 0.0);
+        set_parent(&mut world, b, a).unwrap();
+        assert!(set_parent(&mut world, a, b).is_err());
+        assert!(set_parent(&mut world, a, a).is_err());
+    }
+
+    #[test]
+    fn reparenting_moves_the_child_between_parents() {
+        let mut world = World::new();
+        let a = at(&mut world, 0.0);
+        let b = at(&mut world, 0.0);
+        let child = at(&mut world, 0.0);
+        set_parent(&mut world, child, a).unwrap();
+        set_parent(&mut world, child, b).unwrap();
+        assert!(world.get::<&Children>(a).unwrap().0.is_empty());
+        assert_eq!(world.get::<&Children>(b).unwrap().0, vec![child]);
+        assert_eq!(*world.get::<&Parent>(child).unwrap(), Parent(b));
+    }
+
+    #[test]
+    fn orphans_keep_their_own_transform() {
+        let mut world = World::new();
+        let parent = at(&mut world, 5.0);
+        let child = at(&mut world, 1.0);
+        set_parent(&mut world, child, parent).unwrap();
+        update_global_transforms(&mut world);
+        world.despawn(parent).unwrap();
+        update_global_transforms(&mut world);
+        assert!(world.get::<&Parent>(child).is_err());
+        assert!(world.get::<&GlobalTransform>(child).is_err());
+    }
+}
@@ -0,0 +1,125 @@
+// Reloads scripts when their source changes on disk, so scripts can be iterated on without
+// restarting the game. Only compiled into debug builds.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// How often the watched files are checked for changes.
+pub const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Watches script files by polling their modification times.
+#[derive(Debug)]
+pub struct ScriptWatcher {
+    modified: HashMap<PathBuf, Option<SystemTime>>,
+    last_poll: Instant,
+}
+
+impl Default for ScriptWatcher {
+    fn default() -> Self {
+        Self {
+            modified: HashMap::new(),
+            last_poll: Instant::now(),
+        }
+    }
+}
+
+impl ScriptWatcher {
+    /// Starts watching `path`, treating its current contents as already loaded.
+    pub fn watch(&mut self, path: &Path) {
+        self.modified
+            .insert(path.to_path_buf(), modified_time(path));
+    }
+
+    /// Stops watching every file, for when the scene is unloaded.
+    pub fn clear(&mut self) {
+        self.modified.clear();
+    }
+
+    /// Returns every watched file that changed since it was last seen, checking at most once
+    /// every [`POLL_INTERVAL`].
+    pub fn poll(&mut self) -> Vec<PathBuf> {
+        if self.last_poll.elapsed() < POLL_INTERVAL {
+            return Vec::new();
+        }
+        self.last_poll = Instant::now();
+
+        let mut changed = Vec::new();
+        for (path, last_modified) in &mut self.modified {
+            let modified = modified_time(path);
+            if modified != *last_modified {
+                *last_modified = modified;
+                changed.push(path.clone());
+            }
+        }
+        changed
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|meta| meta.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A watcher that's due to check its files straight away.
+    fn due(watcher: &mut ScriptWatcher) {
+        watcher.last_poll = Instant::now() - POLL_INTERVAL;
+    }
+
+    fn touch(path: &Path, modified: SystemTime) {
+        std::fs::File::options()
+            .write(true)
+            .open(path)
+            .and_then(|file| file.set_modified(modified))
+            .unwrap();
+    }
+
+    #[test]
+    fn changed_scripts_are_reported_once() {
+        let dir = std::env::temp_dir().join(format!("redback-hot-reload-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("player.script");
+        std::fs::write(&script, b"fn update() {}").unwrap();
+        touch(&script, SystemTime::UNIX_EPOCH + Duration::from_secs(1));
+
+        let mut watcher = ScriptWatcher::default();
+        watcher.watch(&script);
+        due(&mut watcher);
+        assert!(watcher.poll().is_empty());
+
+        touch(&script, SystemTime::UNIX_EPOCH + Duration::from_secs(2));
+        // not due yet, so the change waits for the next check
+        assert!(watcher.poll().is_empty());
+        due(&mut watcher);
+        assert_eq!(watcher.poll(), std::slice::from_ref(&script));
+        due(&mut watcher);
+        assert!(watcher.poll().is_empty());
+
+        std::fs::remove_file(&script).unwrap();
+        due(&mut watcher);
+        assert_eq!(watcher.poll(), [script]);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn cleared_scripts_are_not_watched() {
+        let dir = std::env::temp_dir().join(format!("redback-hot-clear-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("enemy.script");
+
+        let mut watcher = ScriptWatcher::default();
+        watcher.watch(&script);
+        watcher.clear();
+        std::fs::write(&script, b"fn update() {}").unwrap();
+        due(&mut watcher);
+        assert!(watcher.poll().is_empty());
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,209 @@
+// Helpers for turning raw device input into something scripts can use.
+
+use std::collections::{HashMap, HashSet};
+
+use dropbear_engine::gilrs::{Button, GamepadId};
+use eucalyptus_core::input::InputState;
+
+/// How far a stick has to be pushed from the centre before it registers, as a fraction of its
+/// full range.
+pub const DEFAULT_STICK_DEAD_ZONE: f32 = 0.15;
+
+/// Applies a radial dead zone to a stick position.
+///
+/// Anything inside `dead_zone` snaps to the centre, and the rest of the range is rescaled so the
+/// output still goes smoothly from 0 to 1 instead of jumping as it leaves the dead zone.
+pub fn apply_dead_zone(x: f32, y: f32, dead_zone: f32) -> (f32, f32) {
+    let magnitude = (x * x + y * y).sqrt();
+    if magnitude <= dead_zone || magnitude == 0.0 {
+        return (0.0, 0.0);
+    }
+
+    let clamped = magnitude.min(1.0);
+    let scale = (clamped - dead_zone) / (1.0 - dead_zone) / magnitude;
+    (x * scale, y * scale)
+}
+
+/// How many pixels of mouse movement a second a right stick pushed all the way over stands in
+/// for.
+pub const DEFAULT_STICK_LOOK_SPEED: f64 = 800.0;
+
+/// How mouse movement and the right stick turn the camera, stored on the runtime entity from the
+/// package's settings.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LookSettings {
+    /// What mouse deltas are multiplied by before reaching the camera.
+    pub sensitivity: f64,
+    /// Whether moving the mouse up looks down.
+    pub invert_y: bool,
+    /// How many pixels of mouse movement a second the right stick stands in for at full tilt.
+    /// 0 turns stick look off.
+    pub stick_speed: f64,
+}
+
+impl Default for LookSettings {
+    fn default() -> Self {
+        Self {
+            sensitivity: 1.0,
+            invert_y: false,
+            stick_speed: DEFAULT_STICK_LOOK_SPEED,
+        }
+    }
+}
+
+impl LookSettings {
+    /// Scales a mouse delta, in pixels, into the delta the camera should turn by.
+    pub fn apply(&self, dx: f64, dy: f64) -> (f64, f64) {
+        let dy = if self.invert_y { -dy } else { dy };
+        (dx * self.sensitivity, dy * self.sensitivity)
+    }
+
+    /// The mouse delta, in pixels, that holding the right stick at `x`, `y` for `dt` seconds
+    /// is worth, before [`Self::apply`]. Pushing the stick up looks up, like moving the mouse up.
+    pub fn stick_delta(&self, x: f32, y: f32, dt: f64) -> (f64, f64) {
+        let distance = self.stick_speed * dt;
+        (x as f64 * distance, -y as f64 * distance)
+    }
+}
+
+/// Remembers which controller buttons and sticks are held, so they can be let go of when input
+/// goes missing (such as the window losing focus).
+#[derive(Debug, Default)]
+pub struct ControllerTracker {
+    buttons: HashSet<(GamepadId, Button)>,
+    /// Controllers whose sticks are away from the centre, as `(left, right)`.
+    sticks: HashSet<(GamepadId, bool)>,
+    /// Where each controller's right stick is, for camera look.
+    right_sticks: HashMap<GamepadId, (f32, f32)>,
+}
+
+impl ControllerTracker {
+    pub fn set_button(&mut self, id: GamepadId, button: Button, pressed: bool) {
+        if pressed {
+            self.buttons.insert((id, button));
+        } else {
+            self.buttons.remove(&(id, button));
+        }
+    }
+
+    pub fn set_stick(&mut self, id: GamepadId, left: bool, x: f32, y: f32) {
+        if x == 0.0 && y == 0.0 {
+            self.sticks.remove(&(id, left));
+        } else {
+            self.sticks.insert((id, left));
+        }
+        if !left {
+            self.right_sticks.insert(id, (x, y));
+        }
+    }
+
+    /// The right stick pushed furthest from the centre across every controller, which is the
+    /// one camera look follows.
+    pub fn look_stick(&self) -> (f32, f32) {
+        furthest_stick(self.right_sticks.values().copied())
+    }
+
+    /// Lets go of every held button and centres every stick.
+    pub fn release_all(&mut self, input: &mut InputState) {
+        self.right_sticks.clear();
+        for (id, button) in self.buttons.drain() {
+            input.controller_button_up(button, id);
+        }
+        for (id, left) in self.sticks.drain() {
+            if left {
+                input.left_stick_changed(0.0, 0.0, id);
+            } else {
+                input.right_stick_changed(0.0, 0.0, id);
+            }
+        }
+    }
+}
+
+/// The stick position out of `sticks` furthest from the centre, or the centre if there are none.
+fn furthest_stick(sticks: impl Iterator<Item = (f32, f32)>) -> (f32, f32) {
+    sticks
+        .max_by(|a, b| (a.0.hypot(a.1)).total_cmp(&b.0.hypot(b.1)))
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close((x, y): (f32, f32), (expected_x, expected_y): (f32, f32)) {
+        assert!(
+            (x - expected_x).abs() < 1e-5 && (y - expected_y).abs() < 1e-5,
+            "({}, {}) is not ({}, {})",
+            x,
+            y,
+            expected_x,
+            expected_y
+        );
+    }
+
+    #[test]
+    fn sticks_inside_the_dead_zone_are_centred() {
+        assert_eq!(apply_dead_zone(0.0, 0.0, 0.15), (0.0, 0.0));
+        assert_eq!(apply_dead_zone(0.1, -0.1, 0.15), (0.0, 0.0));
+    }
+
+    #[test]
+    fn dead_zone_is_rescaled_out_of_the_range() {
+        // just past the edge of the dead zone is barely pushed at all
+        let (x, y) = apply_dead_zone(0.16, 0.0, 0.15);
+        assert!(x > 0.0 && x < 0.02 && y == 0.0, "({}, {})", x, y);
+        assert_close(apply_dead_zone(0.575, 0.0, 0.15), (0.5, 0.0));
+        assert_close(apply_dead_zone(0.0, -1.0, 0.15), (0.0, -1.0));
+    }
+
+    #[test]
+    fn look_deltas_are_scaled_by_the_sensitivity() {
+        let settings = LookSettings {
+            sensitivity: 0.5,
+            ..Default::default()
+        };
+        assert_eq!(settings.apply(10.0, -4.0), (5.0, -2.0));
+    }
+
+    #[test]
+    fn inverting_y_only_flips_vertical_look() {
+        let settings = LookSettings {
+            invert_y: true,
+            ..Default::default()
+        };
+        assert_eq!(settings.apply(3.0, 7.0), (3.0, -7.0));
+    }
+
+    #[test]
+    fn sticks_past_their_range_are_clamped() {
+        let (x, y) = apply_dead_zone(1.0, 1.0, 0.15);
+        assert!((x.hypot(y) - 1.0).abs() < 1e-5);
+        assert_close((x, y), (0.70710677, 0.70710677));
+    }
+
+    #[test]
+    fn pushing_the_stick_up_looks_up() {
+        let settings = LookSettings {
+            stick_speed: 100.0,
+            ..Default::default()
+        };
+        assert_eq!(settings.stick_delta(1.0, 0.5, 0.5), (50.0, -25.0));
+        assert_eq!(settings.stick_delta(1.0, 1.0, 0.0), (0.0, 0.0));
+    }
+
+    #[test]
+    fn zero_stick_speed_turns_stick_look_off() {
+        let settings = LookSettings {
+            stick_speed: 0.0,
+            ..Default::default()
+        };
+        assert_eq!(settings.stick_delta(1.0, -1.0, 0.016), (0.0, 0.0));
+    }
+
+    #[test]
+    fn camera_look_follows_the_furthest_pushed_stick() {
+        let sticks = [(0.2, 0.0), (0.0, -0.9), (0.5, 0.5)];
+        assert_eq!(furthest_stick(sticks.into_iter()), (0.0, -0.9));
+        assert_eq!(furthest_stick(std::iter::empty()), (0.0, 0.0));
+    }
+}
@@ -0,0 +1,348 @@
+// Instanced drawing of entities that share a model.
+//
+// Every frame, the entities seen by the camera are grouped by model, their instances are written
+// into one shared buffer, and each group is drawn with a single instanced draw. Entities share a
+// model when they were loaded from the same path, see [`crate::model_cache`].
+//
+// Transparent entities still have to be drawn back to front, so only neighbours in that order
+// that share a model are merged. Entities are only batched with others culled the same way, as
+// each way has a pipeline of its own, in the same render layer and with the same material, which
+// is bound once for the whole batch.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    ops::Range,
+};
+
+use dropbear_engine::{
+    entity::{AdoptedEntity, InstanceRaw, Transform},
+    graphics::Graphics,
+    model::Model,
+    wgpu,
+};
+use glam::DVec3;
+use hecs::World;
+
+use crate::{
+    culling::{BoundingSphere, CullStats, Frustum},
+    draw_order::{self, RenderLayer, Transparent},
+    hierarchy::{self, GlobalTransform},
+    materials::Material,
+    pipeline::Culling,
+    visibility::{self, Enabled, Visible},
+};
+
+/// The size of an [`InstanceRaw`] in the instance buffer: its model matrix followed by its normal
+/// matrix.
+const INSTANCE_SIZE: usize = (16 + 9) * 4;
+
+/// A single instanced draw of one model.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawBatch {
+    /// One of the entities drawn, to get the model from.
+    pub entity: hecs::Entity,
+    /// The batch's instances in the shared instance buffer.
+    pub instances: Range<u32>,
+    /// How the batch's triangles are culled, picking the pipeline it's drawn with.
+    pub culling: Culling,
+    pub layer: RenderLayer,
+    pub material: Option<Material>,
+    pub transparent: bool,
+}
+
+/// Everything to draw this frame, worked out once so every pass the scene is drawn into (such as
+/// a screenshot's) draws the same thing.
+#[derive(Debug, Default)]
+pub struct DrawList {
+    /// Every layer's batches in the order they're drawn.
+    pub batches: Vec<DrawBatch>,
+    pub stats: CullStats,
+    instances: Vec<u8>,
+}
+
+/// An entity that passed culling, before it's been put into a batch.
+struct VisibleEntity {
+    entity: hecs::Entity,
+    model: *const Model,
+    culling: Culling,
+    layer: RenderLayer,
+    material: Option<Material>,
+    instance: InstanceRaw,
+}
+
+/// A layer's entities before they're batched.
+#[derive(Default)]
+struct LayerEntities {
+    opaque: Vec<VisibleEntity>,
+    transparent: Vec<(VisibleEntity, f64)>,
+}
+
+impl DrawList {
+    /// Culls the entities in `world` against `frustum` and batches the rest a layer at a time,
+    /// each layer's opaque entities first and then its transparent ones from furthest to nearest
+    /// `eye`.
+    pub fn collect(world: &World, frustum: &Frustum, eye: DVec3) -> Self {
+        let mut stats = CullStats::default();
+        let mut layers: BTreeMap<RenderLayer, LayerEntities> = BTreeMap::new();
+
+        let mut query = world.query::<(
+            &AdoptedEntity,
+            &Transform,
+            Option<&GlobalTransform>,
+            Option<&BoundingSphere>,
+            Option<&Culling>,
+            Option<&RenderLayer>,
+            Option<&Material>,
+            Option<&Enabled>,
+            Option<&Visible>,
+            Option<&Transparent>,
+        )>();
+        for (
+            entity_id,
+            (
+                entity,
+                transform,
+                global,
+                bounds,
+                culling,
+                layer,
+                material,
+                enabled,
+                visible,
+                transparent,
+            ),
+        ) in query.iter()
+        {
+            if !visibility::is_drawn(enabled, visible) {
+                continue;
+            }
+            let transform = hierarchy::world_transform(transform, global);
+            let (center, radius) = bounds.copied().unwrap_or_default().to_world(&transform);
+            if !frustum.intersects_sphere(center, radius) {
+                stats.culled += 1;
+                continue;
+            }
+            stats.drawn += 1;
+
+            let visible = VisibleEntity {
+                entity: entity_id,
+                model: std::ptr::from_ref(entity.model()),
+                culling: culling.copied().unwrap_or_default(),
+                layer: layer.copied().unwrap_or_default(),
+                material: material.cloned(),
+                instance: entity.instance.to_raw(),
+            };
+            let layer = layers.entry(visible.layer).or_default();
+            if transparent.is_some() {
+                let distance = transform.position.distance_squared(eye);
+                layer.transparent.push((visible, distance));
+            } else {
+                layer.opaque.push(visible);
+            }
+        }
+
+        let mut list = Self {
+            stats,
+            ..Default::default()
+        };
+        for mut layer in layers.into_values() {
+            for group in group_by_model(layer.opaque) {
+                list.push_batch(group, false);
+            }
+            draw_order::sort_back_to_front(&mut layer.transparent);
+            for run in runs_by_model(layer.transparent.into_iter().map(|(visible, _)| visible)) {
+                list.push_batch(run, true);
+            }
+        }
+        list
+    }
+
+    /// The instances of every batch, laid out as the instance buffer expects.
+    pub fn instance_bytes(&self) -> &[u8] {
+        &self.instances
+    }
+
+    fn push_batch(&mut self, entities: Vec<VisibleEntity>, transparent: bool) {
+        let Some(first) = entities.first() else {
+            return;
+        };
+        let start = (self.instances.len() / INSTANCE_SIZE) as u32;
+        let entity = first.entity;
+        let culling = first.culling;
+        let layer = first.layer;
+        let material = first.material.clone();
+        for visible in &entities {
+            write_instance(&mut self.instances, &visible.instance);
+        }
+        self.batches.push(DrawBatch {
+            entity,
+            instances: start..start + entities.len() as u32,
+            culling,
+            layer,
+            material,
+            transparent,
+        });
+    }
+}
+
+/// The buffer every batch's instances are written into, grown when a frame needs more room.
+#[derive(Default)]
+pub struct InstanceBuffer {
+    buffer: Option<wgpu::Buffer>,
+}
+
+impl InstanceBuffer {
+    /// Uploads `list`'s instances, recreating the buffer if they don't fit.
+    pub fn write(&mut self, graphics: &Graphics, list: &DrawList) {
+        let bytes = list.instance_bytes();
+        if bytes.is_empty() {
+            return;
+        }
+
+        let needed = bytes.len() as u64;
+        if self
+            .buffer
+            .as_ref()
+            .is_none_or(|buffer| buffer.size() < needed)
+        {
+            self.buffer = Some(
+                graphics
+                    .state
+                    .device
+                    .create_buffer(&wgpu::BufferDescriptor {
+                        label: Some("Batched Instance Buffer"),
+                        size: needed.next_power_of_two(),
+                        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                        mapped_at_creation: false,
+                    }),
+            );
+        }
+        if let Some(buffer) = &self.buffer {
+            graphics.state.queue.write_buffer(buffer, 0, bytes);
+        }
+    }
+
+    pub fn buffer(&self) -> Option<&wgpu::Buffer> {
+        self.buffer.as_ref()
+    }
+}
+
+/// Groups entities by model, culling and material, in the order each was first seen.
+fn group_by_model(entities: Vec<VisibleEntity>) -> Vec<Vec<VisibleEntity>> {
+    let mut groups: Vec<Vec<VisibleEntity>> = Vec::new();
+    let mut group_of_model: HashMap<_, usize> = HashMap::new();
+    for visible in entities {
+        let key = (visible.model, visible.culling, visible.material.clone());
+        match group_of_model.get(&key) {
+            Some(&index) => groups[index].push(visible),
+            None => {
+                group_of_model.insert(key, groups.len());
+                groups.push(vec![visible]);
+            }
+        }
+    }
+    groups
+}
+
+/// Splits entities into runs of neighbours sharing a model, culling and material, keeping their
+/// order.
+fn runs_by_model(entities: impl IntoIterator<Item = VisibleEntity>) -> Vec<Vec<VisibleEntity>> {
+    let mut runs: Vec<Vec<VisibleEntity>> = Vec::new();
+    for visible in entities {
+        match runs.last_mut() {
+            Some(run)
+                if run[0].model == visible.model
+                    && run[0].culling == visible.culling
+                    && run[0].material == visible.material =>
+            {
+                run.push(visible)
+            }
+            _ => runs.push(vec![visible]),
+        }
+    }
+    runs
+}
+
+fn write_instance(bytes: &mut Vec<u8>, instance: &InstanceRaw) {
+    for value in instance.model.iter().flatten() {
+        bytes.extend_from_slice(&value.to_ne_bytes());
+    }
+    for value in instance.normal.iter().flatten() {
+        bytes.extend_from_slice(&value.to_ne_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Entities drawing the models numbered in `models`, each spawned into `world`.
+    fn visible(world: &mut World, models: &[usize]) -> Vec<VisibleEntity> {
+        models
+            .iter()
+            .map(|model| VisibleEntity {
+                entity: world.spawn(()),
+                model: std::ptr::null::<Model>().wrapping_byte_add(*model),
+                culling: Culling::default(),
+                layer: RenderLayer::default(),
+                material: None,
+                instance: InstanceRaw {
+                    model: [[0.0; 4]; 4],
+                    normal: [[0.0; 3]; 3],
+                },
+            })
+            .collect()
+    }
+
+    fn entities(groups: &[Vec<VisibleEntity>]) -> Vec<Vec<hecs::Entity>> {
+        groups
+            .iter()
+            .map(|group| group.iter().map(|visible| visible.entity).collect())
+            .collect()
+    }
+
+    #[test]
+    fn entities_sharing_a_model_are_grouped_wherever_they_are() {
+        let mut world = World::new();
+        let models = visible(&mut world, &[1, 2, 1]);
+        let ids: Vec<hecs::Entity> = models.iter().map(|visible| visible.entity).collect();
+        assert_eq!(
+            entities(&group_by_model(models)),
+            [vec![ids[0], ids[2]], vec![ids[1]]]
+        );
+    }
+
+    #[test]
+    fn runs_only_merge_neighbours() {
+        let mut world = World::new();
+        let models = visible(&mut world, &[1, 1, 2, 1]);
+        let ids: Vec<hecs::Entity> = models.iter().map(|visible| visible.entity).collect();
+        assert_eq!(
+            entities(&runs_by_model(models)),
+            [vec![ids[0], ids[1]], vec![ids[2]], vec![ids[3]]]
+        );
+    }
+
+    #[test]
+    fn different_materials_arent_batched() {
+        let mut world = World::new();
+        let mut models = visible(&mut world, &[1, 1]);
+        models[1].material = Some(Material {
+            roughness: 1.0,
+            ..Default::default()
+        });
+        assert_eq!(group_by_model(models).len(), 2);
+    }
+
+    #[test]
+    fn instances_are_written_at_their_full_size() {
+        let mut bytes = Vec::new();
+        let instance = InstanceRaw {
+            model: [[1.0; 4]; 4],
+            normal: [[1.0; 3]; 3],
+        };
+        write_instance(&mut bytes, &instance);
+        write_instance(&mut bytes, &instance);
+        assert_eq!(bytes.len(), INSTANCE_SIZE * 2);
+    }
+}
@@ -0,0 +1,104 @@
+// Finding model entities by their label without going through the whole world.
+//
+// The index lives on the runtime entity as a `LabelIndex`, built from the scene's entities when it
+// loads. Looking entities up in the world skips indexed entities that have been despawned since.
+// It isn't updated after that, so entities scripts spawn later aren't found by their label.
+//
+// Labels don't have to be unique. When several entities share one, they're all kept in order of
+// entity id and the lowest id is the one a lookup gives back, which (unlike hecs iteration order)
+// doesn't change between runs.
+
+use std::collections::HashMap;
+
+use dropbear_engine::entity::AdoptedEntity;
+use hecs::World;
+
+/// The model entities in the world by label, each label's entities in order of entity id.
+#[derive(Debug, Clone, Default)]
+pub struct LabelIndex {
+    entities: HashMap<String, Vec<hecs::Entity>>,
+}
+
+impl LabelIndex {
+    /// Indexes every model entity in `world`.
+    pub fn build(world: &World) -> Self {
+        let mut index = Self::default();
+        for (entity, adopted) in world.query::<&AdoptedEntity>().iter() {
+            index.insert(adopted.label(), entity);
+        }
+        index
+    }
+
+    pub fn insert(&mut self, label: &str, entity: hecs::Entity) {
+        let entities = self.entities.entry(label.to_string()).or_default();
+        if let Err(at) = entities.binary_search_by_key(&entity.id(), |entity| entity.id()) {
+            entities.insert(at, entity);
+        }
+    }
+
+    /// The entity labelled `label`, the one with the lowest id if several are.
+    pub fn get(&self, label: &str) -> Option<hecs::Entity> {
+        self.get_all(label).first().copied()
+    }
+
+    /// Every entity labelled `label`, lowest id first.
+    pub fn get_all(&self, label: &str) -> &[hecs::Entity] {
+        self.entities.get(label).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Every entity labelled `label` that's still in `world`, lowest id first. Empty outside of a
+/// scene.
+///
+/// Only the entities the scene loaded with are indexed, so entities spawned since aren't found.
+pub fn find_all_by_label(world: &World, label: &str) -> Vec<hecs::Entity> {
+    let mut query = world.query::<&LabelIndex>();
+    let Some((_, index)) = query.iter().next() else {
+        return Vec::new();
+    };
+    index
+        .get_all(label)
+        .iter()
+        .copied()
+        .filter(|entity| world.contains(*entity))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_labels_are_kept_in_entity_id_order() {
+        let mut world = World::new();
+        let first = world.spawn(());
+        let second = world.spawn(());
+        let mut index = LabelIndex::default();
+        index.insert("crate", second);
+        index.insert("crate", first);
+        index.insert("crate", second);
+        assert_eq!(index.get_all("crate"), [first, second]);
+        assert_eq!(index.get("crate"), Some(first));
+        assert_eq!(index.get("barrel"), None);
+    }
+
+    #[test]
+    fn despawned_entities_are_skipped() {
+        let mut world = World::new();
+        let gone = world.spawn(());
+        let kept = world.spawn(());
+        let mut index = LabelIndex::default();
+        index.insert("crate", gone);
+        index.insert("crate", kept);
+        world.spawn((index,));
+        world.despawn(gone).unwrap();
+        assert_eq!(find_all_by_label(&world, "crate"), [kept]);
+    }
+
+    #[test]
+    fn nothing_is_found_without_an_index() {
+        let mut world = World::new();
+        world.spawn(());
+        assert!(find_all_by_label(&world, "crate").is_empty());
+    }
+}
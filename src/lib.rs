@@ -1,23 +1,2217 @@
-// This is just for android, as it uses a dynamic library to load itself. On top of that, it uses
-// logcat for logging instead of env_logger (as in PC). There might be some useful stuff in this
-// library later on, but for now, its useless...
+// The runtime itself, shared by the desktop executable in `main.rs` and android, which loads it as
+// a dynamic library. Android logs to logcat instead of the log file (as in PC), and has no dialogs
+// to show errors in, so they're reported to logcat too.
+
+mod args;
+mod audio;
+mod billboard;
+mod bindings;
+mod camera;
+mod compression;
+mod config;
+mod culling;
+mod cursor;
+mod debug_overlay;
+mod device_lost;
+mod draw_order;
+mod gpu;
+mod hierarchy;
+#[cfg(debug_assertions)]
+mod hot_reload;
+mod input;
+mod instancing;
+mod labels;
+mod lifecycle;
+mod lights;
+mod loading;
+mod logging;
+mod low_power;
+mod materials;
+mod model_cache;
+mod package;
+mod particles;
+mod pause;
+mod pipeline;
+mod post;
+mod preferences;
+mod raycast;
+mod saves;
+mod scenes;
+mod scheduler;
+mod screenshot;
+mod script_errors;
+mod shader_check;
+mod shadows;
+mod shutdown;
+mod single_instance;
+mod skybox;
+mod surface;
+mod text;
+mod time;
+mod transition;
+mod ui;
+mod validation;
+mod visibility;
+mod window_mode;
+
+use args::LaunchOptions;
+use audio::{Audio, Listener};
+use bindings::KeyBindings;
+use camera::{FieldOfView, FollowFallback, FollowSmoothing, FollowWarnings, Projection};
+use config::{LightSettings, PackageConfig, WindowMode};
+use culling::Frustum;
+use debug_overlay::DebugOverlay;
+use device_lost::DeviceLostSignal;
+use draw_order::{RenderLayer, Transparent};
+use dropbear_engine::lighting::LightComponent;
+use dropbear_engine::model::{DrawLight, DrawModel};
+use dropbear_engine::{
+    camera::Camera,
+    entity::{AdoptedEntity, Transform},
+    gilrs::{Button, GamepadId},
+    graphics::Graphics,
+    input::{Controller, Keyboard, Mouse},
+    lighting::{Light, LightManager},
+    scene::{Scene, SceneCommand},
+    wgpu::{Color, RenderPass, RenderPipeline},
+};
+use eucalyptus_core::{
+    camera::{CameraComponent, CameraFollowTarget},
+    input::InputState,
+    scripting::ScriptManager,
+    states::{RuntimeData, SceneConfig, ScriptComponent},
+};
+use glam::DVec3;
+use gpu::AdapterCheck;
+use hierarchy::GlobalTransform;
+use input::{ControllerTracker, LookSettings};
+use instancing::{DrawList, InstanceBuffer};
+use labels::LabelIndex;
+use lifecycle::Lifecycle;
+use lights::{LightKind, LightParams, LightTracker};
+use loading::{LoadState, LoadingScreen, StreamedAssets};
+use materials::{Material, MaterialBindings};
+use model_cache::ModelCache;
+use package::RuntimeError;
+use particles::ParticleRenderer;
+use pause::{MenuItem, PauseMenu};
+use pipeline::{Culling, PipelineOptions};
+use post::{PostProcess, PostSettings};
+use preferences::{GraphicsPreferences, VolumeChannel, VolumeSettings};
+use raycast::CursorRay;
+use scenes::SceneNames;
+use scheduler::{CriticalScript, ScriptScheduler, ScriptedEntity};
+use screenshot::{Capture, Screenshots};
+use script_errors::ScriptFailures;
+use shadows::{CastsShadows, ShadowMap};
+use shutdown::Shutdown;
+use skybox::Skybox;
+use std::{
+    cell::RefCell,
+    collections::{HashMap, HashSet},
+    panic::AssertUnwindSafe,
+    path::{Path, PathBuf},
+    rc::Rc,
+    sync::Arc,
+    time::Instant,
+};
+use surface::ResizeDebounce;
+use text::TextRenderer;
+use time::{FixedTimestep, FrameTime, PreviousTransform};
+use transition::{FadeOverlay, Transition};
+use ui::UiRenderer;
+use visibility::{Enabled, Visible};
+use window_mode::WindowModeState;
+use winit::{
+    dpi::PhysicalPosition, event::MouseButton, event_loop::ActiveEventLoop, keyboard::KeyCode,
+    window::Window,
+};
+
+// to be changed by user
+pub const APP_INFO: app_dirs2::AppInfo = app_dirs2::AppInfo {
+    name: "redback-runtime",
+    author: "4tkbytes",
+};
+
+// pub fn run_web() -> Result<(), Box<dyn std::error::Error>> {
+//     console_error_panic_hook::set_once();
+//     match run() {
+//         Ok(_) => {}
+//         Err(e) => {
+//             panic!("{}", e)
+//         }
+//     }
+//     Ok(())
+// }
+
+/// Runs the game, telling the player why if it fails to start. This is all the desktop
+/// executable does.
+pub fn start() {
+    logging::init();
+    // good to set this earlier on so we can catch any .eupak issues
+    dropbear_engine::panic::set_hook();
+
+    if let Err(e) = run() {
+        report_failure(&e);
+        // exiting skips destructors, so nothing else gets the chance to flush
+        logging::flush();
+        std::process::exit(1);
+    }
+    log::info!("Exiting...");
+    logging::flush();
+}
 
 #[cfg(target_os = "android")]
-#[no_mangle]
+#[unsafe(no_mangle)]
 pub extern "C" fn android_main() {
     #[cfg(debug_assertions)]
+    unsafe {
+        std::env::set_var("RUST_BACKTRACE", "full");
+    }
+    let level = if cfg!(debug_assertions) {
+        log::LevelFilter::Trace
+    } else {
+        log::LevelFilter::Info
+    };
+    android_logger::init_once(android_logger::Config::default().with_max_level(level));
+
+    let runtime = std::thread::spawn(run);
+
+    // finish the activity on failure rather than leaving the player looking at a frozen view
+    match runtime.join() {
+        Ok(Ok(())) => {}
+        Ok(Err(e)) => {
+            report_failure(&e);
+            std::process::exit(1);
+        }
+        Err(_) => {
+            log::error!(target: "redback-runtime::fatal", "Runtime thread panicked");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// The scene games start in unless told otherwise.
+pub const DEFAULT_SCENE: &str = "Default";
+
+fn run() -> anyhow::Result<()> {
+    let options = LaunchOptions::parse(std::env::args().skip(1))?;
+    let current_exe = std::env::current_exe()?;
+    let project_name = project_name_from_exe(&current_exe)?;
+    let exe_dir = current_exe
+        .parent()
+        .ok_or_else(|| anyhow::anyhow!("Failed to get executable directory"))?;
+    let init_eupak_path = match options.eupak_override() {
+        Some(path) => {
+            log::info!("Using the package override at {}", path.display());
+            path
+        }
+        None => eupak_path(exe_dir, &project_name),
+    };
+
+    log::info!("Loading runtime data from: {}", init_eupak_path.display());
+
+    let (mut package_config, content) = package::load_package(&init_eupak_path)?;
+    // held until the game exits, before anything that could touch the shared files
+    let _instance_lock = if package_config.window.single_instance() {
+        single_instance::guard()?
+    } else {
+        None
+    };
+    // applied before the window is created, so the player's resolution and mode are used from
+    // the very first frame
+    preferences::load::<GraphicsPreferences>(GraphicsPreferences::FILE_NAME)
+        .apply(&mut package_config);
+    let low_power = options.low_power || package_config.graphics.low_power();
+    if low_power {
+        log::info!("Rendering in low-power mode, without MSAA or shadows");
+        low_power::apply(&mut package_config);
+        low_power::request_adapter();
+    }
+
+    let missing = validation::find_missing_assets(&content.scene_data);
+    if !missing.is_empty() {
+        for asset in &missing {
+            log::error!("Missing asset: {}", asset);
+        }
+        return Err(RuntimeError::MissingAssets(missing).into());
+    }
+
+    let oversized = validation::find_oversized_scenes(&content.scene_data, &package_config.limits);
+    if !oversized.is_empty() {
+        for scene in &oversized {
+            log::error!("Oversized scene: {}", scene);
+        }
+        return Err(RuntimeError::TooManyEntities(oversized).into());
+    }
+
+    log::info!("Loaded {} scenes", content.scene_data.len());
+
+    let start_scene = match options.scene {
+        Some(scene) => {
+            if !content
+                .scene_data
+                .iter()
+                .any(|data| data.scene_name == scene)
+            {
+                let scenes = content
+                    .scene_data
+                    .iter()
+                    .map(|data| data.scene_name.clone())
+                    .collect();
+                return Err(RuntimeError::UnknownScene { scene, scenes }.into());
+            }
+            log::info!(
+                "Starting in scene '{}' as asked for on the command line",
+                scene
+            );
+            scene
+        }
+        None => package_config
+            .saves
+            .resume_slot
+            .as_deref()
+            .and_then(saves::saved_scene)
+            .filter(|scene| {
+                content
+                    .scene_data
+                    .iter()
+                    .any(|data| &data.scene_name == scene)
+            })
+            .inspect(|scene| log::info!("Resuming in scene '{}'", scene))
+            .unwrap_or_else(|| DEFAULT_SCENE.to_string()),
+    };
+
+    log::debug!("Runtime Data: {:#?}", content);
+
+    match gpu::check_adapter(low_power::power_preference(low_power)) {
+        AdapterCheck::Found(info) => log::info!(
+            "Rendering with {} ({:?}, {:?})",
+            info.name,
+            info.backend,
+            info.device_type
+        ),
+        AdapterCheck::Missing => return Err(RuntimeError::NoGpuAdapter.into()),
+        AdapterCheck::Unknown => log::debug!("Leaving the GPU adapter check to the engine"),
+    }
+
+    let config = package_config.window.to_window_configuration(&project_name);
+
+    dropbear_engine::run_app!(config, |sm, im| {
+        setup_from_runtime_data(sm, im, content, package_config, start_scene)
+    })
+    .unwrap();
+
+    Ok(())
+}
+
+/// Tells the player why the game failed to start.
+fn report_failure(e: &anyhow::Error) {
+    match e.downcast_ref::<RuntimeError>() {
+        Some(error) => show_error_dialog(
+            "Error loading game",
+            error.dialog_message(&logging::log_location()),
+        ),
+        None => show_error_dialog(
+            "Error starting game",
+            format!("{}\n\nPlease report this to the game developer!", e),
+        ),
+    }
+}
+
+/// Logs an error report and shows it to the player in a dialog, where dialogs are available.
+fn show_error_dialog(title: &str, description: String) {
+    // logcat cuts off long entries, so the report goes out a line at a time
+    for line in error_report(title, &description).lines() {
+        log::error!(target: "redback-runtime::fatal", "{}", line);
+    }
+
+    #[cfg(not(target_os = "android"))]
     {
-        unsafe {
-            std::env::set_var("RUST_BACKTRACE", "full");
+        rfd::MessageDialog::new()
+            .set_title(title)
+            .set_description(description)
+            .set_buttons(rfd::MessageButtons::Ok)
+            .set_level(rfd::MessageLevel::Error)
+            .show();
+    }
+}
+
+/// The contents of an error dialog as plain text, for the log.
+fn error_report(title: &str, description: &str) -> String {
+    format!("FATAL: {}\n\n{}", title, description)
+}
+
+/// Derives the project name from the runtime executable's path.
+///
+/// On Windows the `.exe` suffix is stripped, everywhere else the file name is used as-is, as
+/// executables don't carry an extension there.
+fn project_name_from_exe(exe: &Path) -> anyhow::Result<String> {
+    let file_name = exe
+        .file_name()
+        .ok_or_else(|| anyhow::anyhow!("Unable to get file name"))?
+        .to_string_lossy();
+
+    if cfg!(target_os = "windows") {
+        Ok(file_name
+            .strip_suffix(".exe")
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Unable to strip suffix while fetching the executable's name: {}",
+                    file_name
+                )
+            })?
+            .to_string())
+    } else {
+        Ok(file_name.to_string())
+    }
+}
+
+/// The `.eupak` package that sits next to the executable for a given project.
+fn eupak_path(exe_dir: &Path, project_name: &str) -> PathBuf {
+    exe_dir.join(format!("{}.eupak", project_name))
+}
+
+struct RuntimeScene {
+    config: PackageConfig,
+    /// The first scene loaded, which is [`DEFAULT_SCENE`] unless `--scene` says otherwise.
+    start_scene: String,
+    scene_data: HashMap<String, SceneConfig>,
+    current_scene_name: String,
+    world: hecs::World,
+    script_manager: ScriptManager,
+    light_manager: LightManager,
+    light_tracker: LightTracker,
+    /// Only set while the scene has render pipelines.
+    light_params: Option<LightParams>,
+    follow_warnings: FollowWarnings,
+    scene_command: SceneCommand,
+    shutdown: Shutdown,
+    input_state: InputState,
+    controllers: ControllerTracker,
+    audio: Audio,
+    /// Models loaded by this or earlier scenes, shared by every entity using them.
+    model_cache: ModelCache,
+    /// The player's graphics preferences, already applied to `config`.
+    graphics_preferences: GraphicsPreferences,
+    transition: Transition,
+    render_pipeline: Option<RenderPipeline>,
+    /// Entity pipelines for the ways of culling the scene's models use, and whether they're
+    /// depth tested, other than the default `render_pipeline` is created with.
+    culled_pipelines: HashMap<(Culling, bool), RenderPipeline>,
+    /// Only set when the device can draw wireframes.
+    wireframe_pipeline: Option<RenderPipeline>,
+    /// Only set while the depth pre-pass is enabled.
+    depth_pipeline: Option<RenderPipeline>,
+    /// Whether entities are drawn as wireframes, toggled for debugging.
+    wireframe: bool,
+    skybox: Option<Skybox>,
+    particle_renderer: Option<ParticleRenderer>,
+    shadow_map: Option<ShadowMap>,
+    /// The bind groups of entities' materials, made against `shadow_map` so they go with it.
+    materials: Option<MaterialBindings>,
+    /// Only set while post-processing is enabled.
+    post_process: Option<PostProcess>,
+    /// The instances of every batch drawn this frame.
+    instance_buffer: InstanceBuffer,
+    fade_overlay: Option<FadeOverlay>,
+    ui_renderer: Option<UiRenderer>,
+    text_renderer: Option<TextRenderer>,
+    pause_menu: PauseMenu,
+    window: Option<Arc<Window>>,
+    /// Whether the window had focus last frame.
+    focused: bool,
+    window_mode: WindowModeState,
+    resize: ResizeDebounce,
+    active_camera: Option<hecs::Entity>,
+    load_state: LoadState,
+    /// The loading screen's image, if the package has one.
+    loading_screen: Option<LoadingScreen>,
+    lifecycle: Lifecycle,
+    device_lost: DeviceLostSignal,
+    /// Files streamed in for the scene being loaded, used up as the scene loads.
+    streamed_assets: StreamedAssets,
+    clear_color: Color,
+    /// The scene's ambient light, scaled by its intensity.
+    ambient: [f32; 3],
+    paused: bool,
+    screenshots: Screenshots,
+    /// The clock scripts read through [`FrameTime`], kept here so it outlives the scene.
+    frame_time: FrameTime,
+    script_failures: ScriptFailures,
+    script_scheduler: ScriptScheduler,
+    /// Only set when the package asks for a fixed timestep.
+    fixed_timestep: Option<FixedTimestep>,
+    debug_overlay: DebugOverlay,
+    bindings: KeyBindings,
+    /// Holds the runtime's own components (such as the label index) in the world.
+    runtime_entity: Option<hecs::Entity>,
+    #[cfg(debug_assertions)]
+    script_watcher: hot_reload::ScriptWatcher,
+}
+
+impl RuntimeScene {
+    fn new(runtime_data: RuntimeData, config: PackageConfig, start_scene: String) -> Self {
+        let mut scene_data = HashMap::new();
+        for data in &runtime_data.scene_data {
+            scene_data.insert(data.scene_name.clone(), data.clone());
+        }
+
+        Self {
+            bindings: KeyBindings::with_overrides(&config.bindings),
+            window_mode: WindowModeState::new(config.window.mode()),
+            resize: ResizeDebounce::default(),
+            graphics_preferences: preferences::load(GraphicsPreferences::FILE_NAME),
+            transition: Transition::default(),
+            instance_buffer: InstanceBuffer::default(),
+            fade_overlay: None,
+            ui_renderer: None,
+            text_renderer: None,
+            pause_menu: PauseMenu::default(),
+            model_cache: ModelCache::new(config.graphics.model_cache_budget()),
+            audio: Audio::new(&config.audio, preferences::load(VolumeSettings::FILE_NAME)),
+            fixed_timestep: config.timing.fixed_timestep().map(FixedTimestep::new),
+            config,
+            start_scene,
+            scene_data,
+            current_scene_name: String::new(),
+            world: hecs::World::new(),
+            script_manager: ScriptManager::new().unwrap(),
+            light_manager: LightManager::new(),
+            light_tracker: LightTracker::default(),
+            light_params: None,
+            follow_warnings: FollowWarnings::default(),
+            scene_command: SceneCommand::None,
+            shutdown: Shutdown::default(),
+            input_state: InputState::new(),
+            controllers: ControllerTracker::default(),
+            render_pipeline: None,
+            culled_pipelines: HashMap::new(),
+            wireframe_pipeline: None,
+            depth_pipeline: None,
+            wireframe: false,
+            skybox: None,
+            particle_renderer: None,
+            shadow_map: None,
+            materials: None,
+            post_process: None,
+            window: None,
+            focused: true,
+            active_camera: None,
+            load_state: LoadState::Ready,
+            loading_screen: None,
+            lifecycle: Lifecycle::default(),
+            device_lost: DeviceLostSignal::default(),
+            streamed_assets: StreamedAssets::default(),
+            clear_color: config::SceneSettings::default().clear_color(),
+            ambient: config::SceneSettings::default().ambient(),
+            paused: false,
+            screenshots: Screenshots::default(),
+            frame_time: FrameTime::default(),
+            script_failures: ScriptFailures::default(),
+            script_scheduler: ScriptScheduler::default(),
+            debug_overlay: DebugOverlay::default(),
+            runtime_entity: None,
+            #[cfg(debug_assertions)]
+            script_watcher: hot_reload::ScriptWatcher::default(),
+        }
+    }
+
+    fn load_scene(
+        &mut self,
+        graphics: &mut Graphics,
+        scene_name: impl Into<String>,
+    ) -> anyhow::Result<()> {
+        let scene_name: String = scene_name.into();
+
+        Self::unload_scripts(
+            &self.world,
+            &mut self.script_manager,
+            &mut self.script_failures,
+            &mut self.script_scheduler,
+        );
+        self.world.clear();
+        self.follow_warnings.clear();
+
+        let scene = self
+            .scene_data
+            .get(&scene_name)
+            .ok_or_else(|| anyhow::anyhow!("Unable to fetch scene config: Returned \"None\""))?;
+
+        let settings = self.config.scene(&scene_name);
+        self.clear_color = settings.clear_color();
+        self.ambient = settings.ambient();
+        self.light_tracker.mark_dirty();
+        // entity ids start over in the new world, so they can't be matched up with old sounds
+        self.audio.stop_sources();
+        match &settings.music {
+            Some(music) => self.audio.play_music(music),
+            None => self.audio.stop_music(),
+        }
+
+        // the scene's own models are loaded through the cache, leaving its cameras and lights to
+        // the scene config
+        let mut scene = scene.clone();
+        let entities = std::mem::take(&mut scene.entities);
+        let camera = scene.load_into_world(&mut self.world, graphics)?;
+        for element in &settings.hud {
+            self.world.spawn((element.clone(),));
+        }
+        for text in &settings.text {
+            self.world.spawn((text.clone(),));
+        }
+        let mut parents = Vec::new();
+        for entity in entities {
+            let model =
+                self.model_cache
+                    .get_or_load(graphics, &entity.model_path, Some(&entity.label))?;
+            let mut adopted = AdoptedEntity::adopt(graphics, model, Some(&entity.label));
+            adopted.update(graphics, &entity.transform);
+            let entity_id = self
+                .world
+                .spawn((adopted, entity.transform, entity.properties));
+            if let Some(script) = entity.script {
+                self.world.insert_one(entity_id, script)?;
+            }
+            if let Some(model) = settings.models.get(&entity.label) {
+                self.world
+                    .insert(entity_id, (model.culling(), model.layer()))?;
+                if let Some(material) = model.material() {
+                    self.world.insert_one(entity_id, material)?;
+                }
+                if model.transparent() {
+                    self.world.insert_one(entity_id, Transparent)?;
+                }
+                if let Some(visible) = model.visible {
+                    self.world.insert_one(entity_id, Visible(visible))?;
+                }
+                if let Some(enabled) = model.enabled {
+                    self.world.insert_one(entity_id, Enabled(enabled))?;
+                }
+                if let Some(source) = &model.audio_source {
+                    self.world.insert_one(entity_id, source.clone())?;
+                }
+                if let Some(particles) = &model.particles {
+                    self.world.insert_one(entity_id, particles.emitter())?;
+                }
+                if let Some(bounds) = model.bounding_box() {
+                    self.world.insert_one(entity_id, bounds)?;
+                }
+                if let Some(billboard) = model.billboard {
+                    self.world.insert_one(entity_id, billboard)?;
+                }
+                if model.critical_script == Some(true) {
+                    self.world.insert_one(entity_id, CriticalScript)?;
+                }
+                if let Some(parent) = &model.parent {
+                    parents.push((entity_id, entity.label.clone(), parent));
+                }
+            }
+        }
+        // the previous scene's entities are gone by now, so its models can be evicted
+        self.model_cache.trim();
+
+        let shadows = self.config.graphics.shadows();
+        let shadow_casters: Vec<hecs::Entity> = self
+            .world
+            .query::<&Light>()
+            .iter()
+            .filter(|(_, light)| {
+                shadows
+                    && settings
+                        .lights
+                        .get(light.label())
+                        .and_then(|light| light.casts_shadows)
+                        .unwrap_or(false)
+            })
+            .map(|(entity, _)| entity)
+            .collect();
+        for entity in shadow_casters {
+            self.world.insert_one(entity, CastsShadows)?;
+        }
+        let light_settings: Vec<(hecs::Entity, LightSettings)> = self
+            .world
+            .query::<&Light>()
+            .iter()
+            .filter_map(|(entity, light)| {
+                Some((entity, settings.lights.get(light.label())?.clone()))
+            })
+            .collect();
+        for (entity, light) in light_settings {
+            if let Some(attenuation) = light.attenuation() {
+                self.world.insert_one(entity, attenuation)?;
+            }
+            if let Some(kind) = light.kind {
+                self.world.insert_one(entity, LightKind::from(kind))?;
+            }
+        }
+        camera::set_active_camera(&mut self.world, camera)?;
+        if let Some(smoothing) = settings.camera.follow_smoothing() {
+            self.world.insert_one(camera, smoothing)?;
+        }
+        if let Some(fallback) = settings.camera.follow_fallback() {
+            self.world.insert_one(camera, fallback)?;
+        }
+        if let Some(projection) = settings.camera.projection {
+            self.world.insert_one(camera, projection)?;
+        }
+        if let Some(fov) = settings.camera.field_of_view() {
+            self.world.insert_one(camera, fov)?;
+        }
+        self.active_camera = Some(camera);
+        let label_index = LabelIndex::build(&self.world);
+        for (child, label, parent_label) in parents {
+            let Some(parent) = label_index.get(parent_label) else {
+                log::warn!(
+                    "'{}' is attached to '{}', but no entity has that label",
+                    label,
+                    parent_label
+                );
+                continue;
+            };
+            if let Err(e) = hierarchy::set_parent(&mut self.world, child, parent) {
+                log::warn!("Unable to attach '{}' to '{}': {}", label, parent_label, e);
+            }
+        }
+        hierarchy::update_global_transforms(&mut self.world);
+        self.runtime_entity = Some(self.world.spawn((
+            self.frame_time,
+            self.config.input.look_settings(),
+            CursorRay::default(),
+            label_index,
+        )));
+
+        #[cfg(debug_assertions)]
+        self.script_watcher.clear();
+
+        let mut script_entities: Vec<(hecs::Entity, ScriptComponent)> = Vec::new();
+        for (entity_id, script) in self.world.query::<&ScriptComponent>().iter() {
+            script_entities.push((entity_id, script.clone()));
+        }
+
+        let script_count = script_entities.len();
+        let mut failed_scripts = Vec::new();
+        for (entity_id, script) in script_entities {
+            #[cfg(debug_assertions)]
+            self.script_watcher.watch(&script.path);
+
+            if let Err(e) = self.load_entity_script(entity_id, &script) {
+                failed_scripts.push(format!(
+                    "  - '{}' on entity {:?}: {}",
+                    script.name, entity_id, e
+                ));
+            }
+        }
+        if !failed_scripts.is_empty() {
+            log::error!(
+                "{} of {} scripts in scene '{}' failed to load and were disabled:\n{}",
+                failed_scripts.len(),
+                script_count,
+                scene_name,
+                failed_scripts.join("\n")
+            );
+        }
+
+        self.streamed_assets = StreamedAssets::default();
+        if let Some(slot) = &self.config.saves.resume_slot {
+            saves::save(slot, scene_name.as_bytes());
+        }
+        self.current_scene_name = scene_name;
+
+        // re-applied so the window's cursor matches, even if the scene asks for a different mode
+        let cursor_locked = settings
+            .cursor_locked
+            .unwrap_or(self.input_state.is_cursor_locked);
+        self.set_cursor_locked(cursor_locked);
+        self.input_state.mouse_delta = None;
+        Ok(())
+    }
+
+    fn alt_held(&self) -> bool {
+        self.input_state.pressed_keys.contains(&KeyCode::AltLeft)
+            || self.input_state.pressed_keys.contains(&KeyCode::AltRight)
+    }
+
+    /// Turns the active camera by a mouse delta, in pixels, scaled by the look settings.
+    fn mouse_look(&mut self, dx: f64, dy: f64) {
+        let look_settings = self.runtime_component::<LookSettings>().unwrap_or_default();
+        let (dx, dy) = look_settings.apply(dx, dy);
+        if let Some(active_camera) = self.active_camera
+            && let Ok(mut query) = self
+                .world
+                .query_one::<(&mut Camera, &CameraComponent)>(active_camera)
+            && let Some((camera, _component)) = query.get()
+        {
+            camera.track_mouse_delta(dx, dy);
+        }
+    }
+
+    /// Turns the active camera with the right stick, as if the mouse had moved for `dt` seconds.
+    fn stick_look(&mut self, dt: f64) {
+        let (x, y) = self.controllers.look_stick();
+        if x == 0.0 && y == 0.0 {
+            return;
+        }
+        let look_settings = self.runtime_component::<LookSettings>().unwrap_or_default();
+        let (dx, dy) = look_settings.stick_delta(x, y, dt);
+        self.mouse_look(dx, dy);
+    }
+
+    /// Casts the ray under the cursor from the active camera, for picking entities with. While
+    /// the cursor is locked it sits in the middle of the window, so the ray goes through the
+    /// crosshair.
+    fn update_cursor_ray(&mut self, graphics: &Graphics) {
+        let config = &graphics.state.config;
+        let ray = self.active_camera.and_then(|active_camera| {
+            let mut query = self
+                .world
+                .query_one::<(&Camera, Option<&Projection>)>(active_camera)
+                .ok()?;
+            let (camera, projection) = query.get()?;
+            raycast::screen_ray(
+                camera::view_proj(camera, projection),
+                self.input_state.mouse_pos,
+                (config.width, config.height),
+            )
+        });
+        if let Some(runtime_entity) = self.runtime_entity
+            && let Ok(cursor_ray) = self.world.query_one_mut::<&mut CursorRay>(runtime_entity)
+        {
+            cursor_ray.0 = ray;
+        }
+    }
+
+    /// Where the active camera is, if there is one.
+    fn active_camera_eye(&self) -> Option<DVec3> {
+        let camera = self.world.get::<&Camera>(self.active_camera?).ok()?;
+        Some(camera.eye)
+    }
+
+    /// A copy of one of the runtime entity's components.
+    fn runtime_component<T: hecs::Component + Copy>(&self) -> Option<T> {
+        let runtime_entity = self.runtime_entity?;
+        self.world
+            .get::<&T>(runtime_entity)
+            .ok()
+            .map(|component| *component)
+    }
+
+    /// Locks or frees the cursor, hiding it and moving it to the middle of the window while
+    /// locked.
+    fn set_cursor_locked(&mut self, locked: bool) {
+        self.input_state.is_cursor_locked = locked;
+        self.input_state.lock_cursor(locked);
+        if let Some(window) = &self.window {
+            cursor::grab(window, locked);
+            window.set_cursor_visible(!locked);
+            if locked {
+                let size = window.inner_size();
+                let center =
+                    PhysicalPosition::new(size.width as f64 / 2.0, size.height as f64 / 2.0);
+                let _ = window.set_cursor_position(center);
+                self.input_state.mouse_pos = (center.x, center.y);
+            }
+        }
+    }
+
+    /// Loads an entity's script from disk and initialises it. A script that fails (or panics)
+    /// along the way is disabled, leaving the entity itself in the scene.
+    fn load_entity_script(
+        &mut self,
+        entity_id: hecs::Entity,
+        script: &ScriptComponent,
+    ) -> anyhow::Result<()> {
+        let result = self.try_load_entity_script(entity_id, script);
+        if result.is_err() {
+            self.script_manager.remove_entity_script(entity_id);
+            self.script_failures.disable(entity_id, &script.name);
+        }
+        result
+    }
+
+    fn try_load_entity_script(
+        &mut self,
+        entity_id: hecs::Entity,
+        script: &ScriptComponent,
+    ) -> anyhow::Result<()> {
+        let source = self
+            .streamed_assets
+            .take(&script.path)
+            .map_or_else(|| std::fs::read(&script.path), Ok)
+            .map_err(|e| anyhow::anyhow!("unable to read {}: {}", script.path.display(), e))?;
+
+        let file_name = script
+            .path
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_else(|| script.name.clone());
+        let script_name = self
+            .script_manager
+            .load_script(&file_name, source)
+            .map_err(|e| anyhow::anyhow!("unable to load: {}", e))?;
+
+        // a panic in one script's initialisation shouldn't take the rest of the scene with it
+        let init = std::panic::catch_unwind(AssertUnwindSafe(|| {
+            self.script_manager.init_entity_script(
+                entity_id,
+                &script_name,
+                &mut self.world,
+                &self.input_state,
+            )
+        }));
+        match init {
+            Ok(Ok(_)) => Ok(()),
+            Ok(Err(e)) => Err(anyhow::anyhow!("unable to initialise: {}", e)),
+            Err(panic) => Err(anyhow::anyhow!(
+                "panicked while initialising: {}",
+                script_errors::panic_message(panic.as_ref())
+            )),
+        }
+    }
+
+    /// Reloads the scripts of every entity using a script file that changed on disk. The
+    /// entities' components are left alone, only the script state starts over.
+    #[cfg(debug_assertions)]
+    fn reload_changed_scripts(&mut self) {
+        for path in self.script_watcher.poll() {
+            let mut script_entities: Vec<(hecs::Entity, ScriptComponent)> = Vec::new();
+            for (entity_id, script) in self.world.query::<&ScriptComponent>().iter() {
+                if script.path == path {
+                    script_entities.push((entity_id, script.clone()));
+                }
+            }
+
+            for (entity_id, script) in script_entities {
+                log::info!(
+                    "Reloading script '{}' for entity {:?} as {} changed",
+                    script.name,
+                    entity_id,
+                    path.display()
+                );
+                self.script_manager.remove_entity_script(entity_id);
+                self.script_failures.forget(entity_id);
+                if let Err(e) = self.load_entity_script(entity_id, &script) {
+                    log::warn!("Failed to reload script '{}': {}", script.name, e);
+                }
+            }
+        }
+    }
+
+    /// Shuts the game down and tells the engine to quit.
+    fn quit(&mut self) {
+        self.shut_down();
+        self.scene_command = SceneCommand::Quit;
+    }
+
+    /// Gets the game ready for the process to end, however it's quitting: scripts are torn down
+    /// and the logs are flushed. Only the first call does anything.
+    fn shut_down(&mut self) {
+        self.shutdown.run(log::logger(), || {
+            Self::unload_scripts(
+                &self.world,
+                &mut self.script_manager,
+                &mut self.script_failures,
+                &mut self.script_scheduler,
+            )
+        });
+    }
+
+    /// Tears down the script state of every scripted entity in `world`.
+    fn unload_scripts(
+        world: &hecs::World,
+        script_manager: &mut ScriptManager,
+        script_failures: &mut ScriptFailures,
+        script_scheduler: &mut ScriptScheduler,
+    ) {
+        for (entity_id, _) in world.query::<&ScriptComponent>().iter() {
+            script_manager.remove_entity_script(entity_id);
+        }
+        script_failures.clear();
+        script_scheduler.clear();
+    }
+
+    /// Creates the entity, light, skybox and particle pipelines for the active camera of the
+    /// loaded scene.
+    fn create_render_pipelines(&mut self, graphics: &mut Graphics) -> anyhow::Result<()> {
+        // MSAA and the depth pre-pass only work on the offscreen target, as the engine's surface
+        // pass is single sampled and clears its own depth buffer
+        let post_processing = &self.config.post_processing;
+        let sample_count = match self.config.graphics.msaa_samples() {
+            1 => 1,
+            requested => pipeline::sample_count(graphics, post::HDR_FORMAT, requested),
+        };
+        let depth_prepass = self.config.graphics.depth_prepass();
+        let offscreen = post_processing.enabled() || sample_count > 1 || depth_prepass;
+        self.post_process = offscreen.then(|| {
+            PostProcess::new(
+                graphics,
+                PostSettings {
+                    tonemap: post_processing.enabled(),
+                    exposure: post_processing.exposure(),
+                    sample_count,
+                },
+            )
+        });
+        let options = PipelineOptions {
+            color_format: if self.post_process.is_some() {
+                post::HDR_FORMAT
+            } else {
+                graphics.state.config.format
+            },
+            sample_count,
+        };
+
+        self.skybox = match &self.config.scene(&self.current_scene_name).skybox {
+            Some(settings) => Some(Skybox::new(graphics, &settings.faces, &options)?),
+            None => None,
+        };
+        self.particle_renderer = Some(ParticleRenderer::new(graphics, &options));
+
+        self.light_manager.create_light_array_resources(graphics);
+        self.light_tracker.mark_dirty();
+        let texture_bind_group = graphics.texture_bind_group().clone();
+        let light_params = LightParams::new(graphics);
+        let shadow_map = ShadowMap::new(graphics, light_params.buffer());
+
+        let active_camera = self.active_camera.ok_or_else(|| {
+            anyhow::anyhow!("Scene '{}' has no active camera", self.current_scene_name)
+        })?;
+        let mut query = self
+            .world
+            .query_one::<&Camera>(active_camera)
+            .map_err(|e| anyhow::anyhow!("Unable to query active camera entity: {}", e))?;
+        let camera = query.get().ok_or_else(|| {
+            anyhow::anyhow!(
+                "Active camera entity {:?} has no camera component",
+                active_camera
+            )
+        })?;
+
+        let layouts = [
+            &texture_bind_group,
+            camera.layout(),
+            self.light_manager.layout(),
+            shadow_map.layout(),
+        ];
+        let pipeline = pipeline::create_model_pipeline(
+            graphics,
+            "redback_runtime_default",
+            include_str!("shader.wgsl"),
+            &layouts,
+            &options,
+            Culling::default().primitive_state(),
+            true,
+        );
+        self.render_pipeline = Some(pipeline);
+
+        let cullings: HashSet<(Culling, bool)> = self
+            .world
+            .query::<(Option<&Culling>, Option<&RenderLayer>)>()
+            .iter()
+            .map(|(_, (culling, layer))| {
+                let layer = layer.copied().unwrap_or_default();
+                let depth_tested = self.config.graphics.depth_test(layer);
+                (culling.copied().unwrap_or_default(), depth_tested)
+            })
+            .filter(|key| *key != (Culling::default(), true))
+            .collect();
+        self.culled_pipelines = cullings
+            .into_iter()
+            .map(|(culling, depth_tested)| {
+                let pipeline = pipeline::create_model_pipeline(
+                    graphics,
+                    &format!("redback_runtime_{:?}_{}", culling, depth_tested),
+                    include_str!("shader.wgsl"),
+                    &layouts,
+                    &options,
+                    culling.primitive_state(),
+                    depth_tested,
+                );
+                ((culling, depth_tested), pipeline)
+            })
+            .collect();
+
+        let wireframe_supported = graphics
+            .state
+            .device
+            .features()
+            .contains(dropbear_engine::wgpu::Features::POLYGON_MODE_LINE);
+        self.depth_pipeline = depth_prepass.then(|| {
+            pipeline::create_depth_pipeline(
+                graphics,
+                "redback_runtime_depth",
+                include_str!("shader.wgsl"),
+                &layouts,
+                &options,
+            )
+        });
+        self.wireframe_pipeline = wireframe_supported.then(|| {
+            pipeline::create_model_pipeline(
+                graphics,
+                "redback_runtime_wireframe",
+                include_str!("shader.wgsl"),
+                &layouts,
+                &options,
+                pipeline::wireframe_primitive_state(),
+                true,
+            )
+        });
+        self.shadow_map = Some(shadow_map);
+        self.materials = Some(MaterialBindings::default());
+        self.light_params = Some(light_params);
+
+        let light_layout = pipeline::light_bind_group_layout(graphics);
+        self.light_manager.pipeline = Some(pipeline::create_model_pipeline(
+            graphics,
+            "Light Pipeline",
+            include_str!("light.wgsl"),
+            &[camera.layout(), &light_layout],
+            &options,
+            Culling::default().primitive_state(),
+            true,
+        ));
+        self.fade_overlay = Some(FadeOverlay::new(graphics));
+        self.ui_renderer = Some(UiRenderer::new(graphics));
+        self.text_renderer = Some(TextRenderer::new(
+            graphics,
+            self.config.text.font.as_deref(),
+        ));
+        Ok(())
+    }
+
+    /// Puts up the loading screen, after which the current scene is unloaded and `scene_name` is
+    /// loaded in its place. The current scene is left untouched if `scene_name` doesn't exist.
+    fn switch_scene(&mut self, scene_name: &str) -> anyhow::Result<()> {
+        if !self.scene_data.contains_key(scene_name) {
+            return Err(anyhow::anyhow!(
+                "Unable to switch to scene '{}' as it does not exist in this package",
+                scene_name
+            ));
+        }
+
+        log::info!(
+            "Switching scene from '{}' to '{}'",
+            self.current_scene_name,
+            scene_name
+        );
+        self.load_state = LoadState::begin(scene_name);
+        Ok(())
+    }
+
+    /// Loads the pending scene, its camera and its render pipelines once its files have been
+    /// streamed in.
+    ///
+    /// A scene that fails to load is never entered. The player is told about it and the runtime
+    /// goes back to the scene it came from, or shows an empty screen if there is none.
+    fn finish_loading(&mut self, graphics: &mut Graphics) {
+        let scene_data = &self.scene_data;
+        let Some((scene_name, assets)) = self.load_state.poll(|scene| {
+            scene_data
+                .get(scene)
+                .map(loading::scene_script_paths)
+                .unwrap_or_default()
+        }) else {
+            return;
+        };
+        self.streamed_assets = assets;
+        let previous_scene = self.current_scene_name.clone();
+
+        let result = self
+            .load_scene(graphics, &scene_name)
+            .and_then(|_| self.create_render_pipelines(graphics));
+        if let Err(e) = result {
+            log::error!("Failed to load scene '{}': {}", scene_name, e);
+            self.render_pipeline = None;
+            self.culled_pipelines.clear();
+            self.wireframe_pipeline = None;
+            self.depth_pipeline = None;
+            self.skybox = None;
+            self.particle_renderer = None;
+            self.shadow_map = None;
+            self.materials = None;
+            self.light_params = None;
+            self.post_process = None;
+            self.ui_renderer = None;
+            self.text_renderer = None;
+            self.active_camera = None;
+            show_error_dialog(
+                "Error loading scene",
+                format!(
+                    "The scene '{}' could not be loaded: {}\n\nPlease report this to the game developer!",
+                    scene_name, e
+                ),
+            );
+
+            if !previous_scene.is_empty() && previous_scene != scene_name {
+                log::info!("Returning to scene '{}'", previous_scene);
+                self.load_state = LoadState::begin(previous_scene);
+            }
+        }
+    }
+
+    /// Runs scripts and moves cameras and entities along for the frame. Skipped while paused.
+    ///
+    /// The scripted entities are looked up once and updated for every fixed step, then the
+    /// cameras and particles are moved along in one pass each, and a last pass uploads every
+    /// entity's transform.
+    fn update_gameplay(&mut self, dt: f32, graphics: &mut Graphics) {
+        let (steps, script_dt, interpolation) = match &mut self.fixed_timestep {
+            Some(fixed) => (fixed.advance(dt), fixed.step(), Some(fixed.alpha())),
+            None => (1, dt, None),
+        };
+        if let Some(runtime_entity) = self.runtime_entity
+            && let Ok(frame_time) = self.world.query_one_mut::<&mut FrameTime>(runtime_entity)
+        {
+            frame_time.delta = script_dt;
+            frame_time.interpolation = interpolation.unwrap_or(0.0);
+        }
+
+        // the scripted entities are looked up once a frame rather than for every step
+        let script_entities = self.scripted_entities();
+        for _ in 0..steps {
+            if self.fixed_timestep.is_some() {
+                self.snapshot_transforms();
+            }
+            self.update_scripts(&script_entities, script_dt);
+        }
+
+        self.stick_look(dt as f64);
+        self.update_cameras(dt, graphics);
+        particles::update(
+            &mut self.world,
+            dt as f64,
+            self.config.limits.max_particles(),
+        );
+        if let Some(eye) = self.active_camera_eye() {
+            billboard::face_camera(&mut self.world, eye);
+        }
+        hierarchy::update_global_transforms(&mut self.world);
+
+        let query = self.world.query_mut::<(
+            &mut AdoptedEntity,
+            &Transform,
+            Option<&GlobalTransform>,
+            Option<&PreviousTransform>,
+        )>();
+        for (_, (entity, transform, global, previous)) in query {
+            // children are drawn wherever their parents are this frame, without interpolating
+            match (interpolation, global, previous) {
+                (_, Some(global), _) => entity.update(graphics, &global.0),
+                (Some(alpha), None, Some(previous)) => {
+                    let shown = time::interpolate(&previous.0, transform, alpha as f64);
+                    entity.update(graphics, &shown);
+                }
+                _ => entity.update(graphics, transform),
+            }
+        }
+    }
+
+    /// Remembers every entity's transform before a fixed step, for rendering to blend from.
+    fn snapshot_transforms(&mut self) {
+        let missing: Vec<(hecs::Entity, Transform)> = self
+            .world
+            .query::<&Transform>()
+            .without::<&PreviousTransform>()
+            .iter()
+            .map(|(entity, transform)| (entity, *transform))
+            .collect();
+        for (entity, transform) in missing {
+            let _ = self.world.insert_one(entity, PreviousTransform(transform));
+        }
+
+        for (_, (transform, previous)) in self
+            .world
+            .query_mut::<(&Transform, &mut PreviousTransform)>()
+        {
+            previous.0 = *transform;
+        }
+    }
+
+    /// The enabled entities with a script, along with the script's name.
+    fn scripted_entities(&self) -> Vec<ScriptedEntity> {
+        self.world
+            .query::<(&ScriptComponent, Option<&Enabled>, Option<&CriticalScript>)>()
+            .iter()
+            .filter(|(entity_id, (_, enabled, _))| {
+                visibility::is_updated(*enabled) && !self.script_failures.is_disabled(*entity_id)
+            })
+            .map(|(entity_id, (script, _, critical))| ScriptedEntity {
+                entity: entity_id,
+                script: script.name.clone(),
+                critical: critical.is_some(),
+            })
+            .collect()
+    }
+
+    /// Runs the scripts of `script_entities` for `dt` seconds, or as many of them as the cap
+    /// on scripts per update allows.
+    fn update_scripts(&mut self, script_entities: &[ScriptedEntity], dt: f32) {
+        let disable_after = self.config.scripts.disable_after_failures();
+        let scheduled = self.script_scheduler.schedule(
+            script_entities,
+            dt,
+            self.config.scripts.max_per_update(),
+        );
+        for (scripted, dt) in scheduled {
+            let entity_id = &scripted.entity;
+            let script_name = &scripted.script;
+            match self.script_manager.update_entity_script(
+                *entity_id,
+                script_name,
+                &mut self.world,
+                &self.input_state,
+                dt,
+            ) {
+                Ok(_) => self.script_failures.record_success(*entity_id),
+                Err(e) => self.script_failures.record(
+                    *entity_id,
+                    script_name,
+                    &e.to_string(),
+                    Instant::now(),
+                    disable_after,
+                ),
+            }
+        }
+    }
+
+    /// Moves following cameras along, picks the active camera and uploads the lights it sees.
+    /// Every camera is moved and uploaded in the same pass over them.
+    fn update_cameras(&mut self, dt: f32, graphics: &mut Graphics) {
+        let config = &graphics.state.config;
+        let aspect = camera::aspect_ratio(config.width, config.height);
+        for (_entity_id, (camera, follow_target, smoothing, fallback, projection, fov)) in self
+            .world
+            .query::<(
+                &mut Camera,
+                Option<&CameraFollowTarget>,
+                Option<&FollowSmoothing>,
+                Option<&FollowFallback>,
+                Option<&Projection>,
+                Option<&FieldOfView>,
+            )>()
+            .iter()
+        {
+            if let Some(target) = follow_target {
+                if let Some(target_pos) = camera::find_follow_target(
+                    &self.world,
+                    &target.follow_target,
+                    &mut self.follow_warnings,
+                ) {
+                    let lerp_speed = smoothing.map_or(0.0, |smoothing| smoothing.lerp_speed);
+                    camera.eye = camera::follow_step(
+                        camera.eye,
+                        target_pos + target.offset,
+                        lerp_speed,
+                        dt as f64,
+                    );
+                    camera.target =
+                        camera::follow_step(camera.target, target_pos, lerp_speed, dt as f64);
+                } else if let Some(fallback) = fallback {
+                    camera.eye = fallback.eye;
+                    camera.target = fallback.target;
+                }
+            }
+
+            camera::apply_lens(camera, aspect, fov);
+            camera.update(graphics);
+            camera::upload_projection(graphics, camera, projection);
+        }
+
+        let active_camera = camera::resolve_active_camera(&mut self.world, self.active_camera);
+        if active_camera != self.active_camera {
+            log::debug!(
+                "Active camera changed from {:?} to {:?}",
+                self.active_camera,
+                active_camera
+            );
+            self.active_camera = active_camera;
+        }
+
+        let camera_eye = self.active_camera_eye().unwrap_or_default();
+        if self.light_tracker.refresh(&self.world, camera_eye) {
+            log::debug!("Lighting entities with {:?}", self.light_tracker.selected());
+            if let Some(light_params) = &self.light_params {
+                light_params.upload(
+                    graphics,
+                    &self.world,
+                    self.ambient,
+                    self.light_tracker.selected(),
+                );
+            }
+        }
+    }
+
+    /// Switches entities between being drawn normally and as wireframes. Devices that can't draw
+    /// wireframes keep drawing normally.
+    fn toggle_wireframe(&mut self) {
+        if !self.wireframe && self.render_pipeline.is_some() && self.wireframe_pipeline.is_none() {
+            log::warn!("Unable to draw wireframes as the device doesn't support line polygons");
+            return;
         }
-        android_logger::init_once(
-            android_logger::Config::default().with_max_level(log::Level::Trace.to_level_filter()),
+        self.wireframe = !self.wireframe;
+        log::info!(
+            "Wireframe rendering {}",
+            if self.wireframe { "on" } else { "off" }
         );
     }
 
-    std::thread::spawn(|| {
-        if let Err(e) = run() {
-            log::error!("Runtime failed: {:?}", e);
+    /// Pauses or resumes the game.
+    fn set_paused(&mut self, paused: bool) {
+        log::info!("{}", if paused { "Paused" } else { "Resumed" });
+        self.paused = paused;
+
+        if !self.config.pause_menu.enabled() {
+            return;
+        }
+        // the cursor is let go while the menu is up, so its buttons can be clicked
+        if paused {
+            self.pause_menu.open(self.input_state.is_cursor_locked);
+            if self.input_state.is_cursor_locked {
+                self.set_cursor_locked(false);
+            }
+        } else if self.pause_menu.close() {
+            self.set_cursor_locked(true);
+        }
+    }
+
+    fn pause_menu_open(&self) -> bool {
+        self.paused && self.config.pause_menu.enabled()
+    }
+
+    /// Shows the entity under the cursor in the debug overlay.
+    fn pick_entity(&mut self) {
+        self.debug_overlay.picked = raycast::pick(&self.world, f64::INFINITY).and_then(|hit| {
+            let entity = self.world.get::<&AdoptedEntity>(hit.entity).ok()?;
+            Some((entity.label().to_string(), hit.distance))
+        });
+    }
+
+    /// The pause menu button under the cursor, if the menu is open.
+    fn menu_item_under_cursor(&self) -> Option<MenuItem> {
+        if !self.pause_menu_open() {
+            return None;
+        }
+        let size = self.window.as_ref()?.inner_size();
+        self.pause_menu
+            .item_at(self.input_state.mouse_pos, size.width, size.height)
+    }
+
+    /// Does what the pause menu's `item` button is there for.
+    fn press_menu_item(&mut self, item: MenuItem) {
+        match item {
+            MenuItem::Resume => self.set_paused(false),
+            MenuItem::Volume(channel) => {
+                let full = self.audio.volumes().volume(channel) >= 1.0;
+                self.change_volume(channel, if full { -VolumeSettings::STEPS } else { 1 });
+            }
+            MenuItem::Quit => {
+                log::info!("Quitting from the pause menu");
+                self.shut_down();
+            }
+        }
+        if let Some(command) = item.scene_command() {
+            self.scene_command = command;
+        }
+    }
+
+    /// Turns the selected pause menu button's volume up by `steps`, or down for negative steps.
+    fn step_selected_volume(&mut self, steps: i32) {
+        if let MenuItem::Volume(channel) = self.pause_menu.selected() {
+            self.change_volume(channel, steps);
+        }
+    }
+
+    /// Turns the volume of `channel` up by `steps`, or down for negative steps, saving it in the
+    /// player's preferences.
+    fn change_volume(&mut self, channel: VolumeChannel, steps: i32) {
+        let mut volumes = self.audio.volumes();
+        volumes.step(channel, steps);
+        if volumes == self.audio.volumes() {
+            return;
+        }
+        self.audio.set_volumes(volumes);
+        preferences::save(VolumeSettings::FILE_NAME, &volumes);
+    }
+
+    /// Switches the window to `mode`, saving it in the player's graphics preferences so the
+    /// game starts in it next time.
+    fn set_window_mode(&mut self, mode: WindowMode) {
+        if mode == self.window_mode.current() {
+            return;
+        }
+
+        if let Some(window) = &self.window {
+            window_mode::apply(window, mode, self.config.window.resolution());
+            self.window_mode.set(mode);
+            self.graphics_preferences.mode = Some(mode);
+            preferences::save(GraphicsPreferences::FILE_NAME, &self.graphics_preferences);
+        }
+    }
+
+    /// Drops the pipelines and buffers the runtime created on the GPU, which are created again
+    /// by [`Self::create_render_pipelines`].
+    fn release_gpu_resources(&mut self) {
+        self.render_pipeline = None;
+        self.culled_pipelines.clear();
+        self.wireframe_pipeline = None;
+        self.depth_pipeline = None;
+        self.skybox = None;
+        self.particle_renderer = None;
+        self.shadow_map = None;
+        self.materials = None;
+        self.light_params = None;
+        self.post_process = None;
+        self.fade_overlay = None;
+        self.ui_renderer = None;
+        self.text_renderer = None;
+        self.light_manager.pipeline = None;
+    }
+
+    /// Halts the game once the GPU device is gone and quits, as there's no device left to draw
+    /// with. Everything stops where it is rather than the next frame crashing on a lost device.
+    fn lose_device(&mut self, message: &str) {
+        log::error!("The GPU device was lost: {}", message);
+        self.lifecycle.lose_device();
+        self.release_gpu_resources();
+        self.release_input();
+        show_error_dialog(
+            "Graphics device lost",
+            format!(
+                "The graphics device stopped responding ({}), so the game has to close.\n\nRestarting the game usually fixes this. If it keeps happening, try updating your graphics drivers.",
+                message
+            ),
+        );
+        self.quit();
+    }
+
+    /// Catches up with the window gaining or losing focus.
+    fn focus_changed(&mut self, focused: bool) {
+        if focused {
+            // the OS gives the cursor back while the window isn't focused, so grab it again
+            self.set_cursor_locked(self.input_state.is_cursor_locked);
+        } else {
+            // anything held when focus went away never gets its release event
+            self.release_input();
+        }
+    }
+
+    /// Lets go of every key and button that is currently held.
+    fn release_input(&mut self) {
+        self.input_state.pressed_keys.clear();
+        self.input_state.mouse_button.clear();
+        self.controllers.release_all(&mut self.input_state);
+    }
+
+    /// Loads the current scene again from its config, behind the loading screen like any other
+    /// switch. Scripts are torn down and initialised from scratch, and every entity goes back to
+    /// where the scene puts it. The window stays as it is, and models come out of the cache.
+    fn reload_scene(&mut self) {
+        let scene_name = self.current_scene_name.clone();
+        log::info!("Reloading scene '{}'", scene_name);
+        if let Err(e) = self.switch_scene(&scene_name) {
+            log::error!("Failed to reload scene '{}': {}", scene_name, e);
+        }
+    }
+
+    /// Fades over to the scene `offset` places on from the current one in alphabetical order,
+    /// wrapping around at either end.
+    fn cycle_scene(&mut self, offset: isize) {
+        let names = SceneNames::new(self.scene_data.keys().cloned());
+        let Some(scene_name) = names
+            .cycle(&self.current_scene_name, offset)
+            .map(str::to_string)
+        else {
+            return;
+        };
+        self.transition.fade_out(scene_name);
+    }
+
+    /// Fills the depth buffer with the depth of `draw_list`'s opaque batches using
+    /// `depth_pipeline`, returning the amount of draw calls made. Batches culled some other way
+    /// than the default are left to the scene pass, as the depth pipeline only culls back faces.
+    fn draw_depth<'a>(
+        &'a self,
+        render_pass: &mut RenderPass<'a>,
+        depth_pipeline: &'a RenderPipeline,
+        camera: &'a Camera,
+        draw_list: &DrawList,
+        scene: &'a SceneDraws<'_, '_>,
+    ) -> usize {
+        let (Some(shadow_map), Some(instance_buffer)) =
+            (&self.shadow_map, self.instance_buffer.buffer())
+        else {
+            return 0;
+        };
+        render_pass.set_pipeline(depth_pipeline);
+        render_pass.set_bind_group(3, shadow_map.bind_group(), &[]);
+        render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+
+        let mut draw_calls = 0;
+        // layers that aren't depth tested are drawn over the pre-pass's depth anyway
+        for batch in &draw_list.batches {
+            if batch.transparent
+                || batch.culling != Culling::default()
+                || !self.config.graphics.depth_test(batch.layer)
+            {
+                continue;
+            }
+            let Some(entity) = scene.entities.get(batch.entity) else {
+                continue;
+            };
+            render_pass.draw_model_instanced(
+                entity.model(),
+                batch.instances.clone(),
+                camera.bind_group(),
+                self.light_manager.bind_group(),
+            );
+            draw_calls += 1;
+        }
+        draw_calls
+    }
+
+    /// Draws the UI and then the text over the scene already in `render_pass`, returning the
+    /// amount of draw calls made.
+    fn draw_ui(&self, render_pass: &mut RenderPass<'_>) -> usize {
+        let mut draw_calls = self
+            .ui_renderer
+            .as_ref()
+            .map_or(0, |ui_renderer| ui_renderer.draw(render_pass));
+        if let Some(text_renderer) = &self.text_renderer
+            && text_renderer.draw(render_pass)
+        {
+            draw_calls += 1;
+        }
+        draw_calls
+    }
+
+    /// Draws the skybox, lights, `draw_list`'s batches and particles into `render_pass` as seen
+    /// by `camera`, returning the amount of draw calls made.
+    fn draw_scene<'a>(
+        &'a self,
+        render_pass: &mut RenderPass<'a>,
+        camera: &'a Camera,
+        draw_list: &DrawList,
+        scene: &'a SceneDraws<'_, '_>,
+    ) -> usize {
+        let mut draw_calls = 0;
+        let (Some(pipeline), Some(shadow_map)) = (&self.render_pipeline, &self.shadow_map) else {
+            return draw_calls;
+        };
+
+        if let Some(skybox) = &self.skybox {
+            skybox.draw(render_pass);
+            draw_calls += 1;
+        }
+
+        if let Some(light_pipeline) = &self.light_manager.pipeline {
+            render_pass.set_pipeline(light_pipeline);
+            for (_, (light, component, kind)) in &scene.lights {
+                // directional lights aren't anywhere, so there's nowhere to draw them
+                if component.enabled && kind.is_none_or(LightKind::has_model) {
+                    render_pass
+                        .set_vertex_buffer(1, light.instance_buffer.as_ref().unwrap().slice(..));
+                    render_pass.draw_light_model(
+                        light.model(),
+                        camera.bind_group(),
+                        light.bind_group(),
+                    );
+                    draw_calls += 1;
+                }
+            }
+        }
+
+        // entities are lit by the nearest enabled light, or the engine's own light without one
+        let light_bind_group = self
+            .light_tracker
+            .selected()
+            .first()
+            .and_then(|selected| scene.lights.iter().find(|(entity, _)| entity == selected))
+            .map_or(self.light_manager.bind_group(), |(_, (light, _, _))| {
+                light.bind_group()
+            });
+
+        // wireframes are drawn for every entity however it's culled or layered
+        let wireframe = self.wireframe_pipeline.as_ref().filter(|_| self.wireframe);
+        render_pass.set_pipeline(wireframe.unwrap_or(pipeline));
+        render_pass.set_bind_group(3, shadow_map.bind_group(), &[]);
+
+        if let Some(instance_buffer) = self.instance_buffer.buffer() {
+            render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+            let mut current = (Culling::default(), true);
+            let mut current_material: Option<&Material> = None;
+            for batch in &draw_list.batches {
+                let Some(entity) = scene.entities.get(batch.entity) else {
+                    continue;
+                };
+                if batch.material.as_ref() != current_material {
+                    current_material = batch.material.as_ref();
+                    let bind_group = self
+                        .materials
+                        .as_ref()
+                        .and_then(|materials| materials.bind_group(current_material))
+                        .unwrap_or(shadow_map.bind_group());
+                    render_pass.set_bind_group(3, bind_group, &[]);
+                }
+                // entities that were given a culling or layer after the pipelines were created
+                // are drawn with the default pipeline instead
+                let key = (batch.culling, self.config.graphics.depth_test(batch.layer));
+                if key != current && wireframe.is_none() {
+                    current = key;
+                    render_pass
+                        .set_pipeline(self.culled_pipelines.get(&current).unwrap_or(pipeline));
+                }
+                render_pass.draw_model_instanced(
+                    entity.model(),
+                    batch.instances.clone(),
+                    camera.bind_group(),
+                    light_bind_group,
+                );
+                draw_calls += 1;
+            }
+        }
+
+        // blended over the models, so they have to come last
+        if let Some(particle_renderer) = &self.particle_renderer
+            && particle_renderer.draw(render_pass)
+        {
+            draw_calls += 1;
+        }
+
+        draw_calls
+    }
+}
+
+/// The entities and lights a frame's passes draw, borrowed from the world before the passes begin
+/// so they can be drawn from for as long as the passes are open.
+struct SceneDraws<'a, 'w> {
+    entities: hecs::View<'a, &'w AdoptedEntity>,
+    lights: Vec<(hecs::Entity, LightDraw<'a>)>,
+}
+
+/// A light's components, as the scene pass draws its cube with them.
+type LightDraw<'a> = (&'a Light, &'a LightComponent, Option<&'a LightKind>);
+
+fn setup_from_runtime_data(
+    mut scene_manager: dropbear_engine::scene::Manager,
+    mut input_manager: dropbear_engine::input::Manager,
+    runtime_data: RuntimeData,
+    package_config: PackageConfig,
+    start_scene: String,
+) -> (
+    dropbear_engine::scene::Manager,
+    dropbear_engine::input::Manager,
+) {
+    let runtime_scene = Rc::new(RefCell::new(RuntimeScene::new(
+        runtime_data,
+        package_config,
+        start_scene,
+    )));
+
+    dropbear_engine::scene::add_scene_with_input(
+        &mut scene_manager,
+        &mut input_manager,
+        runtime_scene,
+        "runtime_game",
+    );
+
+    scene_manager.switch("runtime_game");
+
+    (scene_manager, input_manager)
+}
+
+impl Scene for RuntimeScene {
+    fn load(&mut self, graphics: &mut SharedGraphicsContext) {
+        if let Some(pacing) = self.config.window.frame_pacing {
+            surface::apply_present_mode(graphics, surface::present_mode(pacing));
+        }
+        self.window = Some(graphics.state.window.clone());
+
+        if let Err(e) = shader_check::check(&graphics.state.device) {
+            log::error!("Shaders failed to compile on this device: {}", e);
+            show_error_dialog(
+                "Unsupported graphics device",
+                format!(
+                    "This game's shaders could not be compiled for your graphics device:\n\n{}\n\nPlease report this to the game developer!",
+                    e
+                ),
+            );
+            self.quit();
+            return;
+        }
+
+        self.loading_screen = LoadingScreen::new(graphics, &self.config.loading_screen);
+        // the scene itself is loaded in `update`, after the loading screen has been shown
+        self.load_state = LoadState::begin(self.start_scene.clone());
+    }
+
+    fn update(&mut self, dt: f32, graphics: &mut Graphics) {
+        // the scripts are gone once shut down, in the frames before the engine quits
+        if self.lifecycle.is_halted() || self.shutdown.is_done() {
+            return;
+        }
+
+        if self.load_state.is_loading() {
+            self.finish_loading(graphics);
+            return;
+        }
+
+        if !self.input_state.is_cursor_locked
+            && let Some(window) = &self.window
+        {
+            window.set_cursor_visible(true);
+        }
+
+        // the engine doesn't pass focus changes on, so they're picked up from the window
+        if let Some(focused) = self.window.as_ref().map(|window| window.has_focus())
+            && focused != self.focused
+        {
+            self.focused = focused;
+            self.focus_changed(focused);
+        }
+
+        #[cfg(debug_assertions)]
+        self.reload_changed_scripts();
+
+        let raw_dt = dt;
+        self.debug_overlay.script_failures = self.script_failures.total();
+        if self.debug_overlay.is_visible()
+            && self.debug_overlay.record_frame(raw_dt, self.world.len())
+        {
+            self.debug_overlay.show(self.window.as_ref());
+        }
+
+        let dt = time::clamp_delta_time(raw_dt, self.config.timing.max_delta_time());
+        let run_gameplay = !self.paused;
+        self.frame_time.advance(dt, raw_dt, run_gameplay);
+        if let Some(runtime_entity) = self.runtime_entity
+            && let Ok(frame_time) = self.world.query_one_mut::<&mut FrameTime>(runtime_entity)
+        {
+            *frame_time = self.frame_time;
         }
-    });
+
+        let fade_duration = self.config.transition.duration();
+        if let Some(scene_name) = self.transition.advance(dt, fade_duration)
+            && let Err(e) = self.switch_scene(&scene_name)
+        {
+            log::error!("Failed to switch to scene '{}': {}", scene_name, e);
+        }
+
+        if run_gameplay {
+            self.update_cursor_ray(graphics);
+            self.update_gameplay(dt, graphics);
+        }
+
+        self.input_state.mouse_delta = None;
+
+        self.audio.update(dt);
+        let listener = self.active_camera.and_then(|camera| {
+            let camera = self.world.get::<&Camera>(camera).ok()?;
+            Some(Listener::looking_at(camera.eye, camera.target, camera.up))
+        });
+        self.audio.update_sources(&self.world, listener);
+    }
+
+    fn render(&mut self, graphics: &mut SharedGraphicsContext) {
+        if self.lifecycle.is_halted() {
+            return;
+        }
+        self.device_lost.watch(&graphics.state.device);
+        if let Some(message) = self.device_lost.take() {
+            self.lose_device(&message);
+            return;
+        }
+
+        self.window = Some(graphics.state.window.clone());
+        self.screenshots.poll(graphics);
+        // the surface, offscreen targets and camera aspect ratios all follow the surface size,
+        // so they're updated on the first frame after the resizing settles
+        self.resize
+            .watch(graphics.state.window.inner_size(), Instant::now());
+        if let Some(size) = self.resize.settled(Instant::now()) {
+            surface::resize(graphics, size);
+        }
+
+        if self.load_state.is_loading() {
+            if let Some(loading_screen) = &self.loading_screen {
+                loading_screen.update(graphics);
+            }
+            {
+                let mut render_pass =
+                    graphics.clear_colour(self.config.loading_screen.clear_color());
+                if let Some(loading_screen) = &self.loading_screen {
+                    loading_screen.draw(&mut render_pass);
+                }
+            }
+            self.load_state.mark_frame_shown();
+            return;
+        }
+
+        if self.render_pipeline.is_none()
+            || self.shadow_map.is_none()
+            || self.active_camera.is_none()
+        {
+            // nothing loaded successfully, so there's nothing to draw
+            let _ = graphics.clear_colour(self.clear_color);
+            return;
+        }
+
+        let Some(active_camera) = self.active_camera else {
+            return;
+        };
+
+        if let Some(post_process) = &mut self.post_process {
+            post_process.prepare(graphics);
+        }
+        if let Some(shadow_map) = &mut self.shadow_map {
+            shadow_map.update(graphics, shadows::shadow_caster(&self.world));
+            shadow_map.render(
+                graphics,
+                self.world
+                    .query::<(&AdoptedEntity, Option<&Enabled>, Option<&Visible>)>()
+                    .iter()
+                    .filter(|(_, (_, enabled, visible))| visibility::is_drawn(*enabled, *visible))
+                    .map(|(_, (entity, _, _))| entity),
+            );
+            if let Some(materials) = &mut self.materials {
+                materials.prepare(graphics, &self.world, shadow_map);
+            }
+        }
+
+        let Ok(mut query) = self
+            .world
+            .query_one::<(&Camera, Option<&Projection>)>(active_camera)
+        else {
+            return;
+        };
+        let Some((camera, projection)) = query.get() else {
+            return;
+        };
+        if let Some(skybox) = &self.skybox {
+            skybox.update(graphics, camera);
+        }
+        let view_proj = camera::view_proj(camera, projection);
+        let frustum = Frustum::from_view_proj(view_proj);
+        let draw_list = DrawList::collect(&self.world, &frustum, camera.eye);
+        self.instance_buffer.write(graphics, &draw_list);
+        if let Some(particle_renderer) = &mut self.particle_renderer {
+            particle_renderer.update(graphics, &self.world, camera, view_proj);
+        }
+        // the menu covers the game's own text, which would otherwise end up drawn over it
+        let (menu_elements, text) = if self.pause_menu_open() {
+            let config = &graphics.state.config;
+            self.pause_menu.layout(
+                &self.config.pause_menu,
+                &self.audio.volumes(),
+                config.width,
+                config.height,
+            )
+        } else {
+            (Vec::new(), text::queued(&self.world))
+        };
+        if let Some(ui_renderer) = &mut self.ui_renderer {
+            ui_renderer.update(graphics, &self.world, &menu_elements);
+        }
+        if let Some(text_renderer) = &mut self.text_renderer {
+            text_renderer.update(graphics, &text);
+        }
+
+        let mut entity_query = self.world.query::<&AdoptedEntity>();
+        let mut light_query = self
+            .world
+            .query::<(&Light, &LightComponent, Option<&LightKind>)>();
+        let scene = SceneDraws {
+            entities: entity_query.view(),
+            lights: light_query.iter().collect(),
+        };
+
+        let fade = self
+            .fade_overlay
+            .as_ref()
+            .filter(|_| self.transition.is_active());
+        if let Some(fade) = fade {
+            let opacity = self.transition.opacity(self.config.transition.duration());
+            fade.update(graphics, self.config.transition.color(), opacity);
+        }
+
+        let draw_calls = match &self.post_process {
+            Some(post_process) => {
+                let mut encoder = graphics.state.device.create_command_encoder(
+                    &dropbear_engine::wgpu::CommandEncoderDescriptor {
+                        label: Some("HDR Scene Encoder"),
+                    },
+                );
+                let mut drawn = 0;
+                if let Some(depth_pipeline) = &self.depth_pipeline {
+                    let mut render_pass = post_process.begin_depth_pass(&mut encoder);
+                    drawn += self.draw_depth(
+                        &mut render_pass,
+                        depth_pipeline,
+                        camera,
+                        &draw_list,
+                        &scene,
+                    );
+                }
+                {
+                    let mut render_pass = post_process.begin_scene_pass(
+                        &mut encoder,
+                        self.clear_color,
+                        self.depth_pipeline.is_some(),
+                    );
+                    drawn += self.draw_scene(&mut render_pass, camera, &draw_list, &scene);
+                }
+                graphics
+                    .state
+                    .queue
+                    .submit(std::iter::once(encoder.finish()));
+                let mut render_pass = graphics.clear_colour(Color::BLACK);
+                post_process.draw_resolve(&mut render_pass);
+                drawn += self.draw_ui(&mut render_pass);
+                if let Some(fade) = fade {
+                    fade.draw(&mut render_pass);
+                }
+                drawn
+            }
+            None => {
+                let mut render_pass = graphics.clear_colour(self.clear_color);
+                let drawn = self.draw_scene(&mut render_pass, camera, &draw_list, &scene)
+                    + self.draw_ui(&mut render_pass);
+                if let Some(fade) = fade {
+                    fade.draw(&mut render_pass);
+                }
+                drawn
+            }
+        };
+
+        if self.screenshots.take_request() {
+            let capture = Capture::new(graphics);
+            let mut encoder = graphics.state.device.create_command_encoder(
+                &dropbear_engine::wgpu::CommandEncoderDescriptor {
+                    label: Some("Screenshot Encoder"),
+                },
+            );
+            {
+                let mut render_pass = capture.begin_pass(&mut encoder, self.clear_color);
+                match &self.post_process {
+                    Some(post_process) => post_process.draw_resolve(&mut render_pass),
+                    None => {
+                        self.draw_scene(&mut render_pass, camera, &draw_list, &scene);
+                    }
+                }
+                self.draw_ui(&mut render_pass);
+                if let Some(fade) = fade {
+                    fade.draw(&mut render_pass);
+                }
+            }
+            self.screenshots.read_back(graphics, encoder, capture);
+        }
+        drop(scene);
+        drop(query);
+
+        self.debug_overlay.draw_calls = draw_calls;
+        self.debug_overlay.cull_stats = draw_list.stats;
+    }
+
+    // also where closing the window ends up, as the engine exits the event loop for it
+    fn exit(&mut self, _event_loop: &ActiveEventLoop) {
+        self.shut_down();
+    }
+
+    fn run_command(&mut self) -> SceneCommand {
+        std::mem::replace(&mut self.scene_command, SceneCommand::None)
+    }
+}
+
+impl Keyboard for RuntimeScene {
+    fn key_down(&mut self, key: KeyCode, _event_loop: &ActiveEventLoop) {
+        // Alt+Enter is also Enter, so toggling fullscreen has to win over every other binding
+        // on the key, like selecting a pause menu item.
+        if self.bindings.is_bound(key, bindings::TOGGLE_FULLSCREEN) && self.alt_held() {
+            self.set_window_mode(self.window_mode.toggled());
+            return;
+        }
+
+        if self.bindings.is_bound(key, bindings::QUIT) {
+            self.quit();
+        } else if self.bindings.is_bound(key, bindings::PAUSE) {
+            self.set_paused(!self.paused);
+        } else if self.bindings.is_bound(key, bindings::TOGGLE_DEBUG_OVERLAY)
+            && self.config.debug.overlay_allowed()
+        {
+            self.debug_overlay.toggle(self.window.as_ref());
+        } else if self.pause_menu_open() && self.bindings.is_bound(key, bindings::MENU_UP) {
+            self.pause_menu.move_selection(-1);
+        } else if self.pause_menu_open() && self.bindings.is_bound(key, bindings::MENU_DOWN) {
+            self.pause_menu.move_selection(1);
+        } else if self.pause_menu_open() && self.bindings.is_bound(key, bindings::MENU_SELECT) {
+            self.press_menu_item(self.pause_menu.selected());
+        } else if self.pause_menu_open() && self.bindings.is_bound(key, bindings::MENU_LEFT) {
+            self.step_selected_volume(-1);
+        } else if self.pause_menu_open() && self.bindings.is_bound(key, bindings::MENU_RIGHT) {
+            self.step_selected_volume(1);
+        } else if self.bindings.is_bound(key, bindings::RELOAD_SCENE)
+            && self.config.debug.scene_reload_allowed()
+        {
+            self.reload_scene();
+        } else if self.bindings.is_bound(key, bindings::NEXT_SCENE)
+            && self.config.debug.scene_cycling_allowed()
+        {
+            self.cycle_scene(1);
+        } else if self.bindings.is_bound(key, bindings::PREVIOUS_SCENE)
+            && self.config.debug.scene_cycling_allowed()
+        {
+            self.cycle_scene(-1);
+        } else if self.bindings.is_bound(key, bindings::TOGGLE_WIREFRAME)
+            && self.config.debug.wireframe_allowed()
+        {
+            self.toggle_wireframe();
+        } else if self.bindings.is_bound(key, bindings::SCREENSHOT) {
+            self.screenshots.request();
+        } else if self.bindings.is_bound(key, bindings::TOGGLE_CURSOR_LOCK) {
+            self.set_cursor_locked(!self.input_state.is_cursor_locked);
+        } else {
+            self.input_state.pressed_keys.insert(key);
+        }
+    }
+
+    fn key_up(&mut self, key: KeyCode, _event_loop: &ActiveEventLoop) {
+        self.input_state.pressed_keys.remove(&key);
+    }
+}
+
+impl Mouse for RuntimeScene {
+    fn mouse_move(&mut self, position: PhysicalPosition<f64>) {
+        if self.input_state.is_cursor_locked
+            && let Some(window) = self.window.clone()
+        {
+            let size = window.inner_size();
+            let center = PhysicalPosition::new(size.width as f64 / 2.0, size.height as f64 / 2.0);
+
+            self.mouse_look(position.x - center.x, position.y - center.y);
+
+            let _ = window.set_cursor_position(center);
+            window.set_cursor_visible(false);
+        }
+        self.input_state.mouse_pos = (position.x, position.y);
+        if let Some(item) = self.menu_item_under_cursor() {
+            self.pause_menu.select(item);
+        }
+    }
+
+    fn mouse_down(&mut self, button: MouseButton) {
+        self.input_state.mouse_button.insert(button);
+        if button == MouseButton::Left {
+            if let Some(item) = self.menu_item_under_cursor() {
+                self.press_menu_item(item);
+            } else if self.debug_overlay.is_visible() {
+                self.pick_entity();
+            }
+        }
+    }
+
+    fn mouse_up(&mut self, button: MouseButton) {
+        self.input_state.mouse_button.remove(&button);
+    }
+}
+
+impl Controller for RuntimeScene {
+    fn button_down(&mut self, button: Button, id: GamepadId) {
+        self.controllers.set_button(id, button, true);
+        self.input_state.controller_button_down(button, id);
+
+        match button {
+            Button::Start => self.set_paused(!self.paused),
+            _ if !self.pause_menu_open() => {}
+            Button::DPadUp => self.pause_menu.move_selection(-1),
+            Button::DPadDown => self.pause_menu.move_selection(1),
+            Button::DPadLeft => self.step_selected_volume(-1),
+            Button::DPadRight => self.step_selected_volume(1),
+            Button::South => self.press_menu_item(self.pause_menu.selected()),
+            Button::East => self.set_paused(false),
+            _ => {}
+        }
+    }
+
+    fn button_up(&mut self, button: Button, id: GamepadId) {
+        self.controllers.set_button(id, button, false);
+        self.input_state.controller_button_up(button, id);
+    }
+
+    fn left_stick_changed(&mut self, x: f32, y: f32, id: GamepadId) {
+        let (x, y) = input::apply_dead_zone(x, y, self.config.input.stick_dead_zone());
+        self.controllers.set_stick(id, true, x, y);
+        self.input_state.left_stick_changed(x, y, id);
+    }
+
+    fn right_stick_changed(&mut self, x: f32, y: f32, id: GamepadId) {
+        let (x, y) = input::apply_dead_zone(x, y, self.config.input.stick_dead_zone());
+        self.controllers.set_stick(id, false, x, y);
+        self.input_state.right_stick_changed(x, y, id);
+    }
+
+    fn on_connect(&mut self, id: GamepadId) {
+        log::info!("Controller connected: {:?}", id);
+    }
+
+    fn on_disconnect(&mut self, id: GamepadId) {
+        log::info!("Controller disconnected: {:?}", id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn project_name_is_the_executable_name() {
+        let exe = Path::new("games").join(if cfg!(target_os = "windows") {
+            "my-game.exe"
+        } else {
+            "my-game"
+        });
+        assert_eq!(project_name_from_exe(&exe).unwrap(), "my-game");
+    }
+
+    #[test]
+    fn dotted_project_names_are_kept_whole() {
+        let exe = Path::new(if cfg!(target_os = "windows") {
+            "my.game.exe"
+        } else {
+            "my.game"
+        });
+        assert_eq!(project_name_from_exe(exe).unwrap(), "my.game");
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn windows_executables_need_the_exe_suffix() {
+        assert!(project_name_from_exe(Path::new("my-game")).is_err());
+    }
+
+    #[test]
+    fn paths_without_a_file_name_are_an_error() {
+        assert!(project_name_from_exe(Path::new("/")).is_err());
+    }
+
+    #[test]
+    fn error_reports_lead_with_the_title() {
+        let report = error_report("Error loading game", "Package is missing.\n\nSee the log.");
+        assert_eq!(
+            report.lines().collect::<Vec<_>>(),
+            [
+                "FATAL: Error loading game",
+                "",
+                "Package is missing.",
+                "",
+                "See the log."
+            ]
+        );
+    }
+
+    #[test]
+    fn package_sits_next_to_the_executable() {
+        assert_eq!(
+            eupak_path(Path::new("games"), "my-game"),
+            Path::new("games").join("my-game.eupak")
+        );
+    }
 }
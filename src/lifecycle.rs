@@ -0,0 +1,24 @@
+// Tracks whether the app can still update and render.
+//
+// Losing the GPU device halts the app, as everything created against it is gone and there's no
+// coming back from it short of restarting.
+
+/// Where the app is at in its lifecycle.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Lifecycle {
+    #[default]
+    Running,
+    /// The GPU device was lost, so nothing can be rendered until the app is restarted.
+    DeviceLost,
+}
+
+impl Lifecycle {
+    pub fn lose_device(&mut self) {
+        *self = Self::DeviceLost;
+    }
+
+    /// Whether there's nothing to update or render, with the device gone.
+    pub fn is_halted(&self) -> bool {
+        *self == Self::DeviceLost
+    }
+}
@@ -0,0 +1,340 @@
+// Picks the light the entity shader lights with, keeping up with lights being enabled and disabled.
+//
+// The entity shader has the engine's single light uniform to light with, and that uniform only
+// has a light's position, colour and type, so anything else the runtime lets lights set, like how
+// they fall off with distance or which way they point, is uploaded in a buffer of its own. The
+// scene's ambient light goes at the start of it.
+
+use dropbear_engine::{
+    graphics::Graphics,
+    lighting::{Light, LightComponent},
+    wgpu,
+};
+use glam::DVec3;
+use hecs::World;
+
+/// How many lights entities are lit by, which is as many as the entity shader has light uniforms.
+/// Scenes can contain more lights than this, but only the nearest `MAX_LIGHTS` enabled ones to the
+/// camera light anything.
+pub const MAX_LIGHTS: usize = 1;
+
+/// Tracks which lights entities were lit by the last time the light parameters were uploaded, so
+/// they're only uploaded again once that changes.
+#[derive(Debug, Default)]
+pub struct LightTracker {
+    selected: Vec<hecs::Entity>,
+    dirty: bool,
+    /// How many lights were dropped last time, so the warning is only logged when it changes.
+    dropped: usize,
+}
+
+impl LightTracker {
+    /// Forces an upload on the next [`LightTracker::refresh`], for example after the world was
+    /// reloaded.
+    pub fn mark_dirty(&mut self) {
+        self.dirty = true;
+    }
+
+    /// Checks the world for lights being enabled, disabled, added, removed or moving in and out
+    /// of the nearest [`MAX_LIGHTS`] to `camera_eye`. Returns `true` if the light parameters need
+    /// to be uploaded again.
+    pub fn refresh(&mut self, world: &World, camera_eye: DVec3) -> bool {
+        let mut query = world.query::<(&Light, &LightComponent)>();
+        let lights = query
+            .iter()
+            .map(|(entity, (_, component))| (entity, component.enabled, component.position));
+        let (selected, dropped) = select_lights(lights, camera_eye, MAX_LIGHTS);
+
+        if dropped != self.dropped {
+            if dropped > 0 {
+                log::warn!(
+                    "Scene has {} enabled lights but entities can only be lit by {}, dropping the {} furthest from the camera",
+                    selected.len() + dropped,
+                    MAX_LIGHTS,
+                    dropped
+                );
+            }
+            self.dropped = dropped;
+        }
+
+        if selected != self.selected {
+            self.selected = selected;
+            self.dirty = true;
+        }
+
+        std::mem::take(&mut self.dirty)
+    }
+
+    /// The lights entities are lit by, nearest to the camera first.
+    pub fn selected(&self) -> &[hecs::Entity] {
+        &self.selected
+    }
+}
+
+/// Picks the nearest `max` enabled lights to `camera_eye` out of `lights`, given as each light's
+/// entity, whether it's enabled and where it is. Returns them nearest first, alongside how many
+/// enabled lights didn't make it.
+pub fn select_lights(
+    lights: impl IntoIterator<Item = (hecs::Entity, bool, DVec3)>,
+    camera_eye: DVec3,
+    max: usize,
+) -> (Vec<hecs::Entity>, usize) {
+    let mut enabled: Vec<(hecs::Entity, f64)> = lights
+        .into_iter()
+        .filter(|(_, enabled, _)| *enabled)
+        .map(|(entity, _, position)| (entity, position.distance_squared(camera_eye)))
+        .collect();
+    let dropped = enabled.len().saturating_sub(max);
+    enabled.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+    enabled.truncate(max);
+    (
+        enabled.into_iter().map(|(entity, _)| entity).collect(),
+        dropped,
+    )
+}
+
+/// How a light fades with distance, as `1 / (constant + linear * d + quadratic * d^2)`. The
+/// default doesn't fade at all, which is how lights without one are lit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct LightAttenuation {
+    pub constant: f64,
+    pub linear: f64,
+    pub quadratic: f64,
+    /// How far the light reaches at all. It eases off to nothing by this distance so there's no
+    /// hard edge, and lights anything further away not at all.
+    pub range: Option<f64>,
+}
+
+impl Default for LightAttenuation {
+    fn default() -> Self {
+        Self {
+            constant: 1.0,
+            linear: 0.0,
+            quadratic: 0.0,
+            range: None,
+        }
+    }
+}
+
+impl LightAttenuation {
+    /// Attenuation reaching out to about `range`, with the commonly used linear and quadratic
+    /// terms for it.
+    pub fn with_range(range: f64) -> Self {
+        Self {
+            constant: 1.0,
+            linear: 4.5 / range,
+            quadratic: 75.0 / (range * range),
+            range: Some(range),
+        }
+    }
+
+    /// The attenuation as uploaded, with a range of 0 for lights that reach everywhere.
+    fn to_uniform(self) -> [f32; 4] {
+        [
+            self.constant as f32,
+            self.linear as f32,
+            self.quadratic as f32,
+            self.range
+                .map_or(0.0, |range| range.max(f64::EPSILON) as f32),
+        ]
+    }
+}
+
+/// What sort of light a light entity is. Lights without one are point lights.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum LightKind {
+    /// Light shining from everywhere along `direction`, like the sun. It doesn't fade with
+    /// distance and has nowhere to be, so it's drawn without a model.
+    Directional { direction: DVec3 },
+    /// Light shining out from its position in every direction.
+    #[default]
+    Point,
+    /// A cone of light shining from its position along `direction`. It's full strength within
+    /// `inner_angle` of the middle and fades to nothing at `outer_angle`, both in radians.
+    Spot {
+        direction: DVec3,
+        inner_angle: f64,
+        outer_angle: f64,
+    },
+}
+
+impl LightKind {
+    /// Whether the light is drawn with a model where it is.
+    pub fn has_model(&self) -> bool {
+        !matches!(self, Self::Directional { .. })
+    }
+
+    /// The direction and kind, then the cosines of the cone angles, as uploaded.
+    fn to_uniform(self) -> [f32; 8] {
+        let (direction, kind, inner, outer) = match self {
+            Self::Point => (DVec3::ZERO, 0.0, 0.0, 0.0),
+            Self::Directional { direction } => (direction, 1.0, 0.0, 0.0),
+            Self::Spot {
+                direction,
+                inner_angle,
+                outer_angle,
+            } => (direction, 2.0, inner_angle, outer_angle.max(inner_angle)),
+        };
+        let direction = direction.normalize_or(DVec3::NEG_Y).as_vec3();
+        [
+            direction.x,
+            direction.y,
+            direction.z,
+            kind,
+            inner.cos() as f32,
+            outer.cos() as f32,
+            0.0,
+            0.0,
+        ]
+    }
+}
+
+/// The runtime's own parameters for each light in the light array.
+pub struct LightParams {
+    buffer: wgpu::Buffer,
+}
+
+impl LightParams {
+    pub fn new(graphics: &Graphics) -> Self {
+        Self {
+            buffer: graphics
+                .state
+                .device
+                .create_buffer(&wgpu::BufferDescriptor {
+                    label: Some("Light Params Buffer"),
+                    size: (AMBIENT_SIZE + MAX_LIGHTS * LIGHT_PARAMS_SIZE) as u64,
+                    usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                }),
+        }
+    }
+
+    pub fn buffer(&self) -> &wgpu::Buffer {
+        &self.buffer
+    }
+
+    /// Uploads the scene's `ambient` light and the parameters of `lights`, in the order entities
+    /// are lit by them.
+    pub fn upload(
+        &self,
+        graphics: &Graphics,
+        world: &World,
+        ambient: [f32; 3],
+        lights: &[hecs::Entity],
+    ) {
+        let ambient = [ambient[0], ambient[1], ambient[2], 0.0];
+        let params = lights.iter().take(MAX_LIGHTS).flat_map(|light| {
+            let attenuation = world
+                .get::<&LightAttenuation>(*light)
+                .map(|attenuation| *attenuation)
+                .unwrap_or_default();
+            let kind = world
+                .get::<&LightKind>(*light)
+                .map(|kind| *kind)
+                .unwrap_or_default();
+            attenuation
+                .to_uniform()
+                .into_iter()
+                .chain(kind.to_uniform())
+        });
+        let mut bytes: Vec<u8> = ambient
+            .into_iter()
+            .chain(params)
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        bytes.resize(AMBIENT_SIZE + MAX_LIGHTS * LIGHT_PARAMS_SIZE, 0);
+        graphics.state.queue.write_buffer(&self.buffer, 0, &bytes);
+    }
+}
+
+/// The bytes the ambient light takes up at the start of the buffer, padded out to a `vec4`.
+const AMBIENT_SIZE: usize = 4 * std::mem::size_of::<f32>();
+
+/// The bytes each light's parameters take up: its attenuation terms and range, its direction and
+/// kind, then its cone.
+const LIGHT_PARAMS_SIZE: usize = 12 * std::mem::size_of::<f32>();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ranged_attenuation_reaches_its_range() {
+        let attenuation = LightAttenuation::with_range(10.0);
+        assert_eq!(attenuation.to_uniform(), [1.0, 0.45, 0.75, 10.0]);
+        assert_eq!(
+            LightAttenuation::default().to_uniform(),
+            [1.0, 0.0, 0.0, 0.0]
+        );
+    }
+
+    #[test]
+    fn a_zero_range_still_uploads_as_ranged() {
+        let attenuation = LightAttenuation {
+            range: Some(0.0),
+            ..Default::default()
+        };
+        assert!(attenuation.to_uniform()[3] > 0.0);
+    }
+
+    #[test]
+    fn only_directional_lights_go_without_a_model() {
+        let directional = LightKind::Directional {
+            direction: DVec3::NEG_Y,
+        };
+        assert!(!directional.has_model());
+        assert!(LightKind::Point.has_model());
+    }
+
+    #[test]
+    fn spot_cones_never_end_inside_their_middle() {
+        let spot = LightKind::Spot {
+            direction: DVec3::new(0.0, 0.0, -2.0),
+            inner_angle: 0.5,
+            outer_angle: 0.25,
+        };
+        let uniform = spot.to_uniform();
+        assert_eq!(&uniform[..4], &[0.0, 0.0, -1.0, 2.0]);
+        assert_eq!(uniform[4], uniform[5]);
+    }
+
+    #[test]
+    fn directionless_lights_point_down() {
+        let directional = LightKind::Directional {
+            direction: DVec3::ZERO,
+        };
+        assert_eq!(&directional.to_uniform()[..4], &[0.0, -1.0, 0.0, 1.0]);
+    }
+
+    #[test]
+    fn disabled_lights_light_nothing() {
+        let mut world = World::new();
+        let light = world.spawn(());
+        let (selected, _) = select_lights([(light, true, DVec3::ZERO)], DVec3::ZERO, MAX_LIGHTS);
+        assert_eq!(selected, [light]);
+        let (selected, dropped) =
+            select_lights([(light, false, DVec3::ZERO)], DVec3::ZERO, MAX_LIGHTS);
+        assert!(selected.is_empty());
+        assert_eq!(dropped, 0);
+    }
+
+    #[test]
+    fn only_the_nearest_lights_are_kept() {
+        let mut world = World::new();
+        let far = world.spawn(());
+        let near = world.spawn(());
+        let middle = world.spawn(());
+        let off = world.spawn(());
+        let lights = [
+            (far, true, DVec3::new(0.0, 0.0, 30.0)),
+            (near, true, DVec3::new(1.0, 0.0, 0.0)),
+            (middle, true, DVec3::new(0.0, -5.0, 0.0)),
+            (off, false, DVec3::ZERO),
+        ];
+        assert_eq!(
+            select_lights(lights, DVec3::ZERO, 2),
+            (vec![near, middle], 1)
+        );
+        assert_eq!(select_lights(lights, DVec3::ZERO, 1), (vec![near], 2));
+    }
+}
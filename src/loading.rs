@@ -0,0 +1,436 @@
+// The built-in loading screen, shown while a scene's models and scripts are being loaded.
+//
+// Loading happens in two halves. First the scene's scripts are read from disk on a worker thread
+// while the main thread keeps rendering the loading screen. Once they're all in memory, the GPU
+// resources and scripts are created back on the main thread, which is the only part that blocks.
+// Scripts are initialised straight from the streamed bytes. Models aren't streamed, as the
+// engine only loads them from their paths and would read them from disk a second time.
+//
+// The loading screen is the package's clear colour, with its image (if it has one) drawn over it
+// as large as it fits without being cropped or stretched.
+
+use std::{
+    collections::HashMap,
+    path::PathBuf,
+    sync::mpsc::{self, Receiver, TryRecvError},
+};
+
+use dropbear_engine::{
+    graphics::{Graphics, Texture},
+    wgpu,
+};
+use eucalyptus_core::states::SceneConfig;
+
+use crate::config::LoadingScreenSettings;
+
+/// Where the runtime is at with getting a scene on screen.
+#[derive(Debug)]
+pub enum LoadState {
+    /// The loading screen is up and streaming starts once a frame of it has been shown.
+    Loading { scene: String, frame_shown: bool },
+    /// The scene's files are being read on a worker thread.
+    Streaming { scene: String, assets: AssetStream },
+    /// The scene is loaded (or failed to load) and is being rendered as normal.
+    Ready,
+}
+
+impl LoadState {
+    pub fn begin(scene: impl Into<String>) -> Self {
+        Self::Loading {
+            scene: scene.into(),
+            frame_shown: false,
+        }
+    }
+
+    pub fn is_loading(&self) -> bool {
+        !matches!(self, Self::Ready)
+    }
+
+    /// Called after rendering a frame of the loading screen.
+    pub fn mark_frame_shown(&mut self) {
+        if let Self::Loading { frame_shown, .. } = self {
+            *frame_shown = true;
+        }
+    }
+
+    /// Moves loading along without blocking. Once the loading screen has been shown, the files
+    /// listed by `asset_paths` for the scene start streaming in. Once they have all been read,
+    /// the scene and its files are returned and the state moves to [`LoadState::Ready`].
+    pub fn poll(
+        &mut self,
+        asset_paths: impl FnOnce(&str) -> Vec<PathBuf>,
+    ) -> Option<(String, StreamedAssets)> {
+        match self {
+            Self::Loading {
+                scene,
+                frame_shown: true,
+            } => {
+                let scene = std::mem::take(scene);
+                let assets = AssetStream::spawn(asset_paths(&scene));
+                *self = Self::Streaming { scene, assets };
+                None
+            }
+            Self::Streaming { assets, .. } => {
+                let streamed = assets.try_finish()?;
+                match std::mem::replace(self, Self::Ready) {
+                    Self::Streaming { scene, .. } => Some((scene, streamed)),
+                    _ => None,
+                }
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The files a scene uses, read into memory.
+#[derive(Debug, Default)]
+pub struct StreamedAssets {
+    files: HashMap<PathBuf, Vec<u8>>,
+}
+
+impl StreamedAssets {
+    /// Takes a file's contents out, if it was streamed in.
+    pub fn take(&mut self, path: &std::path::Path) -> Option<Vec<u8>> {
+        self.files.remove(path)
+    }
+}
+
+/// Reads a list of files on a worker thread.
+#[derive(Debug)]
+pub struct AssetStream {
+    receiver: Receiver<StreamedAssets>,
+}
+
+impl AssetStream {
+    pub fn spawn(paths: Vec<PathBuf>) -> Self {
+        let (sender, receiver) = mpsc::channel();
+
+        std::thread::spawn(move || {
+            let mut assets = StreamedAssets::default();
+            for path in paths {
+                match std::fs::read(&path) {
+                    Ok(bytes) => {
+                        assets.files.insert(path, bytes);
+                    }
+                    // the main thread reports missing files when it gets to them
+                    Err(e) => log::debug!("Unable to stream {}: {}", path.display(), e),
+                }
+            }
+            let _ = sender.send(assets);
+        });
+
+        Self { receiver }
+    }
+
+    /// Returns the streamed files once the worker is done.
+    pub fn try_finish(&self) -> Option<StreamedAssets> {
+        match self.receiver.try_recv() {
+            Ok(assets) => Some(assets),
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                log::warn!("Asset streaming thread stopped early, loading from disk instead");
+                Some(StreamedAssets::default())
+            }
+        }
+    }
+}
+
+/// The files streamed in while a scene loads, which are its scripts.
+pub fn scene_script_paths(scene: &SceneConfig) -> Vec<PathBuf> {
+    scene
+        .entities
+        .iter()
+        .filter_map(|entity| Some(entity.script.as_ref()?.path.clone()))
+        .collect()
+}
+
+/// Every file a scene reads from disk while loading: its models and its scripts.
+pub fn scene_asset_paths(scene: &SceneConfig) -> Vec<PathBuf> {
+    let mut paths = Vec::new();
+    for entity in &scene.entities {
+        paths.push(entity.model_path.clone());
+        if let Some(script) = &entity.script {
+            paths.push(script.path.clone());
+        }
+    }
+    paths
+}
+
+/// Where an image `image` pixels in size goes on a surface `surface` pixels in size, as large as
+/// it fits without changing its aspect ratio and centred. Given as the clip space positions of
+/// its top left and bottom right corners.
+pub fn fit_rect(image: (u32, u32), surface: (u32, u32)) -> [f32; 4] {
+    let image_aspect = image.0.max(1) as f32 / image.1.max(1) as f32;
+    let surface_aspect = surface.0.max(1) as f32 / surface.1.max(1) as f32;
+    let (width, height) = if image_aspect > surface_aspect {
+        (1.0, surface_aspect / image_aspect)
+    } else {
+        (image_aspect / surface_aspect, 1.0)
+    };
+    [-width, height, width, -height]
+}
+
+/// Draws the loading screen's image.
+pub struct LoadingScreen {
+    pipeline: wgpu::RenderPipeline,
+    uniform: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    image_size: (u32, u32),
+}
+
+impl LoadingScreen {
+    /// Loads the loading screen's image, or gives back `None` when there's no image to show,
+    /// including when it can't be loaded.
+    pub fn new(graphics: &Graphics, settings: &LoadingScreenSettings) -> Option<Self> {
+        let path = settings.texture.as_ref()?;
+        let image = match image::open(path) {
+            Ok(image) => image.to_rgba8(),
+            Err(e) => {
+                log::warn!(
+                    "Unable to load the loading screen image {}: {}",
+                    path.display(),
+                    e
+                );
+                return None;
+            }
+        };
+        let device = &graphics.state.device;
+
+        let size = wgpu::Extent3d {
+            width: image.width(),
+            height: image.height(),
+            depth_or_array_layers: 1,
+        };
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Loading Screen Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        graphics.state.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            &image,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(4 * image.width()),
+                rows_per_image: Some(image.height()),
+            },
+            size,
+        );
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Loading Screen Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Loading Screen Uniform"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Loading Screen Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::VERTEX,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Loading Screen Bind Group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Loading Screen Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("loading.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Loading Screen Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Loading Screen Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: graphics.state.config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // the surface pass comes with the engine's depth buffer, which this never touches
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Some(Self {
+            pipeline,
+            uniform,
+            bind_group,
+            image_size: (image.width(), image.height()),
+        })
+    }
+
+    /// Places the image for the surface as it's sized now, ahead of the next draw.
+    pub fn update(&self, graphics: &Graphics) {
+        let surface = (graphics.state.config.width, graphics.state.config.height);
+        let bytes: Vec<u8> = fit_rect(self.image_size, surface)
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        graphics.state.queue.write_buffer(&self.uniform, 0, &bytes);
+    }
+
+    /// Draws the image over whatever is already in `render_pass`.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..6, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+
+    use super::*;
+
+    /// Polls `state` until it hands back a scene, failing if streaming never finishes.
+    fn poll_until_loaded(state: &mut LoadState, paths: Vec<PathBuf>) -> (String, StreamedAssets) {
+        let deadline = Instant::now() + Duration::from_secs(5);
+        loop {
+            if let Some(loaded) = state.poll(|_| paths.clone()) {
+                return loaded;
+            }
+            assert!(Instant::now() < deadline, "streaming never finished");
+            std::thread::sleep(Duration::from_millis(1));
+        }
+    }
+
+    #[test]
+    fn loading_waits_for_the_loading_screen_to_be_shown() {
+        let mut state = LoadState::begin("Level 1");
+        assert!(state.is_loading());
+        assert!(
+            state
+                .poll(|_| panic!("streamed before a frame was shown"))
+                .is_none()
+        );
+        assert!(matches!(
+            state,
+            LoadState::Loading {
+                frame_shown: false,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn loading_ends_once_the_scripts_are_streamed() {
+        let dir = std::env::temp_dir().join(format!("redback-loading-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let script = dir.join("player.script");
+        std::fs::write(&script, b"fn update() {}").unwrap();
+
+        let mut state = LoadState::begin("Level 1");
+        state.mark_frame_shown();
+        let (scene, mut assets) = poll_until_loaded(&mut state, vec![script.clone()]);
+        assert_eq!(scene, "Level 1");
+        assert!(!state.is_loading());
+        assert_eq!(
+            assets.take(&script).as_deref(),
+            Some(&b"fn update() {}"[..])
+        );
+        assert!(assets.take(&script).is_none());
+
+        std::fs::remove_dir_all(dir).unwrap();
+    }
+
+    #[test]
+    fn missing_files_dont_hold_up_loading() {
+        let mut state = LoadState::begin("Level 1");
+        state.mark_frame_shown();
+        let missing = PathBuf::from("does-not-exist.script");
+        let (_, mut assets) = poll_until_loaded(&mut state, vec![missing.clone()]);
+        assert!(!state.is_loading());
+        assert!(assets.take(&missing).is_none());
+    }
+
+    #[test]
+    fn images_fit_the_surface_without_stretching() {
+        // wider than the surface, so it spans the width
+        assert_eq!(fit_rect((200, 50), (100, 100)), [-1.0, 0.25, 1.0, -0.25]);
+        // taller than the surface, so it spans the height
+        assert_eq!(fit_rect((50, 100), (200, 100)), [-0.25, 1.0, 0.25, -1.0]);
+        assert_eq!(fit_rect((1920, 1080), (1280, 720)), [-1.0, 1.0, 1.0, -1.0]);
+    }
+}
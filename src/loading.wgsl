@@ -0,0 +1,41 @@
+// Shader for the image shown on the loading screen
+
+struct Placement {
+    // the image's top left and bottom right corners in clip space
+    rect: vec4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> placement: Placement;
+@group(0) @binding(1)
+var loading_texture: texture_2d<f32>;
+@group(0) @binding(2)
+var loading_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // two triangles making a quad, with the texture's top left at the image's top left
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[index];
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(mix(placement.rect.xy, placement.rect.zw, corner), 0.0, 1.0);
+    out.uv = corner;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(loading_texture, loading_sampler, in.uv);
+}
@@ -0,0 +1,352 @@
+// Where the runtime keeps its logs, so players can be pointed at them when something breaks.
+//
+// On desktop, everything logged goes to stderr and to the log file, filtered by `REDBACK_LOG`
+// (which takes the same `target=level` syntax as `RUST_LOG`). Android logs to logcat instead, see
+// `lib.rs`.
+//
+// The log file is appended to across runs, so the log of a crash is still there when the player
+// opens the game again. Once it grows past [`MAX_LOG_FILE_SIZE`] it's rotated: the current file
+// becomes `redback-runtime.log.1`, the old `.1` becomes `.2` and so on, dropping the oldest.
+
+use std::{
+    fs::{File, OpenOptions},
+    io::Write,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use app_dirs2::AppDataType;
+use log::{LevelFilter, Log, Metadata, Record};
+
+use crate::APP_INFO;
+
+/// The name of the log file inside the app data `logs` directory.
+pub const LOG_FILE_NAME: &str = "redback-runtime.log";
+
+/// The path of the runtime's log file, inside the user data directory for [`APP_INFO`].
+pub fn log_file_path() -> anyhow::Result<PathBuf> {
+    Ok(app_dirs2::get_app_dir(AppDataType::UserData, &APP_INFO, "logs")?.join(LOG_FILE_NAME))
+}
+
+/// A human readable description of where the logs can be found, for use in error dialogs.
+pub fn log_location() -> String {
+    #[cfg(target_os = "android")]
+    {
+        "the device's logcat buffer (run `adb logcat` to view it)".to_string()
+    }
+
+    #[cfg(not(target_os = "android"))]
+    {
+        match log_file_path() {
+            Ok(path) => path.display().to_string(),
+            Err(e) => {
+                log::warn!("Unable to resolve the log file path: {}", e);
+                "the runtime's log file".to_string()
+            }
+        }
+    }
+}
+
+/// How large the log file can get, in bytes, before it's rotated.
+pub const MAX_LOG_FILE_SIZE: u64 = 5 * 1024 * 1024;
+
+/// How many rotated log files are kept alongside the current one.
+pub const ROTATED_LOG_FILES: u32 = 3;
+
+/// A log file that rotates itself once it grows too large.
+pub struct RotatingFile {
+    path: PathBuf,
+    file: File,
+    written: u64,
+    max_size: u64,
+    kept: u32,
+}
+
+impl RotatingFile {
+    /// Opens the log file at `path` to append to, creating it if it isn't there.
+    pub fn open(path: PathBuf, max_size: u64, kept: u32) -> std::io::Result<Self> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        Ok(Self {
+            path,
+            file,
+            written,
+            max_size,
+            kept,
+        })
+    }
+
+    /// Appends `line`, rotating the files first if it would take the current one past the
+    /// maximum size.
+    pub fn write_line(&mut self, line: &str) -> std::io::Result<()> {
+        if self.written > 0 && self.written + line.len() as u64 > self.max_size {
+            self.rotate()?;
+        }
+        self.file.write_all(line.as_bytes())?;
+        self.written += line.len() as u64;
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> std::io::Result<()> {
+        self.file.flush()
+    }
+
+    fn rotate(&mut self) -> std::io::Result<()> {
+        self.file.flush()?;
+        if self.kept == 0 {
+            std::fs::remove_file(&self.path)?;
+        } else {
+            for index in (1..self.kept).rev() {
+                let from = rotated_path(&self.path, index);
+                if from.exists() {
+                    std::fs::rename(&from, rotated_path(&self.path, index + 1))?;
+                }
+            }
+            std::fs::rename(&self.path, rotated_path(&self.path, 1))?;
+        }
+
+        self.file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        self.written = 0;
+        Ok(())
+    }
+}
+
+/// The path of the `index`th rotated copy of the log file at `path`, such as
+/// `redback-runtime.log.2`.
+pub fn rotated_path(path: &Path, index: u32) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(".{}", index));
+    path.with_file_name(name)
+}
+
+/// The environment variable the log filter is read from.
+pub const LOG_FILTER_ENV: &str = "REDBACK_LOG";
+
+/// What's logged when `REDBACK_LOG` isn't set: warnings from everything, and the runtime's own
+/// progress on top.
+pub const DEFAULT_LOG_FILTER: &str = "warn,redback_runtime_lib=info";
+
+/// Which levels are logged for which targets, parsed from a `RUST_LOG` style string such as
+/// `info,wgpu_core=warn`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LogFilter {
+    default: LevelFilter,
+    /// Levels for targets and everything under them, such as `wgpu_core::device`.
+    targets: Vec<(String, LevelFilter)>,
+}
+
+impl LogFilter {
+    /// Parses a comma separated list of `level` and `target=level` directives. Directives that
+    /// don't parse are skipped with a warning printed to stderr, as the logger isn't running yet.
+    pub fn parse(spec: &str) -> Self {
+        let mut filter = Self {
+            default: LevelFilter::Error,
+            targets: Vec::new(),
+        };
+
+        for directive in spec.split(',').map(str::trim).filter(|d| !d.is_empty()) {
+            let parsed = match directive.split_once('=') {
+                Some((target, level)) => level.trim().parse().map(|level| {
+                    filter.targets.push((target.trim().to_string(), level));
+                }),
+                None => directive.parse().map(|level| filter.default = level),
+            };
+            if parsed.is_err() {
+                eprintln!("Ignoring invalid log directive '{}'", directive);
+            }
+        }
+
+        // the most specific target has to be checked first
+        filter
+            .targets
+            .sort_by_key(|(target, _)| std::cmp::Reverse(target.len()));
+        filter
+    }
+
+    /// The most verbose level logged for `target`.
+    pub fn level_for(&self, target: &str) -> LevelFilter {
+        self.targets
+            .iter()
+            .find(|(prefix, _)| {
+                target == prefix
+                    || target
+                        .strip_prefix(prefix.as_str())
+                        .is_some_and(|rest| rest.starts_with("::"))
+            })
+            .map_or(self.default, |(_, level)| *level)
+    }
+
+    /// The most verbose level logged for any target.
+    pub fn max_level(&self) -> LevelFilter {
+        self.targets
+            .iter()
+            .map(|(_, level)| *level)
+            .fold(self.default, Ord::max)
+    }
+}
+
+/// Writes log records to stderr and the log file.
+struct RuntimeLogger {
+    filter: LogFilter,
+    file: Option<Mutex<RotatingFile>>,
+}
+
+impl Log for RuntimeLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= self.filter.level_for(metadata.target())
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let line = format!(
+            "[{} {}] {}\n",
+            record.level(),
+            record.target(),
+            record.args()
+        );
+        let _ = std::io::stderr().write_all(line.as_bytes());
+        if let Some(file) = &self.file
+            && let Ok(mut file) = file.lock()
+        {
+            let _ = file.write_line(&line);
+        }
+    }
+
+    fn flush(&self) {
+        let _ = std::io::stderr().flush();
+        if let Some(file) = &self.file
+            && let Ok(mut file) = file.lock()
+        {
+            let _ = file.flush();
+        }
+    }
+}
+
+/// Writes out anything logged so far, for before the process ends.
+pub fn flush() {
+    log::logger().flush();
+}
+
+/// Starts logging to stderr and the log file with the filter from [`LOG_FILTER_ENV`]. Logging
+/// still goes to stderr if the log file can't be opened.
+///
+/// The `log` crate only takes one logger per process. If one is already set up, this says so on
+/// stderr and leaves that one running rather than aborting. It's called before the engine
+/// starts, so a logger the engine sets up of its own afterwards is the one that misses out, and
+/// one set up with a panicking call such as `env_logger::init` would panic there.
+pub fn init() {
+    let spec = std::env::var(LOG_FILTER_ENV).unwrap_or_else(|_| DEFAULT_LOG_FILTER.to_string());
+    let filter = LogFilter::parse(&spec);
+
+    let file = log_file_path().and_then(|path| {
+        Ok(RotatingFile::open(
+            path,
+            MAX_LOG_FILE_SIZE,
+            ROTATED_LOG_FILES,
+        )?)
+    });
+    let (file, file_error) = match file {
+        Ok(file) => (Some(Mutex::new(file)), None),
+        Err(e) => (None, Some(e)),
+    };
+
+    let max_level = filter.max_level();
+    let logger = Box::leak(Box::new(RuntimeLogger { filter, file }));
+    if log::set_logger(logger).is_err() {
+        eprintln!("A logger was already set up, so the runtime's own logger wasn't installed");
+        return;
+    }
+    log::set_max_level(max_level);
+
+    if let Some(e) = file_error {
+        log::warn!("Unable to open the log file, only logging to stderr: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_default_filter_logs_the_runtime_at_info() {
+        let filter = LogFilter::parse(DEFAULT_LOG_FILTER);
+        assert_eq!(filter.level_for("wgpu_core::device"), LevelFilter::Warn);
+        assert_eq!(
+            filter.level_for("redback_runtime_lib::loading"),
+            LevelFilter::Info
+        );
+        assert_eq!(filter.max_level(), LevelFilter::Info);
+    }
+
+    #[test]
+    fn the_most_specific_target_wins() {
+        let filter = LogFilter::parse("error, wgpu=info, wgpu::hal=trace");
+        assert_eq!(filter.level_for("wgpu::hal::vulkan"), LevelFilter::Trace);
+        assert_eq!(filter.level_for("wgpu::device"), LevelFilter::Info);
+        assert_eq!(filter.level_for("wgpu"), LevelFilter::Info);
+        // only whole path segments count
+        assert_eq!(filter.level_for("wgpu_core"), LevelFilter::Error);
+        assert_eq!(filter.max_level(), LevelFilter::Trace);
+    }
+
+    #[test]
+    fn invalid_directives_are_skipped() {
+        let filter = LogFilter::parse("loud,gilrs=debug,rodio=");
+        assert_eq!(filter.level_for("winit"), LevelFilter::Error);
+        assert_eq!(filter.level_for("gilrs"), LevelFilter::Debug);
+        assert_eq!(filter.level_for("rodio"), LevelFilter::Error);
+    }
+
+    #[test]
+    fn full_log_files_are_rotated_dropping_the_oldest() {
+        let dir = std::env::temp_dir().join(format!("redback-logs-{}", std::process::id()));
+        let path = dir.join(LOG_FILE_NAME);
+        let mut file = RotatingFile::open(path.clone(), 10, 2).unwrap();
+        for line in ["first\n", "second\n", "third\n", "fourth\n"] {
+            file.write_line(line).unwrap();
+        }
+        file.flush().unwrap();
+
+        let read = |path: PathBuf| std::fs::read_to_string(path).unwrap();
+        assert_eq!(read(path.clone()), "fourth\n");
+        assert_eq!(read(rotated_path(&path, 1)), "third\n");
+        assert_eq!(read(rotated_path(&path, 2)), "second\n");
+        assert!(!rotated_path(&path, 3).exists());
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn reopened_log_files_are_appended_to() {
+        let dir = std::env::temp_dir().join(format!("redback-reopen-{}", std::process::id()));
+        let path = dir.join(LOG_FILE_NAME);
+        RotatingFile::open(path.clone(), 100, 1)
+            .unwrap()
+            .write_line("before\n")
+            .unwrap();
+        RotatingFile::open(path.clone(), 100, 1)
+            .unwrap()
+            .write_line("after\n")
+            .unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "before\nafter\n");
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn rotated_copies_are_numbered_after_the_file() {
+        assert_eq!(
+            rotated_path(Path::new("logs/redback-runtime.log"), 2),
+            Path::new("logs/redback-runtime.log.2")
+        );
+    }
+}
@@ -0,0 +1,48 @@
+// Rendering the cheap way, for very weak devices.
+//
+// Low-power mode is turned on by the package or with `--low-power` on the command line. It asks
+// for the low-power GPU adapter, which on machines with two GPUs is the integrated one, and turns
+// off the most expensive parts of rendering: MSAA and shadows are left out, and the game runs in
+// a window no bigger than `MAX_RESOLUTION`. Everything is applied before the window is created,
+// over the player's own preferences.
+//
+// The engine picks the adapter itself. The runtime sets wgpu's `WGPU_POWER_PREF` environment
+// variable for it to pick up and checks which adapter the low-power preference finds, logging
+// it, but whether the engine goes along with it is up to the engine.
+
+use dropbear_engine::wgpu;
+
+use crate::config::{PackageConfig, WindowMode};
+
+/// The biggest window low-power mode runs in.
+pub const MAX_RESOLUTION: (u32, u32) = (1280, 720);
+
+/// The adapter preference for rendering in low-power mode or not.
+pub fn power_preference(low_power: bool) -> wgpu::PowerPreference {
+    if low_power {
+        wgpu::PowerPreference::LowPower
+    } else {
+        wgpu::PowerPreference::default()
+    }
+}
+
+/// Turns off the expensive rendering settings in `config` and caps its window's size.
+pub fn apply(config: &mut PackageConfig) {
+    config.graphics.msaa_samples = Some(1);
+    config.graphics.shadows = Some(false);
+
+    let (width, height) = config.window.resolution();
+    config.window.resolution = Some((width.min(MAX_RESOLUTION.0), height.min(MAX_RESOLUTION.1)));
+    // a maximised or fullscreen window takes up the whole screen, however big that is
+    config.window.mode = Some(WindowMode::Windowed);
+}
+
+/// Asks the engine for the low-power adapter, through the environment variable wgpu reads the
+/// preference from.
+pub fn request_adapter() {
+    // SAFETY: this runs on the main thread before the engine starts, when nothing else is
+    // reading the environment
+    unsafe {
+        std::env::set_var("WGPU_POWER_PREF", "low");
+    }
+}
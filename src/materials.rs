@@ -0,0 +1,303 @@
+// Surface detail for entities on top of what their model's own material gives them.
+//
+// A `Material` on an entity can point at a tangent-space normal map, which the entity shader
+// samples to bend the surface's normal before lighting it. The engine's vertices have no tangents,
+// so the shader works out the tangent frame from how the position and texture coordinates change
+// across each pixel instead. Entities without a normal map light with their geometric normals.
+//
+// Surfaces are lit with a metallic-roughness BRDF, as in glTF. How metallic and rough a surface is
+// comes from the material's factors, multiplied by the blue and green channels of its
+// metallic-roughness map if it has one. The defaults of no metal and middling roughness come out
+// close to Blinn-Phong shading: Lambert diffuse plus a white specular highlight.
+//
+// Every pipeline bind group is already taken, so a material's textures and uniform go in the
+// shadow map's group (see `crate::shadows`), with a bind group per material. Entities without a
+// `Material` are drawn with the shadow map's own bind group, which has the default material.
+
+use std::{
+    collections::HashMap,
+    hash::{Hash, Hasher},
+    path::{Path, PathBuf},
+};
+
+use dropbear_engine::{graphics::Graphics, wgpu};
+use hecs::World;
+
+use crate::shadows::ShadowMap;
+
+/// How metallic surfaces are unless their material says otherwise, from 0 to 1.
+pub const DEFAULT_METALLIC: f32 = 0.0;
+
+/// How rough surfaces are unless their material says otherwise, from 0 to 1. About as sharp a
+/// highlight as Blinn-Phong with a shininess of 32.
+pub const DEFAULT_ROUGHNESS: f32 = 0.5;
+
+/// The bytes of the material uniform: whether there's a normal map, the metallic and roughness
+/// factors, then padding out to 16 bytes.
+pub const MATERIAL_UNIFORM_SIZE: u64 = 16;
+
+/// How an entity's surface is shaded beyond its model's diffuse texture.
+#[derive(Debug, Clone)]
+pub struct Material {
+    /// A tangent-space normal map, relative to the working directory like scene models. It's
+    /// sampled with the model's texture coordinates.
+    pub normal_map: Option<PathBuf>,
+    /// From 0 for a dielectric like plastic or wood to 1 for bare metal.
+    pub metallic: f32,
+    /// From 0 for a mirror finish to 1 for a completely matte one.
+    pub roughness: f32,
+    /// A texture whose blue channel scales `metallic` and green channel scales `roughness`
+    /// across the surface, relative to the working directory.
+    pub metallic_roughness_map: Option<PathBuf>,
+}
+
+impl Default for Material {
+    fn default() -> Self {
+        Self {
+            normal_map: None,
+            metallic: DEFAULT_METALLIC,
+            roughness: DEFAULT_ROUGHNESS,
+            metallic_roughness_map: None,
+        }
+    }
+}
+
+impl Material {
+    /// What tells materials apart, comparing the factors bit for bit so materials can be keys.
+    fn key(&self) -> (&Option<PathBuf>, u32, u32, &Option<PathBuf>) {
+        (
+            &self.normal_map,
+            self.metallic.to_bits(),
+            self.roughness.to_bits(),
+            &self.metallic_roughness_map,
+        )
+    }
+
+    /// The material uniform, with the factors brought into range.
+    fn uniform_bytes(&self, normal_mapped: bool) -> Vec<u8> {
+        let mut bytes = u32::from(normal_mapped).to_ne_bytes().to_vec();
+        // perfectly smooth surfaces have a highlight too small to show up
+        for factor in [
+            self.metallic.clamp(0.0, 1.0),
+            self.roughness.clamp(0.04, 1.0),
+        ] {
+            bytes.extend(factor.to_ne_bytes());
+        }
+        bytes.resize(MATERIAL_UNIFORM_SIZE as usize, 0);
+        bytes
+    }
+}
+
+impl PartialEq for Material {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+
+impl Eq for Material {}
+
+impl Hash for Material {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.key().hash(state);
+    }
+}
+
+/// The textures and uniform of one material, as bound alongside the shadow map.
+pub struct MaterialResources {
+    pub normal_map: wgpu::TextureView,
+    pub metallic_roughness_map: wgpu::TextureView,
+    pub sampler: wgpu::Sampler,
+    pub uniform: wgpu::Buffer,
+}
+
+impl MaterialResources {
+    /// The resources of entities without a [`Material`]: the default factors, a flat normal map
+    /// and normal mapping turned off.
+    pub fn flat(graphics: &Graphics) -> Self {
+        Self::new(graphics, &Material::default(), None, None)
+    }
+
+    /// Uploads `material`'s textures and uniform. Maps it doesn't have are replaced with ones
+    /// that leave the surface as it is: a flat normal map and a white metallic-roughness map.
+    fn new(
+        graphics: &Graphics,
+        material: &Material,
+        normal_map: Option<&image::RgbaImage>,
+        metallic_roughness_map: Option<&image::RgbaImage>,
+    ) -> Self {
+        let device = &graphics.state.device;
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Material Sampler"),
+            address_mode_u: wgpu::AddressMode::Repeat,
+            address_mode_v: wgpu::AddressMode::Repeat,
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Material Uniform"),
+            size: MATERIAL_UNIFORM_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        graphics.state.queue.write_buffer(
+            &uniform,
+            0,
+            &material.uniform_bytes(normal_map.is_some()),
+        );
+
+        let flat = image::RgbaImage::from_pixel(1, 1, image::Rgba([128, 128, 255, 255]));
+        let white = image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4]));
+        Self {
+            normal_map: upload_texture(graphics, "Normal Map", normal_map.unwrap_or(&flat)),
+            metallic_roughness_map: upload_texture(
+                graphics,
+                "Metallic Roughness Map",
+                metallic_roughness_map.unwrap_or(&white),
+            ),
+            sampler,
+            uniform,
+        }
+    }
+}
+
+/// The bind groups of every material entities have been drawn with, each alongside the shadow
+/// map they were created against.
+#[derive(Default)]
+pub struct MaterialBindings {
+    bind_groups: HashMap<Material, wgpu::BindGroup>,
+}
+
+impl MaterialBindings {
+    /// Creates the bind groups of the materials in `world` that don't have one yet, loading their
+    /// textures.
+    pub fn prepare(&mut self, graphics: &Graphics, world: &World, shadow_map: &ShadowMap) {
+        for (_, material) in world.query::<&Material>().iter() {
+            if self.bind_groups.contains_key(material) {
+                continue;
+            }
+            let normal_map = material
+                .normal_map
+                .as_deref()
+                .and_then(|path| load_texture(path, "normal map"));
+            let metallic_roughness_map = material
+                .metallic_roughness_map
+                .as_deref()
+                .and_then(|path| load_texture(path, "metallic-roughness map"));
+            let resources = MaterialResources::new(
+                graphics,
+                material,
+                normal_map.as_ref(),
+                metallic_roughness_map.as_ref(),
+            );
+            let bind_group = shadow_map.create_bind_group(graphics, &resources);
+            self.bind_groups.insert(material.clone(), bind_group);
+        }
+    }
+
+    /// The bind group to draw an entity with `material` with, or `None` for the shadow map's own.
+    pub fn bind_group(&self, material: Option<&Material>) -> Option<&wgpu::BindGroup> {
+        self.bind_groups.get(material?)
+    }
+}
+
+/// Loads the texture at `path`, or `None` if it can't be loaded, in which case the material is
+/// drawn as if it didn't have it.
+fn load_texture(path: &Path, kind: &str) -> Option<image::RgbaImage> {
+    match image::open(path) {
+        Ok(image) => Some(image.to_rgba8()),
+        Err(e) => {
+            log::warn!("Unable to load {} {}: {}", kind, path.display(), e);
+            None
+        }
+    }
+}
+
+/// Uploads one of a material's maps, which hold directions and factors rather than colours so
+/// aren't sRGB.
+fn upload_texture(graphics: &Graphics, label: &str, image: &image::RgbaImage) -> wgpu::TextureView {
+    let size = wgpu::Extent3d {
+        width: image.width(),
+        height: image.height(),
+        depth_or_array_layers: 1,
+    };
+    let texture = graphics
+        .state
+        .device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some(label),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+    graphics.state.queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        image,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * image.width()),
+            rows_per_image: Some(image.height()),
+        },
+        size,
+    );
+    texture.create_view(&wgpu::TextureViewDescriptor::default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn factors(bytes: &[u8]) -> (u32, f32, f32) {
+        let word = |at: usize| <[u8; 4]>::try_from(&bytes[at..at + 4]).unwrap();
+        (
+            u32::from_ne_bytes(word(0)),
+            f32::from_ne_bytes(word(4)),
+            f32::from_ne_bytes(word(8)),
+        )
+    }
+
+    #[test]
+    fn factors_are_brought_into_range() {
+        let material = Material {
+            metallic: 1.5,
+            roughness: 0.0,
+            ..Default::default()
+        };
+        let bytes = material.uniform_bytes(true);
+        assert_eq!(bytes.len(), MATERIAL_UNIFORM_SIZE as usize);
+        assert_eq!(factors(&bytes), (1, 1.0, 0.04));
+    }
+
+    #[test]
+    fn the_default_material_has_no_normal_map() {
+        let bytes = Material::default().uniform_bytes(false);
+        assert_eq!(factors(&bytes), (0, DEFAULT_METALLIC, DEFAULT_ROUGHNESS));
+    }
+
+    #[test]
+    fn materials_with_the_same_factors_are_shared() {
+        let mut materials = HashMap::new();
+        let rough = Material {
+            roughness: 0.9,
+            ..Default::default()
+        };
+        materials.insert(rough.clone(), "rough");
+        materials.insert(Material::default(), "default");
+        assert_eq!(materials.get(&rough), Some(&"rough"));
+        assert_eq!(materials.len(), 2);
+    }
+
+    #[test]
+    fn missing_maps_are_left_out() {
+        assert!(load_texture(Path::new("does/not/exist.png"), "normal map").is_none());
+    }
+}
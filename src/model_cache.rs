@@ -0,0 +1,155 @@
+// Models kept on the GPU across scene switches.
+//
+// Models are loaded through a cache keyed by their path, so every entity and scene using a model
+// that's already loaded shares it. The textures of a model's materials are part of the model, so
+// they're shared along with it.
+//
+// Models no entity uses any more stay loaded in case they're needed again, until the cache grows
+// past its memory budget. Unused models are then dropped, least recently used first, until the
+// cache fits again. Models still in use are never dropped, whatever the budget.
+
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+use dropbear_engine::{graphics::Graphics, model::Model};
+
+/// How much GPU memory unused models can hold on to when the package doesn't say, in MiB.
+pub const DEFAULT_MODEL_CACHE_BUDGET: u32 = 256;
+
+/// Loaded models keyed by the path they were loaded from.
+pub struct ModelCache {
+    entries: HashMap<PathBuf, CachedModel>,
+    budget: u64,
+    /// Counts up on every lookup, standing in for the time a model was last used.
+    clock: u64,
+}
+
+struct CachedModel {
+    model: Arc<Model>,
+    size: u64,
+    last_used: u64,
+}
+
+impl ModelCache {
+    /// Creates an empty cache that keeps unused models until they take up more than
+    /// `budget_mib`.
+    pub fn new(budget_mib: u32) -> Self {
+        Self {
+            entries: HashMap::new(),
+            budget: budget_mib as u64 * 1024 * 1024,
+            clock: 0,
+        }
+    }
+
+    /// The model at `path`, loading it if it isn't cached yet. Nothing is evicted here, so a
+    /// scene being loaded can't lose models it's about to reuse. Call [`ModelCache::trim`] once
+    /// it has loaded.
+    pub fn get_or_load(
+        &mut self,
+        graphics: &Graphics,
+        path: &Path,
+        label: Option<&str>,
+    ) -> anyhow::Result<Arc<Model>> {
+        self.clock += 1;
+        if let Some(cached) = self.entries.get_mut(path) {
+            cached.last_used = self.clock;
+            log::debug!("Reusing cached model {}", path.display());
+            return Ok(cached.model.clone());
+        }
+
+        let model = Arc::new(Model::load(graphics, path, label)?);
+        let size = model_size(&model);
+        self.entries.insert(
+            path.to_path_buf(),
+            CachedModel {
+                model: model.clone(),
+                size,
+                last_used: self.clock,
+            },
+        );
+        log::debug!("Cached model {} ({} bytes)", path.display(), size);
+        Ok(model)
+    }
+
+    /// Drops the least recently used models nothing else holds on to until the cache fits in
+    /// its budget. Models that are still in use are never dropped, so the cache can stay over
+    /// budget while the scene needs them all.
+    pub fn trim(&mut self) {
+        let total: u64 = self.entries.values().map(|cached| cached.size).sum();
+        if total <= self.budget {
+            return;
+        }
+
+        let unused: Vec<(PathBuf, u64, u64)> = self
+            .entries
+            .iter()
+            .filter(|(_, cached)| Arc::strong_count(&cached.model) == 1)
+            .map(|(path, cached)| (path.clone(), cached.last_used, cached.size))
+            .collect();
+
+        for path in evictions(unused, total, self.budget) {
+            self.entries.remove(&path);
+            log::debug!("Evicted cached model {}", path.display());
+        }
+    }
+}
+
+/// The paths out of `unused`, given as `(path, last_used, size)`, to drop to bring `total` down
+/// to `budget`, least recently used first.
+fn evictions(mut unused: Vec<(PathBuf, u64, u64)>, mut total: u64, budget: u64) -> Vec<PathBuf> {
+    unused.sort_by_key(|(_, last_used, _)| *last_used);
+    let mut evicted = Vec::new();
+    for (path, _, size) in unused {
+        if total <= budget {
+            break;
+        }
+        total -= size;
+        evicted.push(path);
+    }
+    evicted
+}
+
+/// Roughly how much GPU memory `model` takes up, going by its vertex and index buffers.
+fn model_size(model: &Model) -> u64 {
+    model
+        .meshes
+        .iter()
+        .map(|mesh| mesh.vertex_buffer.size() + mesh.index_buffer.size())
+        .sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn unused(models: &[(&str, u64, u64)]) -> Vec<(PathBuf, u64, u64)> {
+        models
+            .iter()
+            .map(|(path, last_used, size)| (PathBuf::from(path), *last_used, *size))
+            .collect()
+    }
+
+    #[test]
+    fn least_recently_used_models_go_first() {
+        let models = unused(&[("a.glb", 3, 100), ("b.glb", 1, 100), ("c.glb", 2, 100)]);
+        assert_eq!(
+            evictions(models, 400, 200),
+            vec![PathBuf::from("b.glb"), PathBuf::from("c.glb")]
+        );
+    }
+
+    #[test]
+    fn nothing_is_evicted_within_budget() {
+        let models = unused(&[("a.glb", 1, 100)]);
+        assert!(evictions(models, 200, 200).is_empty());
+    }
+
+    #[test]
+    fn models_in_use_can_keep_the_cache_over_budget() {
+        let models = unused(&[("a.glb", 1, 50)]);
+        assert_eq!(evictions(models, 500, 200), vec![PathBuf::from("a.glb")]);
+    }
+}
@@ -0,0 +1,396 @@
+// Everything to do with reading a game's `.eupak` package.
+//
+// A package is laid out as a small [`PackageHeader`], followed by the runtime's own
+// [`PackageConfig`] and then the bincode-encoded [`RuntimeData`]. The header is decoded on its
+// own first, so a package built for a different runtime can be told apart from a package that is
+// actually damaged. The whole thing may also be compressed, see [`Codec`].
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use bincode::{Decode, Encode, error::DecodeError};
+use eucalyptus_core::states::RuntimeData;
+
+use crate::{
+    compression::Codec,
+    config::PackageConfig,
+    validation::{MissingAsset, OversizedScene},
+};
+
+/// The `.eupak` format version this runtime can read. Packages with any other version in their
+/// header are turned away before the rest of them is decoded.
+pub const RUNTIME_FORMAT_VERSION: u32 = 1;
+
+/// Sits at the very start of every `.eupak` package.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub struct PackageHeader {
+    pub format_version: u32,
+}
+
+impl PackageHeader {
+    /// Whether a package with this header can be read by this runtime.
+    pub fn is_compatible(&self) -> bool {
+        self.format_version == RUNTIME_FORMAT_VERSION
+    }
+}
+
+/// Decodes the header at the start of the package, returning it alongside the amount of bytes
+/// it took up.
+pub fn decode_header(bytes: &[u8]) -> Result<(PackageHeader, usize), DecodeError> {
+    bincode::decode_from_slice(bytes, bincode::config::standard())
+}
+
+/// Decodes the [`PackageConfig`] and [`RuntimeData`] payload that follows the header.
+pub fn decode_payload(payload: &[u8]) -> Result<(PackageConfig, RuntimeData), DecodeError> {
+    let (config, config_len) = bincode::decode_from_slice(payload, bincode::config::standard())?;
+    let (content, _) =
+        bincode::decode_from_slice(&payload[config_len..], bincode::config::standard())?;
+    Ok((config, content))
+}
+
+/// Everything that can go wrong while loading a package.
+#[derive(Debug)]
+pub enum RuntimeError {
+    /// There is no package at the expected path.
+    MissingPackage { path: PathBuf },
+    /// The package exists but couldn't be read.
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The package is compressed but couldn't be decompressed.
+    Decompress {
+        codec: Codec,
+        source: std::io::Error,
+    },
+    /// The package was built for a different format version.
+    VersionMismatch { package_version: u32 },
+    /// The package matches this runtime but contains invalid text, so it's been damaged.
+    Utf8Decode(DecodeError),
+    /// The package couldn't be decoded for any other reason.
+    Decode(DecodeError),
+    /// Scenes in the package refer to files that don't exist.
+    MissingAssets(Vec<MissingAsset>),
+    /// Scenes in the package have more entities than it allows.
+    TooManyEntities(Vec<OversizedScene>),
+    /// The scene asked for on the command line isn't in the package.
+    UnknownScene { scene: String, scenes: Vec<String> },
+    /// There's no GPU adapter the game can render with.
+    NoGpuAdapter,
+    /// Another copy of the game is running, and the package only allows one.
+    AlreadyRunning,
+}
+
+impl fmt::Display for RuntimeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::MissingPackage { path } => write!(
+                f,
+                "{} was not found, which is required to start the game",
+                path.display()
+            ),
+            Self::Io { path, source } => write!(f, "unable to read {}: {}", path.display(), source),
+            Self::Decompress { codec, source } => {
+                write!(f, "unable to decompress {:?} package: {}", codec, source)
+            }
+            Self::VersionMismatch { package_version } => write!(
+                f,
+                "package format version {} is not supported (expected {})",
+                package_version, RUNTIME_FORMAT_VERSION
+            ),
+            Self::Utf8Decode(e) => write!(f, "package contains invalid text: {}", e),
+            Self::Decode(e) => write!(f, "unable to decode package: {}", e),
+            Self::MissingAssets(missing) => {
+                write!(f, "{} assets are missing from the package", missing.len())
+            }
+            Self::TooManyEntities(oversized) => write!(
+                f,
+                "{} scenes have more entities than allowed",
+                oversized.len()
+            ),
+            Self::UnknownScene { scene, scenes } => write!(
+                f,
+                "there is no scene called '{}' in the package (it has {})",
+                scene,
+                scenes.join(", ")
+            ),
+            Self::NoGpuAdapter => write!(f, "no compatible GPU adapter was found"),
+            Self::AlreadyRunning => write!(f, "another copy of the game is already running"),
+        }
+    }
+}
+
+impl std::error::Error for RuntimeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } | Self::Decompress { source, .. } => Some(source),
+            Self::Utf8Decode(e) | Self::Decode(e) => Some(e),
+            Self::MissingPackage { .. }
+            | Self::VersionMismatch { .. }
+            | Self::MissingAssets(_)
+            | Self::TooManyEntities(_)
+            | Self::UnknownScene { .. }
+            | Self::NoGpuAdapter
+            | Self::AlreadyRunning => None,
+        }
+    }
+}
+
+impl From<DecodeError> for RuntimeError {
+    fn from(e: DecodeError) -> Self {
+        if matches!(e, DecodeError::Utf8 { .. }) {
+            Self::Utf8Decode(e)
+        } else {
+            Self::Decode(e)
+        }
+    }
+}
+
+impl RuntimeError {
+    /// The text for the error dialog shown to the player.
+    pub fn dialog_message(&self, log_location: &str) -> String {
+        match self {
+            Self::VersionMismatch { package_version } => {
+                version_mismatch_message(*package_version, log_location)
+            }
+            Self::Utf8Decode(_) => damaged_package_message(log_location),
+            Self::MissingAssets(missing) => missing_assets_message(missing, log_location),
+            Self::TooManyEntities(oversized) => too_many_entities_message(oversized, log_location),
+            Self::NoGpuAdapter => no_adapter_message(log_location),
+            Self::AlreadyRunning => already_running_message(),
+            _ => format!(
+                "Error loading package: {}\n\nPlease report this to the game developer! \
+                Logs are attached in {}, so send that to them too!",
+                self, log_location
+            ),
+        }
+    }
+}
+
+/// Reads and decodes the package at `path`.
+pub fn load_package(path: &Path) -> Result<(PackageConfig, RuntimeData), RuntimeError> {
+    if !path.exists() {
+        return Err(RuntimeError::MissingPackage {
+            path: path.to_path_buf(),
+        });
+    }
+
+    let bytes = std::fs::read(path).map_err(|source| RuntimeError::Io {
+        path: path.to_path_buf(),
+        source,
+    })?;
+    decode_package(&bytes)
+}
+
+/// Decompresses and decodes a whole package, checking its header before anything else.
+pub fn decode_package(bytes: &[u8]) -> Result<(PackageConfig, RuntimeData), RuntimeError> {
+    let codec = Codec::detect(bytes);
+    log::debug!("Package codec: {:?}", codec);
+    let bytes = codec
+        .decompress(bytes)
+        .map_err(|source| RuntimeError::Decompress { codec, source })?;
+
+    let (header, header_len) = decode_header(&bytes)?;
+    if !header.is_compatible() {
+        return Err(RuntimeError::VersionMismatch {
+            package_version: header.format_version,
+        });
+    }
+
+    Ok(decode_payload(&bytes[header_len..])?)
+}
+
+/// The text shown to the player when the package was built for a different runtime.
+pub fn version_mismatch_message(package_version: u32, log_location: &str) -> String {
+    format!(
+        "Your game .eupak package was built for package format version {}, but this redback-runtime executable \
+        reads format version {}. \n\nPlease either update your game, use a redback-runtime version that matches the package \
+        or report this issue to the developer. \n\n\
+        Logs are attached in {}, so send that to them too! \
+        \n\nGood Luck...",
+        package_version, RUNTIME_FORMAT_VERSION, log_location
+    )
+}
+
+/// The text shown to the player when scenes refer to files that aren't there.
+pub fn missing_assets_message(missing: &[MissingAsset], log_location: &str) -> String {
+    let list: Vec<String> = missing
+        .iter()
+        .map(|asset| format!("  - {}", asset))
+        .collect();
+    format!(
+        "Your game is missing {} files it needs to run:\n\n{}\n\nPlease try reinstalling your game or report \
+        this issue to the developer. \n\nLogs are attached in {}, so send that to them too!",
+        missing.len(),
+        list.join("\n"),
+        log_location
+    )
+}
+
+/// The text shown to the player when scenes are too big to load.
+pub fn too_many_entities_message(oversized: &[OversizedScene], log_location: &str) -> String {
+    let list: Vec<String> = oversized
+        .iter()
+        .map(|scene| format!("  - {}", scene))
+        .collect();
+    format!(
+        "Your game has scenes too big to load:\n\n{}\n\nPlease report this issue to the developer. \
+        \n\nLogs are attached in {}, so send that to them too!",
+        list.join("\n"),
+        log_location
+    )
+}
+
+/// The text shown to the player when there's no GPU to render with.
+pub fn no_adapter_message(log_location: &str) -> String {
+    format!(
+        "No graphics device that can run this game was found. The game needs a GPU with Vulkan, Metal, \
+        DirectX 12 or OpenGL support. \n\nPlease try updating your graphics drivers, or report this issue \
+        to the developer if your device should be supported. \n\nLogs are attached in {}, so send that \
+        to them too!",
+        log_location
+    )
+}
+
+/// The text shown to the player when they start the game while it's already running.
+pub fn already_running_message() -> String {
+    "The game is already running. \n\nSwitch to the window that's already open, or close it before \
+    starting the game again."
+        .to_string()
+}
+
+/// The text shown to the player when the package matches this runtime but still can't be read.
+pub fn damaged_package_message(log_location: &str) -> String {
+    format!(
+        "Your game .eupak package is damaged and cannot be read, even though it was built for this \
+        redback-runtime version. \n\nPlease try reinstalling your game or report this issue to the developer. \n\n\
+        Logs are attached in {}, so send that to them too! \
+        \n\nGood Luck...",
+        log_location
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(format_version: u32) -> Vec<u8> {
+        bincode::encode_to_vec(
+            PackageHeader { format_version },
+            bincode::config::standard(),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn header_decodes_on_its_own() {
+        let mut bytes = header(RUNTIME_FORMAT_VERSION);
+        let len = bytes.len();
+        bytes.extend([0xff; 8]);
+        let (decoded, read) = decode_header(&bytes).unwrap();
+        assert_eq!(decoded.format_version, RUNTIME_FORMAT_VERSION);
+        assert_eq!(read, len);
+    }
+
+    #[test]
+    fn only_this_runtimes_version_is_compatible() {
+        for format_version in [0, RUNTIME_FORMAT_VERSION + 1, u32::MAX] {
+            assert!(!PackageHeader { format_version }.is_compatible());
+        }
+        assert!(
+            PackageHeader {
+                format_version: RUNTIME_FORMAT_VERSION
+            }
+            .is_compatible()
+        );
+    }
+
+    #[test]
+    fn other_versions_are_refused_before_the_payload() {
+        // the payload is garbage, which would fail to decode if it got that far
+        let mut bytes = header(RUNTIME_FORMAT_VERSION + 1);
+        bytes.extend([0xff; 8]);
+        assert!(matches!(
+            decode_package(&bytes),
+            Err(RuntimeError::VersionMismatch { package_version })
+                if package_version == RUNTIME_FORMAT_VERSION + 1
+        ));
+    }
+
+    #[test]
+    fn dialogs_point_at_the_logs() {
+        let location = "/home/player/logs/redback-runtime.log";
+        for message in [
+            version_mismatch_message(RUNTIME_FORMAT_VERSION + 1, location),
+            damaged_package_message(location),
+            RuntimeError::MissingPackage {
+                path: PathBuf::from("my-game.eupak"),
+            }
+            .dialog_message(location),
+        ] {
+            assert!(message.contains(location), "{}", message);
+            assert!(!message.contains("PLACEHOLDER"), "{}", message);
+        }
+    }
+
+    #[test]
+    fn missing_packages_are_missing() {
+        let path = std::env::temp_dir().join("redback-no-such-package.eupak");
+        assert!(matches!(
+            load_package(&path),
+            Err(RuntimeError::MissingPackage { path: missing }) if missing == path
+        ));
+    }
+
+    #[test]
+    fn unreadable_packages_are_io_errors() {
+        // a directory exists, but can't be read like a file
+        let path = std::env::temp_dir().join(format!("redback-package-dir-{}", std::process::id()));
+        std::fs::create_dir_all(&path).unwrap();
+        let result = load_package(&path);
+        std::fs::remove_dir_all(&path).unwrap();
+        assert!(matches!(result, Err(RuntimeError::Io { .. })));
+    }
+
+    #[test]
+    fn broken_compressed_packages_fail_to_decompress() {
+        let bytes = [0x28, 0xB5, 0x2F, 0xFD, 0x00, 0x01, 0x02];
+        assert!(matches!(
+            decode_package(&bytes),
+            Err(RuntimeError::Decompress {
+                codec: Codec::Zstd,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn invalid_text_is_reported_as_damage() {
+        // the window title, the first thing in the config, as a string of invalid UTF-8
+        let mut bytes = header(RUNTIME_FORMAT_VERSION);
+        bytes.extend([1, 2, 0xff, 0xfe]);
+        let error = decode_package(&bytes).unwrap_err();
+        assert!(matches!(error, RuntimeError::Utf8Decode(_)), "{:?}", error);
+        assert!(error.dialog_message("logs").contains("damaged"));
+    }
+
+    #[test]
+    fn truncated_packages_fail_to_decode() {
+        let error = decode_package(&header(RUNTIME_FORMAT_VERSION)).unwrap_err();
+        assert!(matches!(error, RuntimeError::Decode(_)), "{:?}", error);
+        assert!(std::error::Error::source(&error).is_some());
+    }
+
+    #[test]
+    fn compressed_packages_are_decoded_through_the_same_checks() {
+        let mut bytes = header(RUNTIME_FORMAT_VERSION + 1);
+        bytes.extend([0xff; 8]);
+        let compressed = zstd::encode_all(bytes.as_slice(), 0).unwrap();
+        assert!(matches!(
+            decode_package(&compressed),
+            Err(RuntimeError::VersionMismatch { .. })
+        ));
+    }
+}
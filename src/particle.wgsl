@@ -0,0 +1,56 @@
+// Shader for drawing particles as quads turned towards the camera
+
+struct ParticleCamera {
+    view_proj: mat4x4<f32>,
+    // the camera's right and up in world space, for turning the quads to face it
+    right: vec4<f32>,
+    up: vec4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> camera: ParticleCamera;
+
+struct InstanceInput {
+    // xyz is the particle's position, w its size
+    @location(0) position_size: vec4<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+    // two triangles making a quad from -0.5 to 0.5
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-0.5, -0.5),
+        vec2<f32>(0.5, -0.5),
+        vec2<f32>(0.5, 0.5),
+        vec2<f32>(-0.5, -0.5),
+        vec2<f32>(0.5, 0.5),
+        vec2<f32>(-0.5, 0.5),
+    );
+    let corner = corners[index];
+    let size = instance.position_size.w;
+    let position = instance.position_size.xyz
+        + camera.right.xyz * corner.x * size
+        + camera.up.xyz * corner.y * size;
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(position, 1.0);
+    out.color = instance.color;
+    out.uv = corner;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // fade out towards the edges, so particles come out round rather than square
+    let falloff = 1.0 - smoothstep(0.3, 0.5, length(in.uv));
+    if falloff <= 0.0 {
+        discard;
+    }
+    return vec4<f32>(in.color.rgb, in.color.a * falloff);
+}
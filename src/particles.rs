@@ -0,0 +1,399 @@
+// Particle effects like smoke and sparks.
+//
+// Scenes give a model an emitter through its model settings. A `ParticleEmitter` on an entity
+// spawns particles from wherever the entity is, at a steady
+// rate. Every frame the particles move along with their velocity and are removed once they've
+// lived for the emitter's lifetime. They're drawn after the scene's models as camera facing
+// quads, blended over what's behind them without writing depth.
+//
+// There's a limit on particles across the whole world, after which emitters stop spawning new
+// ones until old ones have died off.
+
+use dropbear_engine::{
+    camera::Camera,
+    entity::Transform,
+    graphics::{Graphics, Texture},
+    wgpu,
+};
+use glam::{DMat4, DVec3};
+use hecs::World;
+
+use crate::{
+    hierarchy::{self, GlobalTransform},
+    pipeline::PipelineOptions,
+    visibility::{self, Enabled, Visible},
+};
+
+/// How many particles can be alive at once across the world when the package doesn't say.
+pub const DEFAULT_MAX_PARTICLES: u32 = 10_000;
+
+/// The bytes a particle takes up in the instance buffer: its position and size, then its colour.
+const PARTICLE_SIZE: usize = 8 * std::mem::size_of::<f32>();
+
+/// A single particle, in world space.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Particle {
+    pub position: DVec3,
+    pub velocity: DVec3,
+    /// How long the particle has been alive, in seconds.
+    pub age: f64,
+}
+
+/// Spawns particles from the entity's position.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParticleEmitter {
+    /// How many particles are spawned a second.
+    pub rate: f64,
+    /// How long each particle lives, in seconds.
+    pub lifetime: f64,
+    /// The velocity particles start with, in world units a second.
+    pub velocity: DVec3,
+    /// How far each axis of a particle's starting velocity can stray from `velocity`, so they
+    /// don't all move in a line.
+    pub spread: f64,
+    /// The width and height of each particle's quad, in world units.
+    pub size: f32,
+    /// The RGBA colour particles are drawn with.
+    pub color: [f32; 4],
+    /// Whether new particles are spawned. Particles already alive live out their lifetime
+    /// either way.
+    pub emitting: bool,
+    particles: Vec<Particle>,
+    /// The part of a particle that's built up towards the next spawn.
+    pending: f64,
+    seed: u32,
+}
+
+impl Default for ParticleEmitter {
+    fn default() -> Self {
+        Self {
+            rate: 10.0,
+            lifetime: 1.0,
+            velocity: DVec3::Y,
+            spread: 0.0,
+            size: 0.1,
+            color: [1.0; 4],
+            emitting: true,
+            particles: Vec::new(),
+            pending: 0.0,
+            seed: 0x9e37_79b9,
+        }
+    }
+}
+
+impl ParticleEmitter {
+    /// Moves the emitter's particles on by `dt` seconds and removes the ones past their lifetime.
+    fn age(&mut self, dt: f64) {
+        let lifetime = self.lifetime;
+        self.particles.retain_mut(|particle| {
+            particle.age += dt;
+            particle.position += particle.velocity * dt;
+            particle.age < lifetime
+        });
+    }
+
+    /// Spawns the particles due over `dt` seconds at `origin`, no more than `budget` of them.
+    /// Returns how many were spawned.
+    fn emit(&mut self, origin: DVec3, dt: f64, budget: usize) -> usize {
+        if !self.emitting || self.rate <= 0.0 || self.lifetime <= 0.0 {
+            self.pending = 0.0;
+            return 0;
+        }
+        self.pending += self.rate * dt;
+        let due = self.pending.floor();
+        self.pending -= due;
+
+        let count = (due as usize).min(budget);
+        for _ in 0..count {
+            let jitter = DVec3::new(self.random(), self.random(), self.random()) * self.spread;
+            self.particles.push(Particle {
+                position: origin,
+                velocity: self.velocity + jitter,
+                age: 0.0,
+            });
+        }
+        count
+    }
+
+    /// A number from -1 to 1, from a xorshift of the emitter's seed.
+    fn random(&mut self) -> f64 {
+        self.seed ^= self.seed << 13;
+        self.seed ^= self.seed >> 17;
+        self.seed ^= self.seed << 5;
+        self.seed as f64 / u32::MAX as f64 * 2.0 - 1.0
+    }
+}
+
+/// Moves every enabled emitter's particles on by `dt` seconds, then spawns new ones without going
+/// over `max_particles` across the world.
+pub fn update(world: &mut World, dt: f64, max_particles: usize) {
+    let mut alive = 0;
+    for (_, (emitter, enabled)) in world.query_mut::<(&mut ParticleEmitter, Option<&Enabled>)>() {
+        if visibility::is_updated(enabled) {
+            emitter.age(dt);
+        }
+        alive += emitter.particles.len();
+    }
+
+    let query = world.query_mut::<(
+        &mut ParticleEmitter,
+        &Transform,
+        Option<&GlobalTransform>,
+        Option<&Enabled>,
+    )>();
+    for (_, (emitter, transform, global, enabled)) in query {
+        if !visibility::is_updated(enabled) {
+            continue;
+        }
+        let origin = hierarchy::world_transform(transform, global).position;
+        alive += emitter.emit(origin, dt, max_particles.saturating_sub(alive));
+    }
+}
+
+/// Draws the particles of every visible emitter.
+pub struct ParticleRenderer {
+    pipeline: wgpu::RenderPipeline,
+    uniform: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    instance_buffer: Option<wgpu::Buffer>,
+    count: u32,
+}
+
+impl ParticleRenderer {
+    /// Creates the pipeline drawing particles into a target set up as in `options`.
+    pub fn new(graphics: &Graphics, options: &PipelineOptions) -> Self {
+        let device = &graphics.state.device;
+
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Particle Uniform"),
+            // the view projection, then the camera's right and up
+            size: std::mem::size_of::<[f32; 24]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Particle Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Particle Bind Group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Particle Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("particle.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Particle Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Particle Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: PARTICLE_SIZE as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: options.color_format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // hidden behind models, but never hiding each other
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: options.sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            uniform,
+            bind_group,
+            instance_buffer: None,
+            count: 0,
+        }
+    }
+
+    /// Uploads the particles of every visible emitter in `world`, to be drawn as seen by `camera`
+    /// through `view_proj`.
+    pub fn update(
+        &mut self,
+        graphics: &Graphics,
+        world: &World,
+        camera: &Camera,
+        view_proj: DMat4,
+    ) {
+        let forward = (camera.target - camera.eye).normalize_or(DVec3::NEG_Z);
+        let right = forward.cross(camera.up).normalize_or(DVec3::X);
+        let up = right.cross(forward);
+        let mut uniform: Vec<f32> = view_proj.as_mat4().to_cols_array().to_vec();
+        uniform.extend(right.as_vec3().extend(0.0).to_array());
+        uniform.extend(up.as_vec3().extend(0.0).to_array());
+        graphics
+            .state
+            .queue
+            .write_buffer(&self.uniform, 0, &to_bytes(&uniform));
+
+        let mut instances = Vec::new();
+        let mut query = world.query::<(&ParticleEmitter, Option<&Enabled>, Option<&Visible>)>();
+        for (_, (emitter, enabled, visible)) in query.iter() {
+            if !visibility::is_drawn(enabled, visible) {
+                continue;
+            }
+            for particle in &emitter.particles {
+                instances.extend(particle.position.as_vec3().to_array());
+                instances.push(emitter.size);
+                instances.extend(emitter.color);
+            }
+        }
+        self.count = (instances.len() * std::mem::size_of::<f32>() / PARTICLE_SIZE) as u32;
+        if instances.is_empty() {
+            return;
+        }
+
+        let bytes = to_bytes(&instances);
+        let needed = bytes.len() as u64;
+        if self
+            .instance_buffer
+            .as_ref()
+            .is_none_or(|buffer| buffer.size() < needed)
+        {
+            self.instance_buffer = Some(graphics.state.device.create_buffer(
+                &wgpu::BufferDescriptor {
+                    label: Some("Particle Instance Buffer"),
+                    size: needed.next_power_of_two(),
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                },
+            ));
+        }
+        if let Some(buffer) = &self.instance_buffer {
+            graphics.state.queue.write_buffer(buffer, 0, &bytes);
+        }
+    }
+
+    /// Draws the particles uploaded by the last [`Self::update`], returning whether there were
+    /// any to draw. This should come after the scene's models in the pass.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass<'_>) -> bool {
+        let Some(instance_buffer) = self.instance_buffer.as_ref().filter(|_| self.count > 0) else {
+            return false;
+        };
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.count);
+        true
+    }
+}
+
+fn to_bytes(values: &[f32]) -> Vec<u8> {
+    values
+        .iter()
+        .flat_map(|value| value.to_ne_bytes())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn emitter_at(world: &mut World, emitter: ParticleEmitter) -> hecs::Entity {
+        world.spawn((emitter, Transform::default()))
+    }
+
+    #[test]
+    fn particles_spawn_at_the_emitter_rate() {
+        let mut emitter = ParticleEmitter::default();
+        assert_eq!(emitter.emit(DVec3::ZERO, 0.25, usize::MAX), 2);
+        // the half a particle left over comes through on the next frame
+        assert_eq!(emitter.emit(DVec3::ZERO, 0.05, usize::MAX), 1);
+        assert_eq!(emitter.particles.len(), 3);
+    }
+
+    #[test]
+    fn particles_move_and_die_of_old_age() {
+        let mut emitter = ParticleEmitter::default();
+        emitter.emit(DVec3::ZERO, 0.1, usize::MAX);
+        emitter.age(0.5);
+        assert_eq!(emitter.particles[0].position, DVec3::new(0.0, 0.5, 0.0));
+        emitter.age(0.5);
+        assert!(emitter.particles.is_empty());
+    }
+
+    #[test]
+    fn stopped_emitters_spawn_nothing() {
+        let mut emitter = ParticleEmitter {
+            emitting: false,
+            ..Default::default()
+        };
+        assert_eq!(emitter.emit(DVec3::ZERO, 10.0, usize::MAX), 0);
+    }
+
+    #[test]
+    fn the_world_never_goes_over_its_particle_limit() {
+        let mut world = World::new();
+        let first = emitter_at(&mut world, ParticleEmitter::default());
+        let second = emitter_at(&mut world, ParticleEmitter::default());
+        update(&mut world, 0.5, 8);
+        let count = |entity| {
+            world
+                .get::<&ParticleEmitter>(entity)
+                .unwrap()
+                .particles
+                .len()
+        };
+        assert_eq!(count(first) + count(second), 8);
+    }
+
+    #[test]
+    fn disabled_emitters_stand_still() {
+        let mut world = World::new();
+        let entity = emitter_at(&mut world, ParticleEmitter::default());
+        world.insert_one(entity, Enabled(false)).unwrap();
+        update(&mut world, 1.0, 100);
+        assert!(
+            world
+                .get::<&ParticleEmitter>(entity)
+                .unwrap()
+                .particles
+                .is_empty()
+        );
+    }
+}
@@ -0,0 +1,277 @@
+// Pausing the game, which freezes scripts and gameplay while the scene keeps rendering.
+//
+// While paused, the runtime shows its own menu over the scene with buttons to resume, to change
+// the player's volumes and to quit, unless the package turns it off to draw a menu of its own. The
+// menu is drawn through the UI and text passes like any other UI, and can be worked with the
+// keyboard, the mouse or a gamepad. Volumes are turned down and up with left and right, and
+// pressing a volume turns it up a step, going back to silent after full volume.
+
+use dropbear_engine::scene::SceneCommand;
+
+use crate::{
+    config::PauseMenuSettings,
+    preferences::{VolumeChannel, VolumeSettings},
+    text::TextRequest,
+    ui::{Anchor, UiElement, UiLength},
+};
+
+/// A button on the pause menu.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuItem {
+    Resume,
+    Volume(VolumeChannel),
+    Quit,
+}
+
+impl MenuItem {
+    /// Every button, from the top of the menu down.
+    pub const ALL: [MenuItem; 5] = [
+        MenuItem::Resume,
+        MenuItem::Volume(VolumeChannel::Master),
+        MenuItem::Volume(VolumeChannel::Music),
+        MenuItem::Volume(VolumeChannel::Sfx),
+        MenuItem::Quit,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Resume => "Resume",
+            Self::Volume(VolumeChannel::Master) => "Volume",
+            Self::Volume(VolumeChannel::Music) => "Music",
+            Self::Volume(VolumeChannel::Sfx) => "Effects",
+            Self::Quit => "Quit",
+        }
+    }
+
+    /// The text on the button, which for volumes includes the volume at the moment.
+    pub fn text(&self, volumes: &VolumeSettings) -> String {
+        match self {
+            Self::Volume(channel) => format!(
+                "{}: {}%",
+                self.label(),
+                (volumes.volume(*channel) * 100.0).round()
+            ),
+            _ => self.label().to_string(),
+        }
+    }
+
+    /// The command pressing the button sends the engine, if it sends one.
+    pub fn scene_command(&self) -> Option<SceneCommand> {
+        match self {
+            Self::Resume | Self::Volume(_) => None,
+            Self::Quit => Some(SceneCommand::Quit),
+        }
+    }
+}
+
+/// The size of every button, in pixels.
+const BUTTON_SIZE: [f32; 2] = [280.0, 56.0];
+/// How far apart the tops of neighbouring buttons are, in pixels.
+const BUTTON_SPACING: f32 = 72.0;
+
+/// Which button of the pause menu is selected.
+#[derive(Debug, Default)]
+pub struct PauseMenu {
+    selected: usize,
+    /// Whether the cursor was locked when the menu opened, so it's locked again on resuming.
+    relock_cursor: bool,
+}
+
+impl PauseMenu {
+    /// Selects the first button, as the menu opens with the cursor `cursor_locked` or not.
+    pub fn open(&mut self, cursor_locked: bool) {
+        self.selected = 0;
+        self.relock_cursor = cursor_locked;
+    }
+
+    /// Whether the cursor has to be locked again now that the menu has closed.
+    pub fn close(&mut self) -> bool {
+        std::mem::take(&mut self.relock_cursor)
+    }
+
+    pub fn selected(&self) -> MenuItem {
+        MenuItem::ALL[self.selected]
+    }
+
+    /// Moves the selection `offset` buttons down, wrapping around at either end.
+    pub fn move_selection(&mut self, offset: isize) {
+        let count = MenuItem::ALL.len() as isize;
+        self.selected = (self.selected as isize + offset).rem_euclid(count) as usize;
+    }
+
+    pub fn select(&mut self, item: MenuItem) {
+        if let Some(index) = MenuItem::ALL.iter().position(|other| *other == item) {
+            self.selected = index;
+        }
+    }
+
+    /// The button under `cursor` on a screen `width` by `height` pixels, if there is one.
+    pub fn item_at(&self, cursor: (f64, f64), width: u32, height: u32) -> Option<MenuItem> {
+        let (x, y) = (cursor.0 as f32, cursor.1 as f32);
+        MenuItem::ALL
+            .into_iter()
+            .enumerate()
+            .find_map(|(index, item)| {
+                let rect = button(index).rect(width, height);
+                let inside = x >= rect.x
+                    && x < rect.x + rect.width
+                    && y >= rect.y
+                    && y < rect.y + rect.height;
+                inside.then_some(item)
+            })
+    }
+
+    /// The elements and text drawing the menu, with the player's `volumes`, on a screen `width`
+    /// by `height` pixels.
+    pub fn layout(
+        &self,
+        settings: &PauseMenuSettings,
+        volumes: &VolumeSettings,
+        width: u32,
+        height: u32,
+    ) -> (Vec<UiElement>, Vec<TextRequest>) {
+        let mut elements = vec![UiElement {
+            size: [UiLength::Fraction(1.0); 2],
+            color: settings.backdrop_color(),
+            ..Default::default()
+        }];
+        let mut text = Vec::new();
+        let text_size = settings.text_size();
+        for (index, item) in MenuItem::ALL.into_iter().enumerate() {
+            let mut element = button(index);
+            element.color = if index == self.selected {
+                settings.selected_color()
+            } else {
+                settings.button_color()
+            };
+            let rect = element.rect(width, height);
+            elements.push(element);
+
+            // the runtime can't measure text, so labels are left aligned in their button
+            text.push(TextRequest {
+                text: item.text(volumes),
+                position: [
+                    rect.x + text_size * 0.75,
+                    rect.y + (rect.height - text_size) / 2.0,
+                ],
+                size: text_size,
+                color: settings.text_color(),
+            });
+        }
+        (elements, text)
+    }
+}
+
+/// The element of the button at `index`, with the buttons centred on the screen as a column.
+fn button(index: usize) -> UiElement {
+    let middle = (MenuItem::ALL.len() - 1) as f32 / 2.0;
+    UiElement {
+        anchor: Anchor::Center,
+        offset: [
+            UiLength::Pixels(0.0),
+            UiLength::Pixels((index as f32 - middle) * BUTTON_SPACING),
+        ],
+        size: BUTTON_SIZE.map(UiLength::Pixels),
+        order: 1,
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_menu_opens_on_the_first_button_and_relocks_the_cursor_once() {
+        let mut menu = PauseMenu::default();
+        menu.open(true);
+        menu.move_selection(1);
+        menu.open(true);
+        assert_eq!(menu.selected(), MenuItem::Resume);
+        assert!(menu.close());
+        assert!(!menu.close());
+
+        menu.open(false);
+        assert!(!menu.close());
+    }
+
+    #[test]
+    fn the_selection_wraps_around() {
+        let mut menu = PauseMenu::default();
+        menu.move_selection(-1);
+        assert_eq!(menu.selected(), MenuItem::Quit);
+        menu.move_selection(1);
+        assert_eq!(menu.selected(), MenuItem::Resume);
+        menu.move_selection(7);
+        assert_eq!(menu.selected(), MenuItem::Volume(VolumeChannel::Music));
+
+        menu.select(MenuItem::Resume);
+        assert_eq!(menu.selected(), MenuItem::Resume);
+    }
+
+    #[test]
+    fn the_buttons_are_found_under_the_cursor() {
+        let menu = PauseMenu::default();
+        // the buttons are 280 by 56 pixels, centred on the screen 72 pixels apart
+        let item_at = |x, y| menu.item_at((x, y), 800, 600);
+        assert_eq!(item_at(400.0, 156.0), Some(MenuItem::Resume));
+        assert_eq!(item_at(260.0, 128.0), Some(MenuItem::Resume));
+        assert_eq!(
+            item_at(400.0, 300.0),
+            Some(MenuItem::Volume(VolumeChannel::Music))
+        );
+        assert_eq!(item_at(539.0, 471.0), Some(MenuItem::Quit));
+        assert_eq!(item_at(400.0, 190.0), None);
+        assert_eq!(item_at(540.0, 300.0), None);
+        assert_eq!(item_at(0.0, 0.0), None);
+    }
+
+    #[test]
+    fn the_layout_has_a_backdrop_and_a_labelled_button_per_item() {
+        let settings = PauseMenuSettings::default();
+        let mut volumes = VolumeSettings::default();
+        volumes.step(VolumeChannel::Music, -6);
+        let mut menu = PauseMenu::default();
+        menu.select(MenuItem::Quit);
+        let (elements, text) = menu.layout(&settings, &volumes, 800, 600);
+
+        assert_eq!(elements.len(), MenuItem::ALL.len() + 1);
+        assert_eq!(elements[0].color, settings.backdrop_color());
+        assert_eq!(elements[1].color, settings.button_color());
+        assert_eq!(elements[5].color, settings.selected_color());
+
+        let labels: Vec<_> = text.iter().map(|text| text.text.as_str()).collect();
+        assert_eq!(
+            labels,
+            [
+                "Resume",
+                "Volume: 100%",
+                "Music: 40%",
+                "Effects: 100%",
+                "Quit"
+            ]
+        );
+        // each label sits inside its button
+        for (element, text) in elements[1..].iter().zip(&text) {
+            let rect = element.rect(800, 600);
+            assert!(text.position[0] > rect.x && text.position[0] < rect.x + rect.width);
+            assert!(text.position[1] > rect.y && text.position[1] < rect.y + rect.height);
+        }
+    }
+
+    #[test]
+    fn only_quit_sends_the_engine_a_command() {
+        let mut menu = PauseMenu::default();
+        menu.move_selection(-1);
+        assert!(matches!(
+            menu.selected().scene_command(),
+            Some(SceneCommand::Quit)
+        ));
+        assert!(MenuItem::Resume.scene_command().is_none());
+        assert!(
+            MenuItem::Volume(VolumeChannel::Master)
+                .scene_command()
+                .is_none()
+        );
+    }
+}
@@ -0,0 +1,279 @@
+// Creation of the runtime's entity and light pipelines.
+//
+// The engine's pipeline helpers always render straight to the surface, so the runtime builds its
+// own pipelines wherever it needs a say in how they're set up, such as rendering into an HDR
+// target for post-processing.
+//
+// Entity pipelines also come in one per way of culling triangles that the scene's models use, and
+// for render layers drawn without depth testing, since both are fixed when a pipeline is created.
+
+use bincode::{Decode, Encode};
+use dropbear_engine::{
+    entity::InstanceRaw,
+    graphics::{Graphics, Texture},
+    model::{ModelVertex, Vertex},
+    wgpu,
+};
+
+/// Which of a model's faces are left undrawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Encode, Decode)]
+pub enum CullMode {
+    /// Faces pointing away from the camera, which can't be seen on closed models anyway.
+    #[default]
+    Back,
+    /// Faces pointing towards the camera, for models seen from the inside.
+    Front,
+    /// Draw both sides, for flat models like leaves and cloth.
+    None,
+}
+
+/// Which way round a face's vertices go when it points towards the camera.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Encode, Decode)]
+pub enum Winding {
+    #[default]
+    CounterClockwise,
+    Clockwise,
+}
+
+/// How a model's triangles are culled. Entities carrying one are drawn with the entity pipeline
+/// created for it, and the rest with the default of back faces culled and counter-clockwise
+/// fronts.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct Culling {
+    pub mode: CullMode,
+    pub front_face: Winding,
+}
+
+impl Culling {
+    /// The primitive state a pipeline culling like this is created with.
+    pub fn primitive_state(&self) -> wgpu::PrimitiveState {
+        wgpu::PrimitiveState {
+            topology: wgpu::PrimitiveTopology::TriangleList,
+            front_face: match self.front_face {
+                Winding::CounterClockwise => wgpu::FrontFace::Ccw,
+                Winding::Clockwise => wgpu::FrontFace::Cw,
+            },
+            cull_mode: match self.mode {
+                CullMode::Back => Some(wgpu::Face::Back),
+                CullMode::Front => Some(wgpu::Face::Front),
+                CullMode::None => None,
+            },
+            ..Default::default()
+        }
+    }
+}
+
+/// The primitive state of a pipeline drawing models' triangles as outlines, with nothing culled
+/// so the back of the model shows through. Only devices with
+/// [`wgpu::Features::POLYGON_MODE_LINE`] enabled can create it.
+pub fn wireframe_primitive_state() -> wgpu::PrimitiveState {
+    wgpu::PrimitiveState {
+        topology: wgpu::PrimitiveTopology::TriangleList,
+        cull_mode: None,
+        polygon_mode: wgpu::PolygonMode::Line,
+        ..Default::default()
+    }
+}
+
+/// How a pipeline renders, on top of its shader and bind groups.
+#[derive(Debug, Clone, Copy)]
+pub struct PipelineOptions {
+    /// The format of the colour target being rendered into.
+    pub color_format: wgpu::TextureFormat,
+    /// The MSAA sample count of the target, 1 for no MSAA.
+    pub sample_count: u32,
+}
+
+/// The sample counts MSAA can run at, from highest to lowest.
+pub const SAMPLE_COUNTS: [u32; 4] = [8, 4, 2, 1];
+
+/// The highest sample count no more than `requested` that `supported` allows. A single sample
+/// always works, so that's what it ends up as if nothing else does.
+pub fn nearest_sample_count(requested: u32, supported: impl Fn(u32) -> bool) -> u32 {
+    SAMPLE_COUNTS
+        .into_iter()
+        .find(|count| *count <= requested && supported(*count))
+        .unwrap_or(1)
+}
+
+/// The sample count to render `format` at, falling back to a lower count when the device doesn't
+/// support `requested`.
+pub fn sample_count(graphics: &Graphics, format: wgpu::TextureFormat, requested: u32) -> u32 {
+    let flags = graphics
+        .state
+        .adapter
+        .get_texture_format_features(format)
+        .flags;
+    let count = nearest_sample_count(requested, |count| flags.sample_count_supported(count));
+    if count != requested {
+        log::warn!(
+            "{}x MSAA isn't supported for {:?}, using {}x instead",
+            requested,
+            format,
+            count
+        );
+    }
+    count
+}
+
+/// Creates a pipeline drawing models with `shader_source` as set out by `primitive`, with a vertex
+/// buffer for the model's vertices and one for its instances. Pipelines that aren't
+/// `depth_tested` draw over everything and leave the depth buffer as it was.
+pub fn create_model_pipeline(
+    graphics: &Graphics,
+    label: &str,
+    shader_source: &str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    options: &PipelineOptions,
+    primitive: wgpu::PrimitiveState,
+    depth_tested: bool,
+) -> wgpu::RenderPipeline {
+    let device = &graphics.state.device;
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[ModelVertex::desc(), InstanceRaw::desc()],
+            compilation_options: Default::default(),
+        },
+        fragment: Some(wgpu::FragmentState {
+            module: &shader,
+            entry_point: Some("fs_main"),
+            targets: &[Some(wgpu::ColorTargetState {
+                format: options.color_format,
+                blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                write_mask: wgpu::ColorWrites::ALL,
+            })],
+            compilation_options: Default::default(),
+        }),
+        primitive,
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: depth_tested,
+            // equal depths pass too, so fragments the depth pre-pass already wrote get drawn
+            depth_compare: if depth_tested {
+                wgpu::CompareFunction::LessEqual
+            } else {
+                wgpu::CompareFunction::Always
+            },
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: options.sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// Creates a pipeline that only writes the depth of models drawn with `shader_source`'s
+/// `vs_main`, for the depth pre-pass. It has to be given the same bind group layouts as the
+/// pipeline drawing the models afterwards, so their depths come out the same.
+///
+/// There's no fragment stage, so parts of a model its fragment shader would discard still hide
+/// what's behind them.
+pub fn create_depth_pipeline(
+    graphics: &Graphics,
+    label: &str,
+    shader_source: &str,
+    bind_group_layouts: &[&wgpu::BindGroupLayout],
+    options: &PipelineOptions,
+) -> wgpu::RenderPipeline {
+    let device = &graphics.state.device;
+
+    let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(label),
+        source: wgpu::ShaderSource::Wgsl(shader_source.into()),
+    });
+    let layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+        label: Some(label),
+        bind_group_layouts,
+        push_constant_ranges: &[],
+    });
+
+    device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+        label: Some(label),
+        layout: Some(&layout),
+        vertex: wgpu::VertexState {
+            module: &shader,
+            entry_point: Some("vs_main"),
+            buffers: &[ModelVertex::desc(), InstanceRaw::desc()],
+            compilation_options: Default::default(),
+        },
+        fragment: None,
+        primitive: Culling::default().primitive_state(),
+        depth_stencil: Some(wgpu::DepthStencilState {
+            format: Texture::DEPTH_FORMAT,
+            depth_write_enabled: true,
+            depth_compare: wgpu::CompareFunction::Less,
+            stencil: wgpu::StencilState::default(),
+            bias: wgpu::DepthBiasState::default(),
+        }),
+        multisample: wgpu::MultisampleState {
+            count: options.sample_count,
+            ..Default::default()
+        },
+        multiview: None,
+        cache: None,
+    })
+}
+
+/// The layout of a single light's bind group, as bound by `light.wgsl`. It's built with the same
+/// entries as the engine's own, so the engine's light bind groups can be used with it.
+pub fn light_bind_group_layout(graphics: &Graphics) -> wgpu::BindGroupLayout {
+    graphics
+        .state
+        .device
+        .create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Light Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn supported_sample_counts_are_kept() {
+        assert_eq!(nearest_sample_count(4, |_| true), 4);
+        assert_eq!(nearest_sample_count(1, |_| true), 1);
+    }
+
+    #[test]
+    fn unsupported_sample_counts_fall_back_to_the_next_lowest() {
+        assert_eq!(nearest_sample_count(8, |count| count <= 4), 4);
+        assert_eq!(nearest_sample_count(4, |count| count == 1), 1);
+        // odd counts aren't a sample count at all
+        assert_eq!(nearest_sample_count(3, |_| true), 2);
+    }
+
+    #[test]
+    fn a_single_sample_always_works() {
+        assert_eq!(nearest_sample_count(8, |_| false), 1);
+    }
+}
@@ -0,0 +1,340 @@
+// Post-processing of the rendered scene.
+//
+// With post-processing or MSAA on, the scene is rendered into an offscreen HDR target instead of
+// the surface. A fullscreen pass then copies it onto the surface, tonemapping it down and applying
+// the exposure and gamma if post-processing is on. With MSAA, the scene is drawn into a
+// multisampled target first, which is resolved into the HDR target at the end of the pass. With
+// both off, the scene renders straight to the surface as it always has.
+//
+// The depth pre-pass needs a depth buffer of the runtime's own to fill ahead of the scene pass,
+// so it renders offscreen too.
+
+use dropbear_engine::{
+    graphics::{Graphics, Texture},
+    wgpu,
+};
+
+/// The format of the HDR target the scene is rendered into.
+pub const HDR_FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba16Float;
+
+/// How the offscreen target is set up and copied to the surface.
+#[derive(Debug, Clone, Copy)]
+pub struct PostSettings {
+    /// Whether to tonemap, as opposed to just copying the scene across.
+    pub tonemap: bool,
+    pub exposure: f32,
+    /// The MSAA sample count of the scene pass, 1 for no MSAA.
+    pub sample_count: u32,
+}
+
+/// The HDR target and the pipeline tonemapping it onto the surface.
+pub struct PostProcess {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    uniform: wgpu::Buffer,
+    sample_count: u32,
+    targets: Targets,
+}
+
+/// Everything that has to be the size of the surface.
+struct Targets {
+    size: (u32, u32),
+    /// The multisampled target drawn into with MSAA on, resolving into `hdr_view`.
+    msaa_view: Option<wgpu::TextureView>,
+    hdr_view: wgpu::TextureView,
+    depth_view: wgpu::TextureView,
+    bind_group: wgpu::BindGroup,
+}
+
+impl PostProcess {
+    pub fn new(graphics: &Graphics, settings: PostSettings) -> Self {
+        let device = &graphics.state.device;
+
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Post Process Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Post Process Uniform"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let apply_gamma = !graphics.state.config.format.is_srgb();
+        let mut bytes = Vec::with_capacity(16);
+        bytes.extend_from_slice(&settings.exposure.to_ne_bytes());
+        bytes.extend_from_slice(&u32::from(apply_gamma).to_ne_bytes());
+        bytes.extend_from_slice(&u32::from(settings.tonemap).to_ne_bytes());
+        bytes.resize(16, 0);
+        graphics.state.queue.write_buffer(&uniform, 0, &bytes);
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Post Process Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Post Process Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("post.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Post Process Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Post Process Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: graphics.state.config.format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // the surface pass comes with the engine's depth buffer, which this never touches
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let targets = Targets::new(graphics, &layout, &sampler, &uniform, settings.sample_count);
+        Self {
+            pipeline,
+            layout,
+            sampler,
+            uniform,
+            sample_count: settings.sample_count,
+            targets,
+        }
+    }
+
+    /// Recreates the HDR target if the surface changed size since the last frame.
+    pub fn prepare(&mut self, graphics: &Graphics) {
+        let size = surface_size(graphics);
+        if self.targets.size != size {
+            log::debug!("Resizing the HDR target to {}x{}", size.0, size.1);
+            self.targets = Targets::new(
+                graphics,
+                &self.layout,
+                &self.sampler,
+                &self.uniform,
+                self.sample_count,
+            );
+        }
+    }
+
+    /// Begins a pass that only fills the offscreen target's depth buffer, for the depth pre-pass.
+    pub fn begin_depth_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Depth Pre-pass"),
+            color_attachments: &[],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.targets.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    /// Begins the scene's render pass on the offscreen target, cleared to `clear_color`. The
+    /// depth buffer is kept as it is if `depth_filled`, after a depth pre-pass.
+    pub fn begin_scene_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        clear_color: wgpu::Color,
+        depth_filled: bool,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("HDR Scene Pass"),
+            color_attachments: &[Some(match &self.targets.msaa_view {
+                Some(msaa_view) => wgpu::RenderPassColorAttachment {
+                    view: msaa_view,
+                    resolve_target: Some(&self.targets.hdr_view),
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(clear_color),
+                        // only the resolved image is needed afterwards
+                        store: wgpu::StoreOp::Discard,
+                    },
+                },
+                None => wgpu::RenderPassColorAttachment {
+                    view: &self.targets.hdr_view,
+                    resolve_target: None,
+                    depth_slice: None,
+                    ops: wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(clear_color),
+                        store: wgpu::StoreOp::Store,
+                    },
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.targets.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: if depth_filled {
+                        wgpu::LoadOp::Load
+                    } else {
+                        wgpu::LoadOp::Clear(1.0)
+                    },
+                    store: wgpu::StoreOp::Store,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+
+    /// Copies the offscreen target into a render pass with the surface's format, such as the
+    /// surface's own or a screenshot's. The scene pass has to have been submitted first.
+    pub fn draw_resolve(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.targets.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+impl Targets {
+    fn new(
+        graphics: &Graphics,
+        layout: &wgpu::BindGroupLayout,
+        sampler: &wgpu::Sampler,
+        uniform: &wgpu::Buffer,
+        sample_count: u32,
+    ) -> Self {
+        let device = &graphics.state.device;
+        let size = surface_size(graphics);
+        let extent = wgpu::Extent3d {
+            width: size.0,
+            height: size.1,
+            depth_or_array_layers: 1,
+        };
+
+        let hdr = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("HDR Target"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: HDR_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let hdr_view = hdr.create_view(&wgpu::TextureViewDescriptor::default());
+        let msaa_view = (sample_count > 1).then(|| {
+            device
+                .create_texture(&wgpu::TextureDescriptor {
+                    label: Some("MSAA Target"),
+                    size: extent,
+                    mip_level_count: 1,
+                    sample_count,
+                    dimension: wgpu::TextureDimension::D2,
+                    format: HDR_FORMAT,
+                    usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                    view_formats: &[],
+                })
+                .create_view(&wgpu::TextureViewDescriptor::default())
+        });
+        let depth = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("HDR Depth"),
+            size: extent,
+            mip_level_count: 1,
+            sample_count,
+            dimension: wgpu::TextureDimension::D2,
+            format: Texture::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        });
+        let depth_view = depth.create_view(&wgpu::TextureViewDescriptor::default());
+
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Post Process Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&hdr_view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        });
+
+        Self {
+            size,
+            msaa_view,
+            hdr_view,
+            depth_view,
+            bind_group,
+        }
+    }
+}
+
+/// The size of the surface, never less than 1x1 so the targets are always valid.
+pub fn surface_size(graphics: &Graphics) -> (u32, u32) {
+    (
+        graphics.state.config.width.max(1),
+        graphics.state.config.height.max(1),
+    )
+}
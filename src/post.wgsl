@@ -0,0 +1,54 @@
+// Shader for copying the offscreen scene onto the surface, tonemapping it on the way
+
+struct Post {
+    exposure: f32,
+    // set when the surface doesn't convert to sRGB by itself
+    apply_gamma: u32,
+    // when not set, the scene is only clamped, for when the target is just there for MSAA
+    tonemap: u32,
+}
+@group(0) @binding(0)
+var<uniform> post: Post;
+@group(0) @binding(1)
+var t_scene: texture_2d<f32>;
+@group(0) @binding(2)
+var s_scene: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) tex_coords: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // a single triangle that covers the whole screen
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(uv * 2.0 - 1.0, 0.0, 1.0);
+    out.tex_coords = vec2<f32>(uv.x, 1.0 - uv.y);
+    return out;
+}
+
+// Narkowicz's fit of the ACES filmic curve
+fn aces(color: vec3<f32>) -> vec3<f32> {
+    let a = 2.51;
+    let b = 0.03;
+    let c = 2.43;
+    let d = 0.59;
+    let e = 0.14;
+    return clamp((color * (a * color + b)) / (color * (c * color + d) + e), vec3<f32>(0.0), vec3<f32>(1.0));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let hdr = textureSample(t_scene, s_scene, in.tex_coords);
+    var color = clamp(hdr.rgb, vec3<f32>(0.0), vec3<f32>(1.0));
+    if (post.tonemap != 0u) {
+        color = aces(hdr.rgb * post.exposure);
+    }
+    if (post.apply_gamma != 0u) {
+        color = pow(color, vec3<f32>(1.0 / 2.2));
+    }
+    return vec4<f32>(color, 1.0);
+}
@@ -0,0 +1,275 @@
+// Player preferences, kept in the app data directory so they survive restarts and updates to the
+// game. Unlike the package config, these belong to the player rather than the game developer.
+//
+// Each set of preferences is its own bincode file. A file that's missing or can't be decoded
+// (say, because it was written by an older runtime) is replaced by the defaults rather than
+// stopping the game from starting.
+
+use std::path::{Path, PathBuf};
+
+use app_dirs2::AppDataType;
+use bincode::{Decode, Encode};
+
+use crate::{
+    APP_INFO,
+    config::{FramePacing, PackageConfig, WindowMode},
+};
+
+/// The volumes of the game's sound, each from 0 to 1. The player sets them from the pause menu.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub struct VolumeSettings {
+    /// Scales every other volume.
+    pub master: f32,
+    pub music: f32,
+    /// Sound effects, which are the sounds played from entities.
+    pub sfx: f32,
+}
+
+impl Default for VolumeSettings {
+    fn default() -> Self {
+        Self {
+            master: 1.0,
+            music: 1.0,
+            sfx: 1.0,
+        }
+    }
+}
+
+/// One of the volumes in [`VolumeSettings`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VolumeChannel {
+    Master,
+    Music,
+    Sfx,
+}
+
+impl VolumeSettings {
+    /// The name of the file the volumes are saved in.
+    pub const FILE_NAME: &str = "volume.bin";
+
+    /// How many steps there are between silent and full volume.
+    pub const STEPS: i32 = 10;
+
+    pub fn volume(&self, channel: VolumeChannel) -> f32 {
+        match channel {
+            VolumeChannel::Master => self.master,
+            VolumeChannel::Music => self.music,
+            VolumeChannel::Sfx => self.sfx,
+        }
+    }
+
+    /// Turns the volume of `channel` up by `steps` of the [`Self::STEPS`] steps, or down for
+    /// negative steps, keeping it between 0 and 1.
+    pub fn step(&mut self, channel: VolumeChannel, steps: i32) {
+        let volume = match channel {
+            VolumeChannel::Master => &mut self.master,
+            VolumeChannel::Music => &mut self.music,
+            VolumeChannel::Sfx => &mut self.sfx,
+        };
+        let step = (*volume * Self::STEPS as f32).round() as i32 + steps;
+        *volume = step.clamp(0, Self::STEPS) as f32 / Self::STEPS as f32;
+    }
+
+    /// The volume music actually plays at.
+    pub fn music_gain(&self) -> f32 {
+        self.master.clamp(0.0, 1.0) * self.music.clamp(0.0, 1.0)
+    }
+
+    /// The volume sound effects actually play at.
+    pub fn sfx_gain(&self) -> f32 {
+        self.master.clamp(0.0, 1.0) * self.sfx.clamp(0.0, 1.0)
+    }
+}
+
+/// Graphics options the player has picked, layered over the package's own settings. Anything
+/// left unset keeps what the package says.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode)]
+pub struct GraphicsPreferences {
+    /// The size of the window in windowed mode.
+    pub resolution: Option<(u32, u32)>,
+    pub mode: Option<WindowMode>,
+    pub vsync: Option<bool>,
+    pub msaa_samples: Option<u32>,
+}
+
+impl GraphicsPreferences {
+    /// The name of the file the graphics preferences are saved in.
+    pub const FILE_NAME: &str = "graphics.bin";
+
+    /// Overrides the package's settings with the ones the player has picked.
+    pub fn apply(&self, config: &mut PackageConfig) {
+        if let Some(resolution) = self.resolution {
+            config.window.resolution = Some(resolution);
+        }
+        if let Some(mode) = self.mode {
+            config.window.mode = Some(mode);
+        }
+        match self.vsync {
+            Some(true) => config.window.frame_pacing = Some(FramePacing::Vsync),
+            // turning vsync off keeps the package's frame cap
+            Some(false) if config.window.frame_pacing == Some(FramePacing::Vsync) => {
+                config.window.frame_pacing = Some(FramePacing::Capped)
+            }
+            _ => {}
+        }
+        if let Some(msaa_samples) = self.msaa_samples {
+            config.graphics.msaa_samples = Some(msaa_samples);
+        }
+    }
+}
+
+/// The path of the preferences file called `file_name`.
+pub fn preferences_path(file_name: &str) -> anyhow::Result<PathBuf> {
+    Ok(app_dirs2::get_app_dir(AppDataType::UserConfig, &APP_INFO, "preferences")?.join(file_name))
+}
+
+/// Decodes preferences saved with [`encode`], or `None` if they're damaged.
+pub fn decode<T: Decode<()>>(bytes: &[u8]) -> Option<T> {
+    bincode::decode_from_slice(bytes, bincode::config::standard())
+        .ok()
+        .map(|(preferences, _)| preferences)
+}
+
+pub fn encode<T: Encode>(preferences: &T) -> Vec<u8> {
+    // encoding into a Vec has nothing that can fail
+    bincode::encode_to_vec(preferences, bincode::config::standard()).unwrap_or_default()
+}
+
+/// Loads the preferences in `file_name`, falling back to the defaults when they haven't been
+/// saved yet or can't be read.
+pub fn load<T: Decode<()> + Default>(file_name: &str) -> T {
+    match preferences_path(file_name) {
+        Ok(path) => load_from(&path),
+        Err(e) => {
+            log::warn!("Unable to find the preferences directory: {}", e);
+            T::default()
+        }
+    }
+}
+
+/// Loads the preferences in the file at `path`, falling back to the defaults like [`load`].
+pub fn load_from<T: Decode<()> + Default>(path: &Path) -> T {
+    let bytes = match std::fs::read(path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return T::default(),
+        Err(e) => {
+            log::warn!(
+                "Unable to read {}, using the defaults: {}",
+                path.display(),
+                e
+            );
+            return T::default();
+        }
+    };
+
+    decode(&bytes).unwrap_or_else(|| {
+        log::warn!("{} is damaged, using the defaults", path.display());
+        T::default()
+    })
+}
+
+/// Saves `preferences` to `file_name`, logging rather than failing if they can't be written.
+pub fn save<T: Encode>(file_name: &str, preferences: &T) {
+    let result = preferences_path(file_name).and_then(|path| {
+        save_to(&path, preferences)?;
+        Ok(path)
+    });
+    match result {
+        Ok(path) => log::debug!("Saved preferences to {}", path.display()),
+        Err(e) => log::warn!("Unable to save {}: {}", file_name, e),
+    }
+}
+
+/// Saves `preferences` to the file at `path`, creating its directory if it isn't there.
+pub fn save_to<T: Encode>(path: &Path, preferences: &T) -> std::io::Result<()> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(path, encode(preferences))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn graphics_preferences_override_the_package() {
+        let mut config = PackageConfig::default();
+        config.window.resolution = Some((1280, 720));
+        config.window.frame_pacing = Some(FramePacing::Vsync);
+        config.graphics.msaa_samples = Some(4);
+
+        GraphicsPreferences {
+            resolution: Some((1920, 1080)),
+            mode: Some(WindowMode::Fullscreen),
+            vsync: Some(false),
+            msaa_samples: None,
+        }
+        .apply(&mut config);
+
+        assert_eq!(config.window.resolution, Some((1920, 1080)));
+        assert_eq!(config.window.mode, Some(WindowMode::Fullscreen));
+        assert_eq!(config.window.frame_pacing, Some(FramePacing::Capped));
+        assert_eq!(config.graphics.msaa_samples, Some(4));
+    }
+
+    #[test]
+    fn unset_graphics_preferences_keep_the_package_settings() {
+        let mut config = PackageConfig::default();
+        config.window.frame_pacing = Some(FramePacing::Uncapped);
+        GraphicsPreferences {
+            vsync: Some(false),
+            ..Default::default()
+        }
+        .apply(&mut config);
+        assert_eq!(config.window.frame_pacing, Some(FramePacing::Uncapped));
+        assert_eq!(config.window.mode, PackageConfig::default().window.mode);
+    }
+
+    #[test]
+    fn gains_are_scaled_by_the_master_volume() {
+        let volumes = VolumeSettings {
+            master: 0.5,
+            music: 0.5,
+            sfx: 2.0,
+        };
+        assert_eq!(volumes.music_gain(), 0.25);
+        assert_eq!(volumes.sfx_gain(), 0.5);
+    }
+
+    #[test]
+    fn saved_volumes_are_loaded_again() {
+        let dir = std::env::temp_dir().join(format!("redback-volumes-{}", std::process::id()));
+        let path = dir.join(VolumeSettings::FILE_NAME);
+        assert_eq!(
+            load_from::<VolumeSettings>(&path),
+            VolumeSettings::default()
+        );
+
+        let mut volumes = VolumeSettings::default();
+        volumes.step(VolumeChannel::Music, -7);
+        volumes.step(VolumeChannel::Master, -2);
+        save_to(&path, &volumes).unwrap();
+        assert_eq!(load_from::<VolumeSettings>(&path), volumes);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn volumes_step_between_silent_and_full() {
+        let mut volumes = VolumeSettings::default();
+        volumes.step(VolumeChannel::Sfx, -3);
+        assert_eq!(volumes.volume(VolumeChannel::Sfx), 0.7);
+        volumes.step(VolumeChannel::Sfx, -20);
+        assert_eq!(volumes.volume(VolumeChannel::Sfx), 0.0);
+        volumes.step(VolumeChannel::Sfx, 20);
+        assert_eq!(volumes.volume(VolumeChannel::Sfx), 1.0);
+        assert_eq!(volumes.volume(VolumeChannel::Music), 1.0);
+    }
+
+    #[test]
+    fn damaged_volumes_are_not_decoded() {
+        assert_eq!(decode::<VolumeSettings>(&[]), None);
+        assert_eq!(decode::<VolumeSettings>(&[0xff; 3]), None);
+    }
+}
@@ -0,0 +1,236 @@
+// Ray queries against the entities in the world, for picking and line of sight checks.
+//
+// Entities are hit using a box around their model, as there's no collision geometry in the
+// package. Scenes can size the box in a model's settings. The box is in the model's local space,
+// so it turns and scales with the entity.
+//
+// For picking, the ray under the cursor is worked out from the active camera at the start of
+// every update and kept on the runtime entity as a `CursorRay`. Clicking while the debug overlay
+// is up shows the entity under the cursor in the overlay.
+
+use dropbear_engine::entity::{AdoptedEntity, Transform};
+use glam::{DMat4, DVec3, DVec4};
+use hecs::World;
+
+use crate::{
+    hierarchy::{self, GlobalTransform},
+    visibility::{self, Enabled},
+};
+
+/// A box around an entity's model that rays hit, in the model's local space.
+///
+/// Entities without one are treated as filling the unit cube around their origin, the same
+/// space the default [`crate::culling::BoundingSphere`] assumes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub min: DVec3,
+    pub max: DVec3,
+}
+
+impl Default for BoundingBox {
+    fn default() -> Self {
+        Self {
+            min: DVec3::splat(-1.0),
+            max: DVec3::ONE,
+        }
+    }
+}
+
+/// A half line starting at `origin`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Ray {
+    pub origin: DVec3,
+    /// Always normalised, so distances along the ray are in world units.
+    pub direction: DVec3,
+}
+
+impl Ray {
+    /// A ray from `origin` towards `direction`, or `None` if `direction` has no length.
+    pub fn new(origin: DVec3, direction: DVec3) -> Option<Self> {
+        Some(Self {
+            origin,
+            direction: direction.try_normalize()?,
+        })
+    }
+
+    pub fn at(&self, distance: f64) -> DVec3 {
+        self.origin + self.direction * distance
+    }
+}
+
+/// The ray from the active camera through the cursor, stored on the runtime entity. `None`
+/// while there's no camera to cast from.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct CursorRay(pub Option<Ray>);
+
+/// The nearest entity a ray hit.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RayHit {
+    pub entity: hecs::Entity,
+    /// How far along the ray the hit is.
+    pub distance: f64,
+    pub point: DVec3,
+}
+
+/// The nearest model entity `ray` hits within `max_distance`. Disabled entities are passed
+/// through, but hidden ones still block the ray.
+pub fn raycast(world: &World, ray: Ray, max_distance: f64) -> Option<RayHit> {
+    let mut query = world.query::<(
+        &AdoptedEntity,
+        &Transform,
+        Option<&GlobalTransform>,
+        Option<&BoundingBox>,
+        Option<&Enabled>,
+    )>();
+    query
+        .iter()
+        .filter(|(_, (_, _, _, _, enabled))| visibility::is_updated(*enabled))
+        .filter_map(|(entity, (_, transform, global, bounds, _))| {
+            let transform = hierarchy::world_transform(transform, global);
+            let distance = intersect(&ray, &transform, &bounds.copied().unwrap_or_default())?;
+            (distance <= max_distance).then_some((entity, distance))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, distance)| RayHit {
+            entity,
+            distance,
+            point: ray.at(distance),
+        })
+}
+
+/// The nearest entity under the cursor within `max_distance`, for click to select.
+pub fn pick(world: &World, max_distance: f64) -> Option<RayHit> {
+    let ray = world
+        .query::<&CursorRay>()
+        .iter()
+        .find_map(|(_, cursor)| cursor.0)?;
+    raycast(world, ray, max_distance)
+}
+
+/// The ray through the pixel at `cursor` of a `width` by `height` target, seen through
+/// `view_proj`. The cursor and the size have to be in the same pixels, which for winit's cursor
+/// positions and the surface's size are physical ones, so DPI scaling doesn't come into it.
+pub fn screen_ray(view_proj: DMat4, cursor: (f64, f64), size: (u32, u32)) -> Option<Ray> {
+    let (width, height) = size;
+    if width == 0 || height == 0 {
+        return None;
+    }
+    let x = cursor.0 / width as f64 * 2.0 - 1.0;
+    let y = 1.0 - cursor.1 / height as f64 * 2.0;
+
+    // wgpu's depth goes from 0 at the near plane to 1 at the far one
+    let to_world = view_proj.inverse();
+    let unproject = |depth: f64| {
+        let point = to_world * DVec4::new(x, y, depth, 1.0);
+        point.truncate() / point.w
+    };
+    let near = unproject(0.0);
+    let far = unproject(1.0);
+    Ray::new(near, far - near)
+}
+
+/// How far along `ray` it hits `bounds` placed by `transform`, if it does. A ray starting
+/// inside the box hits it straight away.
+pub fn intersect(ray: &Ray, transform: &Transform, bounds: &BoundingBox) -> Option<f64> {
+    let to_world = DMat4::from_scale_rotation_translation(
+        transform.scale,
+        transform.rotation,
+        transform.position,
+    );
+    if to_world.determinant() == 0.0 {
+        return None;
+    }
+    // the direction isn't renormalised, so distances in local space stay world distances
+    let to_local = to_world.inverse();
+    let origin = to_local.transform_point3(ray.origin);
+    let direction = to_local.transform_vector3(ray.direction);
+    intersect_box(origin, direction, bounds.min, bounds.max)
+}
+
+/// The slab test of a ray against an axis aligned box, giving the distance of the nearest hit
+/// in front of the origin.
+pub fn intersect_box(origin: DVec3, direction: DVec3, min: DVec3, max: DVec3) -> Option<f64> {
+    let mut near = 0.0_f64;
+    let mut far = f64::INFINITY;
+    for axis in 0..3 {
+        if direction[axis] == 0.0 {
+            if origin[axis] < min[axis] || origin[axis] > max[axis] {
+                return None;
+            }
+            continue;
+        }
+        let t1 = (min[axis] - origin[axis]) / direction[axis];
+        let t2 = (max[axis] - origin[axis]) / direction[axis];
+        near = near.max(t1.min(t2));
+        far = far.min(t1.max(t2));
+        if near > far {
+            return None;
+        }
+    }
+    Some(near)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rays_hit_the_near_side_of_a_box() {
+        let hit = intersect_box(
+            DVec3::new(0.0, 0.0, -5.0),
+            DVec3::Z,
+            DVec3::splat(-1.0),
+            DVec3::ONE,
+        );
+        assert_eq!(hit, Some(4.0));
+    }
+
+    #[test]
+    fn rays_miss_boxes_beside_or_behind_them() {
+        let (min, max) = (DVec3::splat(-1.0), DVec3::ONE);
+        assert_eq!(
+            intersect_box(DVec3::new(3.0, 0.0, -5.0), DVec3::Z, min, max),
+            None
+        );
+        assert_eq!(
+            intersect_box(DVec3::new(0.0, 0.0, 5.0), DVec3::Z, min, max),
+            None
+        );
+    }
+
+    #[test]
+    fn rays_starting_inside_hit_straight_away() {
+        let hit = intersect_box(DVec3::ZERO, DVec3::X, DVec3::splat(-1.0), DVec3::ONE);
+        assert_eq!(hit, Some(0.0));
+    }
+
+    #[test]
+    fn boxes_move_and_scale_with_the_entity() {
+        let transform = Transform {
+            position: DVec3::new(10.0, 0.0, 0.0),
+            scale: DVec3::splat(2.0),
+            ..Default::default()
+        };
+        let ray = Ray::new(DVec3::ZERO, DVec3::X).unwrap();
+        let distance = intersect(&ray, &transform, &BoundingBox::default()).unwrap();
+        assert!((distance - 8.0).abs() < 1e-9);
+        assert!(
+            ray.at(distance)
+                .abs_diff_eq(DVec3::new(8.0, 0.0, 0.0), 1e-9)
+        );
+    }
+
+    #[test]
+    fn the_middle_of_the_screen_looks_down_the_camera() {
+        let proj = DMat4::perspective_rh(1.0, 4.0 / 3.0, 0.1, 100.0);
+        let view = DMat4::look_at_rh(DVec3::new(0.0, 0.0, 5.0), DVec3::ZERO, DVec3::Y);
+        let ray = screen_ray(proj * view, (400.0, 300.0), (800, 600)).unwrap();
+        assert!(ray.direction.abs_diff_eq(DVec3::NEG_Z, 1e-9));
+        assert!(screen_ray(proj * view, (0.0, 0.0), (0, 0)).is_none());
+    }
+
+    #[test]
+    fn zero_length_directions_make_no_ray() {
+        assert!(Ray::new(DVec3::ONE, DVec3::ZERO).is_none());
+    }
+}
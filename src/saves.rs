@@ -0,0 +1,242 @@
+// Save games, kept in named slots in the app data directory.
+//
+// What's in a save is up to whoever writes it, as a slot holds a blob of bytes that's read back
+// as the same bytes. The runtime keeps the scene the player is in in one, when the package names
+// a slot for it, so the game starts in that scene the next time it runs.
+//
+// Each slot is its own file, holding the blob along with a checksum of it. A save is written to
+// a temporary file first and then moved over the old one, so a crash halfway through writing
+// leaves the previous save in place. A file that's been damaged anyway fails its checksum and is
+// reported as corrupt instead of being read back as garbage.
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use app_dirs2::AppDataType;
+use bincode::{Decode, Encode};
+
+use crate::APP_INFO;
+
+/// The extension of save files, which are named after their slot.
+pub const SAVE_EXTENSION: &str = "sav";
+
+/// The longest a slot name can be.
+pub const MAX_SLOT_NAME_LENGTH: usize = 64;
+
+/// What's written to a slot's file.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+struct SaveFile {
+    /// A CRC32 of `data`, for telling a damaged file from a save.
+    checksum: u32,
+    data: Vec<u8>,
+}
+
+/// Why a slot couldn't be saved or loaded.
+#[derive(Debug)]
+pub enum SaveError {
+    /// The slot name can't be used as a file name.
+    InvalidSlot(String),
+    /// The save file is there but damaged.
+    Corrupt { path: PathBuf },
+    Io {
+        path: PathBuf,
+        source: std::io::Error,
+    },
+    /// The saves directory couldn't be found.
+    NoSaveDir(String),
+}
+
+impl fmt::Display for SaveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::InvalidSlot(slot) => write!(
+                f,
+                "'{}' is not a valid save slot name (use up to {} letters, digits, '-' and '_')",
+                slot, MAX_SLOT_NAME_LENGTH
+            ),
+            Self::Corrupt { path } => write!(f, "the save at {} is damaged", path.display()),
+            Self::Io { path, source } => {
+                write!(f, "unable to access {}: {}", path.display(), source)
+            }
+            Self::NoSaveDir(e) => write!(f, "unable to find the saves directory: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Self::Io { source, .. } => Some(source),
+            Self::InvalidSlot(_) | Self::Corrupt { .. } | Self::NoSaveDir(_) => None,
+        }
+    }
+}
+
+/// Whether `slot` can name a save. Slot names become file names, so they're kept to characters
+/// that are safe on every platform.
+pub fn is_valid_slot(slot: &str) -> bool {
+    !slot.is_empty()
+        && slot.len() <= MAX_SLOT_NAME_LENGTH
+        && slot
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// The directory save files go in, inside the user data directory for [`APP_INFO`].
+pub fn saves_dir() -> Result<PathBuf, SaveError> {
+    app_dirs2::get_app_dir(AppDataType::UserData, &APP_INFO, "saves")
+        .map_err(|e| SaveError::NoSaveDir(e.to_string()))
+}
+
+/// The path of the save file for `slot` in `dir`.
+pub fn slot_path(dir: &Path, slot: &str) -> Result<PathBuf, SaveError> {
+    if !is_valid_slot(slot) {
+        return Err(SaveError::InvalidSlot(slot.to_string()));
+    }
+    Ok(dir.join(slot).with_extension(SAVE_EXTENSION))
+}
+
+fn checksum(data: &[u8]) -> u32 {
+    let mut crc = flate2::Crc::new();
+    crc.update(data);
+    crc.sum()
+}
+
+/// Encodes `data` as the contents of a save file.
+pub fn encode(data: &[u8]) -> Vec<u8> {
+    let file = SaveFile {
+        checksum: checksum(data),
+        data: data.to_vec(),
+    };
+    // encoding into a Vec has nothing that can fail
+    bincode::encode_to_vec(&file, bincode::config::standard()).unwrap_or_default()
+}
+
+/// The data in the contents of a save file, or `None` if it's damaged.
+pub fn decode(bytes: &[u8]) -> Option<Vec<u8>> {
+    let (file, read): (SaveFile, usize) =
+        bincode::decode_from_slice(bytes, bincode::config::standard()).ok()?;
+    (read == bytes.len() && file.checksum == checksum(&file.data)).then_some(file.data)
+}
+
+/// Writes `data` to `slot` in `dir`, replacing whatever was saved there.
+pub fn write_slot(dir: &Path, slot: &str, data: &[u8]) -> Result<PathBuf, SaveError> {
+    let path = slot_path(dir, slot)?;
+    let io = |source| SaveError::Io {
+        path: path.clone(),
+        source,
+    };
+    std::fs::create_dir_all(dir).map_err(io)?;
+    // moved over the old save only once it's all written out
+    let temporary = path.with_extension(format!("{}.tmp", SAVE_EXTENSION));
+    std::fs::write(&temporary, encode(data)).map_err(io)?;
+    std::fs::rename(&temporary, &path).map_err(io)?;
+    Ok(path)
+}
+
+/// The data saved in `slot` in `dir`, or `None` if nothing has been saved there yet.
+pub fn read_slot(dir: &Path, slot: &str) -> Result<Option<Vec<u8>>, SaveError> {
+    let path = slot_path(dir, slot)?;
+    let bytes = match std::fs::read(&path) {
+        Ok(bytes) => bytes,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(source) => return Err(SaveError::Io { path, source }),
+    };
+    match decode(&bytes) {
+        Some(data) => Ok(Some(data)),
+        None => Err(SaveError::Corrupt { path }),
+    }
+}
+
+/// Saves `data` to `slot`, logging rather than failing if it can't be written.
+pub fn save(slot: &str, data: &[u8]) {
+    match saves_dir().and_then(|dir| write_slot(&dir, slot, data)) {
+        Ok(path) => log::info!("Saved slot '{}' to {}", slot, path.display()),
+        Err(e) => log::error!("Unable to save slot '{}': {}", slot, e),
+    }
+}
+
+/// The data saved in `slot`. A slot that has never been saved to is `Ok(None)`, so a game can
+/// start fresh, while a damaged save is an error.
+pub fn load(slot: &str) -> Result<Option<Vec<u8>>, SaveError> {
+    let result = saves_dir().and_then(|dir| read_slot(&dir, slot));
+    if let Err(e) = &result {
+        log::warn!("Unable to load slot '{}': {}", slot, e);
+    }
+    result
+}
+
+/// The name of the scene the runtime saved to `slot` when it was last loaded, if there's a
+/// readable one.
+pub fn saved_scene(slot: &str) -> Option<String> {
+    let data = load(slot).ok()??;
+    String::from_utf8(data).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("redback-saves-{}-{}", name, std::process::id()))
+    }
+
+    #[test]
+    fn saves_decode_to_what_was_encoded() {
+        let data = b"level 3, 120 coins".to_vec();
+        assert_eq!(decode(&encode(&data)), Some(data));
+        assert_eq!(decode(&encode(&[])), Some(Vec::new()));
+    }
+
+    #[test]
+    fn damaged_saves_dont_decode() {
+        let mut bytes = encode(b"level 3");
+        let last = bytes.len() - 1;
+        bytes[last] ^= 0xff;
+        assert_eq!(decode(&bytes), None);
+        assert_eq!(decode(&bytes[..last]), None);
+        assert_eq!(decode(&[]), None);
+    }
+
+    #[test]
+    fn slots_read_back_what_was_written() {
+        let dir = temp_dir("round-trip");
+        assert_eq!(read_slot(&dir, "slot_1").unwrap(), None);
+
+        let path = write_slot(&dir, "slot_1", b"first").unwrap();
+        assert_eq!(path, dir.join("slot_1.sav"));
+        write_slot(&dir, "slot_1", b"second").unwrap();
+        write_slot(&dir, "slot-2", b"other").unwrap();
+        assert_eq!(read_slot(&dir, "slot_1").unwrap(), Some(b"second".to_vec()));
+        assert_eq!(read_slot(&dir, "slot-2").unwrap(), Some(b"other".to_vec()));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn damaged_slots_are_reported_as_corrupt() {
+        let dir = temp_dir("corrupt");
+        let path = write_slot(&dir, "slot_1", b"progress").unwrap();
+        std::fs::write(&path, b"not a save").unwrap();
+        assert!(matches!(
+            read_slot(&dir, "slot_1"),
+            Err(SaveError::Corrupt { .. })
+        ));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn slot_names_stay_file_name_safe() {
+        assert!(is_valid_slot("autosave_2"));
+        assert!(!is_valid_slot(""));
+        assert!(!is_valid_slot("../escape"));
+        assert!(!is_valid_slot(&"a".repeat(MAX_SLOT_NAME_LENGTH + 1)));
+        assert!(matches!(
+            write_slot(&temp_dir("invalid"), "a/b", b""),
+            Err(SaveError::InvalidSlot(_))
+        ));
+    }
+}
@@ -0,0 +1,65 @@
+// Stepping through the package's scenes by name, for the debug keys that cycle between them.
+
+use std::collections::HashSet;
+
+/// The names of every scene in the package.
+#[derive(Debug, Clone, Default)]
+pub struct SceneNames {
+    names: HashSet<String>,
+}
+
+impl SceneNames {
+    pub fn new(names: impl IntoIterator<Item = String>) -> Self {
+        Self {
+            names: names.into_iter().collect(),
+        }
+    }
+
+    /// The scene `offset` places on from `current`, going through the names in alphabetical
+    /// order and wrapping around at either end. A `current` that isn't one of them counts from
+    /// the first scene.
+    pub fn cycle(&self, current: &str, offset: isize) -> Option<&str> {
+        let mut names: Vec<&str> = self.names.iter().map(String::as_str).collect();
+        if names.is_empty() {
+            return None;
+        }
+        names.sort_unstable();
+        let index = names.binary_search(&current).unwrap_or(0) as isize;
+        let next = (index + offset).rem_euclid(names.len() as isize);
+        Some(names[next as usize])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(names: &[&str]) -> SceneNames {
+        SceneNames::new(names.iter().map(|name| name.to_string()))
+    }
+
+    #[test]
+    fn cycling_visits_every_scene_once() {
+        let scenes = names(&["b", "c", "a"]);
+        let mut current = "a".to_string();
+        let mut visited = Vec::new();
+        for _ in 0..3 {
+            current = scenes.cycle(&current, 1).unwrap().to_string();
+            visited.push(current.clone());
+        }
+        assert_eq!(visited, ["b", "c", "a"]);
+    }
+
+    #[test]
+    fn cycling_backwards_wraps_around() {
+        let scenes = names(&["a", "b", "c"]);
+        assert_eq!(scenes.cycle("a", -1), Some("c"));
+    }
+
+    #[test]
+    fn unknown_scenes_count_from_the_first() {
+        let scenes = names(&["a", "b"]);
+        assert_eq!(scenes.cycle("missing", 1), Some("b"));
+        assert_eq!(names(&[]).cycle("a", 1), None);
+    }
+}
@@ -0,0 +1,137 @@
+// Spreading script updates over several frames in scenes with a lot of scripted entities.
+//
+// With a cap on how many scripts run per update, scripts take turns round robin, carrying on from
+// wherever the last update stopped. A script that misses an update keeps the time it missed and
+// gets all of it on its next turn, so it moves as far as if it had run every update, only in
+// bigger steps. Scripts marked as critical aren't capped and run every update.
+
+use std::collections::{HashMap, HashSet};
+
+/// Marks an entity's script as one that has to run every update, such as player movement, even
+/// when the number of scripts per update is capped.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CriticalScript;
+
+/// An entity whose script is due to run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScriptedEntity {
+    pub entity: hecs::Entity,
+    pub script: String,
+    pub critical: bool,
+}
+
+/// Hands out turns to run when scripts per update are capped.
+#[derive(Debug, Default)]
+pub struct ScriptScheduler {
+    /// Where the next update's turns start, in the scripts sorted by entity.
+    next: usize,
+    /// The time each script has missed since it last ran, in seconds.
+    missed: HashMap<hecs::Entity, f32>,
+}
+
+impl ScriptScheduler {
+    /// Picks which of `scripts` run this update of `dt` seconds, each with the time it runs for.
+    /// Besides the critical ones, no more than `max` run; when that's `None` every script runs.
+    pub fn schedule<'a>(
+        &mut self,
+        scripts: &'a [ScriptedEntity],
+        dt: f32,
+        max: Option<usize>,
+    ) -> Vec<(&'a ScriptedEntity, f32)> {
+        let Some(max) = max else {
+            self.missed.clear();
+            return scripts.iter().map(|script| (script, dt)).collect();
+        };
+
+        let present: HashSet<hecs::Entity> = scripts.iter().map(|script| script.entity).collect();
+        self.missed.retain(|entity, _| present.contains(entity));
+
+        let mut scheduled: Vec<(&ScriptedEntity, f32)> = scripts
+            .iter()
+            .filter(|script| script.critical)
+            .map(|script| (script, dt))
+            .collect();
+
+        // sorted so the order stays put when entities change archetype
+        let mut capped: Vec<&ScriptedEntity> =
+            scripts.iter().filter(|script| !script.critical).collect();
+        capped.sort_by_key(|script| script.entity.to_bits());
+        if capped.is_empty() {
+            self.next = 0;
+            return scheduled;
+        }
+
+        let turns = max.min(capped.len());
+        let start = self.next % capped.len();
+        for offset in 0..capped.len() {
+            let script = capped[(start + offset) % capped.len()];
+            let missed = self.missed.entry(script.entity).or_default();
+            *missed += dt;
+            if offset < turns {
+                scheduled.push((script, std::mem::take(missed)));
+            }
+        }
+        self.next = start + turns;
+        scheduled
+    }
+
+    /// Forgets every script's missed time, for when the world is cleared.
+    pub fn clear(&mut self) {
+        self.next = 0;
+        self.missed.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scripts(critical: &[bool]) -> Vec<ScriptedEntity> {
+        let mut world = hecs::World::new();
+        critical
+            .iter()
+            .enumerate()
+            .map(|(index, critical)| ScriptedEntity {
+                entity: world.spawn(()),
+                script: format!("script_{}", index),
+                critical: *critical,
+            })
+            .collect()
+    }
+
+    fn names(scheduled: &[(&ScriptedEntity, f32)]) -> Vec<String> {
+        scheduled
+            .iter()
+            .map(|(script, _)| script.script.clone())
+            .collect()
+    }
+
+    #[test]
+    fn uncapped_scripts_all_run() {
+        let scripts = scripts(&[false, false]);
+        let scheduled = ScriptScheduler::default().schedule(&scripts, 0.5, None);
+        assert_eq!(names(&scheduled), ["script_0", "script_1"]);
+        assert!(scheduled.iter().all(|(_, dt)| *dt == 0.5));
+    }
+
+    #[test]
+    fn capped_scripts_take_turns_with_the_time_they_missed() {
+        let scripts = scripts(&[false, false, false]);
+        let mut scheduler = ScriptScheduler::default();
+        assert_eq!(
+            scheduler.schedule(&scripts, 0.25, Some(2)),
+            [(&scripts[0], 0.25), (&scripts[1], 0.25)]
+        );
+        assert_eq!(
+            scheduler.schedule(&scripts, 0.25, Some(2)),
+            [(&scripts[2], 0.5), (&scripts[0], 0.25)]
+        );
+    }
+
+    #[test]
+    fn critical_scripts_run_past_the_cap() {
+        let scripts = scripts(&[false, true, false]);
+        let scheduled = ScriptScheduler::default().schedule(&scripts, 0.1, Some(1));
+        assert_eq!(names(&scheduled), ["script_1", "script_0"]);
+    }
+}
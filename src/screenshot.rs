@@ -0,0 +1,353 @@
+// Screenshots of the rendered frame, saved as PNGs in the app data `screenshots` directory.
+//
+// A request made by the screenshot key is picked up by the next frame rendered, which is drawn a
+// second time into a capture texture once it's on the surface. The capture is then copied into a
+// buffer on the GPU.
+// Mapping the buffer for reading finishes some time after the copy is submitted, so it's polled
+// for at the start of each later frame rather than stalling on it, and the PNG is encoded and
+// written on its own thread once the pixels are back.
+
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{self, Receiver, TryRecvError},
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use app_dirs2::AppDataType;
+use dropbear_engine::{
+    graphics::{Graphics, Texture},
+    wgpu,
+};
+
+use crate::{APP_INFO, post};
+
+/// Tracks the screenshot that's been asked for and the one on its way back from the GPU.
+#[derive(Default)]
+pub struct Screenshots {
+    requested: bool,
+    pending: Option<PendingCapture>,
+}
+
+/// A texture the frame is drawn into when taking a screenshot, the same size and format as the
+/// surface.
+pub struct Capture {
+    texture: wgpu::Texture,
+    view: wgpu::TextureView,
+    depth_view: wgpu::TextureView,
+    width: u32,
+    height: u32,
+    format: wgpu::TextureFormat,
+}
+
+/// A capture that's been copied into `buffer` and is waiting on the buffer to be mapped.
+struct PendingCapture {
+    buffer: wgpu::Buffer,
+    width: u32,
+    height: u32,
+    padded_bytes_per_row: u32,
+    format: wgpu::TextureFormat,
+    mapped: Receiver<Result<(), wgpu::BufferAsyncError>>,
+}
+
+impl Screenshots {
+    /// Asks for a screenshot of the next frame.
+    pub fn request(&mut self) {
+        self.requested = true;
+    }
+
+    /// Whether this frame should be captured, clearing the request. A request made while the
+    /// previous screenshot is still being read back waits for it to finish.
+    pub fn take_request(&mut self) -> bool {
+        if self.pending.is_some() {
+            return false;
+        }
+        std::mem::take(&mut self.requested)
+    }
+
+    /// Copies `capture` into a buffer and submits `encoder` along with the copy. The frame has to
+    /// have been drawn into the capture with `encoder`.
+    pub fn read_back(
+        &mut self,
+        graphics: &Graphics,
+        mut encoder: wgpu::CommandEncoder,
+        capture: Capture,
+    ) {
+        if !is_capturable(capture.format) {
+            log::warn!("Can't take a screenshot of a {:?} surface", capture.format);
+            return;
+        }
+        let padded_bytes_per_row = padded_bytes_per_row(capture.width, 4);
+
+        let buffer = graphics
+            .state
+            .device
+            .create_buffer(&wgpu::BufferDescriptor {
+                label: Some("Screenshot Buffer"),
+                size: padded_bytes_per_row as u64 * capture.height as u64,
+                usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+                mapped_at_creation: false,
+            });
+        encoder.copy_texture_to_buffer(
+            wgpu::TexelCopyTextureInfo {
+                texture: &capture.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d::ZERO,
+                aspect: wgpu::TextureAspect::All,
+            },
+            wgpu::TexelCopyBufferInfo {
+                buffer: &buffer,
+                layout: wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(padded_bytes_per_row),
+                    rows_per_image: Some(capture.height),
+                },
+            },
+            wgpu::Extent3d {
+                width: capture.width,
+                height: capture.height,
+                depth_or_array_layers: 1,
+            },
+        );
+        graphics
+            .state
+            .queue
+            .submit(std::iter::once(encoder.finish()));
+
+        let (sender, mapped) = mpsc::channel();
+        buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+
+        self.pending = Some(PendingCapture {
+            buffer,
+            width: capture.width,
+            height: capture.height,
+            padded_bytes_per_row,
+            format: capture.format,
+            mapped,
+        });
+    }
+
+    /// Saves the pending screenshot if the GPU has finished handing it back.
+    pub fn poll(&mut self, graphics: &Graphics) {
+        let Some(pending) = &self.pending else {
+            return;
+        };
+        let _ = graphics.state.device.poll(wgpu::PollType::Poll);
+
+        match pending.mapped.try_recv() {
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => {
+                log::warn!("The screenshot buffer was dropped before it could be read");
+            }
+            Ok(Err(e)) => log::warn!("Failed to read the screenshot back: {}", e),
+            Ok(Ok(())) => {
+                let pixels = pending.pixels();
+                pending.buffer.unmap();
+                save_in_background(pixels, pending.width, pending.height);
+            }
+        }
+        self.pending = None;
+    }
+}
+
+impl Capture {
+    pub fn new(graphics: &Graphics) -> Self {
+        let device = &graphics.state.device;
+        let (width, height) = post::surface_size(graphics);
+        let format = graphics.state.config.format;
+        let size = wgpu::Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Screenshot Target"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let depth_view = device
+            .create_texture(&wgpu::TextureDescriptor {
+                label: Some("Screenshot Depth"),
+                size,
+                mip_level_count: 1,
+                sample_count: 1,
+                dimension: wgpu::TextureDimension::D2,
+                format: Texture::DEPTH_FORMAT,
+                usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+                view_formats: &[],
+            })
+            .create_view(&wgpu::TextureViewDescriptor::default());
+
+        Self {
+            texture,
+            view,
+            depth_view,
+            width,
+            height,
+            format,
+        }
+    }
+
+    /// Begins a render pass on the capture, cleared to `clear_color`.
+    pub fn begin_pass<'a>(
+        &'a self,
+        encoder: &'a mut wgpu::CommandEncoder,
+        clear_color: wgpu::Color,
+    ) -> wgpu::RenderPass<'a> {
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("Screenshot Pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &self.view,
+                resolve_target: None,
+                depth_slice: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(clear_color),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                view: &self.depth_view,
+                depth_ops: Some(wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(1.0),
+                    store: wgpu::StoreOp::Discard,
+                }),
+                stencil_ops: None,
+            }),
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        })
+    }
+}
+
+impl PendingCapture {
+    /// The mapped buffer as tightly packed RGBA8 rows.
+    fn pixels(&self) -> Vec<u8> {
+        let data = self.buffer.slice(..).get_mapped_range();
+        let row_bytes = self.width as usize * 4;
+        let mut pixels = Vec::with_capacity(row_bytes * self.height as usize);
+        for row in data.chunks(self.padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..row_bytes]);
+        }
+
+        if matches!(
+            self.format,
+            wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+        ) {
+            for pixel in pixels.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        pixels
+    }
+}
+
+/// Whether surfaces of `format` can be saved, which is limited to the 8 bit RGBA and BGRA formats
+/// nearly every surface uses.
+fn is_capturable(format: wgpu::TextureFormat) -> bool {
+    matches!(
+        format,
+        wgpu::TextureFormat::Rgba8Unorm
+            | wgpu::TextureFormat::Rgba8UnormSrgb
+            | wgpu::TextureFormat::Bgra8Unorm
+            | wgpu::TextureFormat::Bgra8UnormSrgb
+    )
+}
+
+/// The bytes per row of a copy `width` pixels wide, padded to the alignment wgpu requires of
+/// buffer copies.
+pub fn padded_bytes_per_row(width: u32, bytes_per_pixel: u32) -> u32 {
+    let unpadded = width * bytes_per_pixel;
+    unpadded.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT) * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT
+}
+
+/// The name of a screenshot taken at `time`, such as `screenshot-1718000000123.png`.
+pub fn screenshot_file_name(time: SystemTime) -> String {
+    let millis = time
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or_default();
+    format!("screenshot-{}.png", millis)
+}
+
+/// A path for a screenshot taken at `time` in `dir`, numbered so it doesn't overwrite one taken in
+/// the same millisecond.
+pub fn screenshot_path(dir: &Path, time: SystemTime) -> PathBuf {
+    let name = screenshot_file_name(time);
+    let mut path = dir.join(&name);
+    let mut copy = 1;
+    while path.exists() {
+        let stem = name.trim_end_matches(".png");
+        path = dir.join(format!("{}-{}.png", stem, copy));
+        copy += 1;
+    }
+    path
+}
+
+/// Encodes and writes the screenshot on another thread, so the frame doesn't wait on the disk.
+fn save_in_background(pixels: Vec<u8>, width: u32, height: u32) {
+    std::thread::spawn(move || {
+        let result = app_dirs2::get_app_dir(AppDataType::UserData, &APP_INFO, "screenshots")
+            .map_err(anyhow::Error::from)
+            .and_then(|dir| {
+                std::fs::create_dir_all(&dir)?;
+                let path = screenshot_path(&dir, SystemTime::now());
+                image::save_buffer(&path, &pixels, width, height, image::ColorType::Rgba8)?;
+                Ok(path)
+            });
+        match result {
+            Ok(path) => log::info!("Saved a screenshot to {}", path.display()),
+            Err(e) => log::warn!("Failed to save the screenshot: {}", e),
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rows_are_padded_to_the_copy_alignment() {
+        assert_eq!(padded_bytes_per_row(64, 4), 256);
+        assert_eq!(padded_bytes_per_row(65, 4), 512);
+        assert_eq!(padded_bytes_per_row(1, 4), 256);
+    }
+
+    #[test]
+    fn screenshots_are_named_after_when_they_were_taken() {
+        let time = UNIX_EPOCH + std::time::Duration::from_millis(1_718_000_000_123);
+        assert_eq!(screenshot_file_name(time), "screenshot-1718000000123.png");
+    }
+
+    #[test]
+    fn screenshots_taken_together_are_numbered() {
+        let dir = std::env::temp_dir().join(format!("redback-screenshots-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let time = UNIX_EPOCH + std::time::Duration::from_millis(5);
+
+        let first = screenshot_path(&dir, time);
+        assert_eq!(first, dir.join("screenshot-5.png"));
+        std::fs::write(&first, b"").unwrap();
+        let second = screenshot_path(&dir, time);
+        assert_eq!(second, dir.join("screenshot-5-1.png"));
+        std::fs::write(&second, b"").unwrap();
+        assert_eq!(screenshot_path(&dir, time), dir.join("screenshot-5-2.png"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn only_8_bit_colour_surfaces_are_captured() {
+        assert!(is_capturable(wgpu::TextureFormat::Bgra8UnormSrgb));
+        assert!(!is_capturable(wgpu::TextureFormat::Rgba16Float));
+    }
+}
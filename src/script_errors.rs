@@ -0,0 +1,260 @@
+// Bookkeeping for scripts that fail, either while they load or every time they update.
+//
+// A broken script usually fails the same way on every frame. Each entity's script logs its first
+// failure, and any failure that's different from the last one, in full. Repeats of that failure
+// are only counted, and the count is logged as a summary at most every `SUMMARY_INTERVAL`.
+// Packages can also turn scripts off once they've failed too many times in a row.
+
+use std::{
+    any::Any,
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+/// How often a script that keeps failing the same way is summarised in the log.
+pub const SUMMARY_INTERVAL: Duration = Duration::from_secs(10);
+
+/// The failures of every entity's script since the scene loaded.
+#[derive(Debug, Default)]
+pub struct ScriptFailures {
+    scripts: HashMap<hecs::Entity, Failures>,
+}
+
+#[derive(Debug)]
+struct Failures {
+    script: String,
+    /// Every failure since the scene loaded.
+    total: u32,
+    /// Failures since the script last updated without one.
+    in_a_row: u32,
+    last_error: String,
+    /// Failures that haven't been logged since the last summary.
+    unlogged: u32,
+    last_logged: Instant,
+    disabled: bool,
+}
+
+impl ScriptFailures {
+    /// Records that `script` failed to update on `entity` with `error`, logging it unless it's
+    /// the same as the last failure and was logged recently. Once `disable_after` failures
+    /// happen in a row, the script is disabled.
+    pub fn record(
+        &mut self,
+        entity: hecs::Entity,
+        script: &str,
+        error: &str,
+        now: Instant,
+        disable_after: Option<u32>,
+    ) {
+        let failures = self.failures(entity, script, now);
+        failures.total += 1;
+        failures.in_a_row += 1;
+
+        if failures.total == 1 || failures.script != script || failures.last_error != error {
+            log::warn!(
+                "Failed to update script '{}' for entity {:?}: {}",
+                script,
+                entity,
+                error
+            );
+            failures.script = script.to_string();
+            failures.last_error = error.to_string();
+            failures.unlogged = 0;
+            failures.last_logged = now;
+        } else {
+            failures.unlogged += 1;
+            if now.duration_since(failures.last_logged) >= SUMMARY_INTERVAL {
+                log::warn!(
+                    "Script '{}' for entity {:?} failed {} more times with the same error",
+                    script,
+                    entity,
+                    failures.unlogged
+                );
+                failures.unlogged = 0;
+                failures.last_logged = now;
+            }
+        }
+
+        if disable_after.is_some_and(|limit| failures.in_a_row >= limit) {
+            failures.disabled = true;
+            log::error!(
+                "Disabled script '{}' for entity {:?} after it failed {} times in a row",
+                script,
+                entity,
+                failures.in_a_row
+            );
+        }
+    }
+
+    /// Records that the script on `entity` updated without failing.
+    pub fn record_success(&mut self, entity: hecs::Entity) {
+        if let Some(failures) = self.scripts.get_mut(&entity) {
+            failures.in_a_row = 0;
+        }
+    }
+
+    /// Disables the script on `entity` straight away, such as when it couldn't be initialised.
+    pub fn disable(&mut self, entity: hecs::Entity, script: &str) {
+        let failures = self.failures(entity, script, Instant::now());
+        failures.total += 1;
+        failures.disabled = true;
+    }
+
+    /// Whether the script on `entity` has been disabled for failing too often.
+    pub fn is_disabled(&self, entity: hecs::Entity) -> bool {
+        self.scripts
+            .get(&entity)
+            .is_some_and(|failures| failures.disabled)
+    }
+
+    /// How many times scripts have failed since the scene loaded.
+    pub fn total(&self) -> u32 {
+        self.scripts.values().map(|failures| failures.total).sum()
+    }
+
+    /// Forgets `entity`'s failures, for when its script is reloaded.
+    pub fn forget(&mut self, entity: hecs::Entity) {
+        self.scripts.remove(&entity);
+    }
+
+    /// Forgets every failure, for when the scene is unloaded.
+    pub fn clear(&mut self) {
+        self.scripts.clear();
+    }
+
+    fn failures(&mut self, entity: hecs::Entity, script: &str, now: Instant) -> &mut Failures {
+        self.scripts.entry(entity).or_insert_with(|| Failures {
+            script: script.to_string(),
+            total: 0,
+            in_a_row: 0,
+            last_error: String::new(),
+            unlogged: 0,
+            last_logged: now,
+            disabled: false,
+        })
+    }
+}
+
+/// The message a panic was raised with, if it was raised with one.
+pub fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message
+    } else {
+        "no message"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// How many failures of `entity`'s script haven't been logged, and when it was last logged.
+    fn unlogged(failures: &ScriptFailures, entity: hecs::Entity) -> (u32, Instant) {
+        let failures = &failures.scripts[&entity];
+        (failures.unlogged, failures.last_logged)
+    }
+
+    #[test]
+    fn only_the_first_of_the_same_failures_is_logged() {
+        let entity = hecs::World::new().spawn(());
+        let mut failures = ScriptFailures::default();
+        let start = Instant::now();
+        for frame in 0..5 {
+            let now = start + Duration::from_millis(16 * frame);
+            failures.record(entity, "player", "boom", now, None);
+        }
+
+        assert_eq!(unlogged(&failures, entity), (4, start));
+        assert_eq!(failures.total(), 5);
+    }
+
+    #[test]
+    fn repeats_are_summarised_after_the_interval() {
+        let entity = hecs::World::new().spawn(());
+        let mut failures = ScriptFailures::default();
+        let start = Instant::now();
+        for _ in 0..4 {
+            failures.record(entity, "player", "boom", start, None);
+        }
+        assert_eq!(unlogged(&failures, entity), (3, start));
+
+        // the summary covers the 3 repeats already counted and this one
+        let later = start + SUMMARY_INTERVAL;
+        failures.record(entity, "player", "boom", later, None);
+        assert_eq!(unlogged(&failures, entity), (0, later));
+        assert_eq!(failures.total(), 5);
+    }
+
+    #[test]
+    fn a_different_failure_is_logged_in_full() {
+        let entity = hecs::World::new().spawn(());
+        let mut failures = ScriptFailures::default();
+        let start = Instant::now();
+        failures.record(entity, "player", "boom", start, None);
+        failures.record(entity, "player", "boom", start, None);
+
+        let later = start + Duration::from_secs(1);
+        failures.record(entity, "player", "bang", later, None);
+        assert_eq!(unlogged(&failures, entity), (0, later));
+    }
+
+    #[test]
+    fn scripts_are_disabled_after_failing_too_often_in_a_row() {
+        let entity = hecs::World::new().spawn(());
+        let mut failures = ScriptFailures::default();
+        let now = Instant::now();
+        failures.record(entity, "player", "boom", now, Some(3));
+        failures.record(entity, "player", "boom", now, Some(3));
+        assert!(!failures.is_disabled(entity));
+
+        failures.record(entity, "player", "boom", now, Some(3));
+        assert!(failures.is_disabled(entity));
+    }
+
+    #[test]
+    fn updating_without_failing_starts_the_run_over() {
+        let entity = hecs::World::new().spawn(());
+        let mut failures = ScriptFailures::default();
+        let now = Instant::now();
+        failures.record(entity, "player", "boom", now, Some(2));
+        failures.record_success(entity);
+        failures.record(entity, "player", "boom", now, Some(2));
+        assert!(!failures.is_disabled(entity));
+        assert_eq!(failures.total(), 2);
+    }
+
+    #[test]
+    fn scripts_are_never_disabled_without_a_limit() {
+        let entity = hecs::World::new().spawn(());
+        let mut failures = ScriptFailures::default();
+        let now = Instant::now();
+        for _ in 0..100 {
+            failures.record(entity, "player", "boom", now, None);
+        }
+        assert!(!failures.is_disabled(entity));
+    }
+
+    #[test]
+    fn forgotten_scripts_start_over() {
+        let entity = hecs::World::new().spawn(());
+        let mut failures = ScriptFailures::default();
+        failures.disable(entity, "player");
+        failures.forget(entity);
+        assert!(!failures.is_disabled(entity));
+        assert_eq!(failures.total(), 0);
+    }
+
+    #[test]
+    fn panic_messages_come_from_str_and_string_payloads() {
+        let payload: Box<dyn Any + Send> = Box::new("boom");
+        assert_eq!(panic_message(payload.as_ref()), "boom");
+
+        let payload: Box<dyn Any + Send> = Box::new(String::from("bang"));
+        assert_eq!(panic_message(payload.as_ref()), "bang");
+
+        let payload: Box<dyn Any + Send> = Box::new(42);
+        assert_eq!(panic_message(payload.as_ref()), "no message");
+    }
+}
@@ -67,32 +67,198 @@ var t_diffuse: texture_2d<f32>;
 @group(0) @binding(1)
 var s_diffuse: sampler;
 
+struct Shadow {
+    view_proj: mat4x4<f32>,
+    enabled: u32,
+}
+@group(3) @binding(0)
+var<uniform> shadow: Shadow;
+@group(3) @binding(1)
+var t_shadow: texture_depth_2d;
+@group(3) @binding(2)
+var s_shadow: sampler_comparison;
+
+// how much of the shadow casting light reaches a fragment, from 0 (fully shadowed) to 1
+fn shadow_visibility(world_position: vec3<f32>) -> f32 {
+    let light_space = shadow.view_proj * vec4<f32>(world_position, 1.0);
+    let ndc = light_space.xyz / light_space.w;
+    let uv = ndc.xy * vec2<f32>(0.5, -0.5) + vec2<f32>(0.5, 0.5);
+
+    let bias = 0.005;
+    let visibility = textureSampleCompareLevel(t_shadow, s_shadow, uv, ndc.z - bias);
+
+    let outside = any(uv < vec2<f32>(0.0)) || any(uv > vec2<f32>(1.0)) || ndc.z > 1.0;
+    if (shadow.enabled == 0u || outside) {
+        return 1.0;
+    }
+    return visibility;
+}
+
+struct LightParam {
+    // constant, linear and quadratic attenuation, then the range, with 0 reaching everywhere
+    attenuation: vec4<f32>,
+    // which way directional and spot lights shine, then the kind of light in w
+    direction: vec4<f32>,
+    // the cosines of a spot light's inner and outer angles
+    cone: vec4<f32>,
+}
+struct LightParams {
+    // the scene's ambient colour, already scaled by its intensity
+    ambient: vec4<f32>,
+    // one per light entities are lit by, so it's as long as MAX_LIGHTS in lights.rs
+    lights: array<LightParam, 1>,
+}
+@group(3) @binding(3)
+var<uniform> light_params: LightParams;
+
+@group(3) @binding(4)
+var t_normal: texture_2d<f32>;
+@group(3) @binding(5)
+var s_normal: sampler;
+
+struct Material {
+    // 1 when t_normal is the entity's normal map, 0 when it's flat and should be left alone
+    normal_map: u32,
+    metallic: f32,
+    roughness: f32,
+}
+@group(3) @binding(6)
+var<uniform> material: Material;
+// blue scales the metallic factor and green the roughness, as in glTF
+@group(3) @binding(7)
+var t_metallic_roughness: texture_2d<f32>;
+
+// bends `normal` by the tangent-space normal map texel `sampled`. The vertices carry no tangents,
+// so the tangent frame comes from how the position and texture coordinates change across the
+// pixel instead
+fn apply_normal_map(
+    normal: vec3<f32>,
+    world_position: vec3<f32>,
+    tex_coords: vec2<f32>,
+    sampled: vec3<f32>,
+) -> vec3<f32> {
+    let dp1 = dpdx(world_position);
+    let dp2 = dpdy(world_position);
+    let duv1 = dpdx(tex_coords);
+    let duv2 = dpdy(tex_coords);
+
+    let dp2_perp = cross(dp2, normal);
+    let dp1_perp = cross(normal, dp1);
+    let tangent = dp2_perp * duv1.x + dp1_perp * duv2.x;
+    let bitangent = dp2_perp * duv1.y + dp1_perp * duv2.y;
+    // scaled the same way, so stretched texture coordinates don't skew the frame
+    let scale = inverseSqrt(max(max(dot(tangent, tangent), dot(bitangent, bitangent)), 1e-12));
+    let tbn = mat3x3<f32>(tangent * scale, bitangent * scale, normal);
+    return normalize(tbn * (sampled * 2.0 - 1.0));
+}
+
+const PI: f32 = 3.14159265;
+
+// how many of the surface's microfacets face along the half vector, from the GGX distribution
+fn distribution_ggx(n_dot_h: f32, roughness: f32) -> f32 {
+    let a = roughness * roughness;
+    let a2 = a * a;
+    let d = n_dot_h * n_dot_h * (a2 - 1.0) + 1.0;
+    return a2 / (PI * d * d);
+}
+
+// how much light the microfacets block from each other, by Smith's method with Schlick-GGX
+fn geometry_smith(n_dot_v: f32, n_dot_l: f32, roughness: f32) -> f32 {
+    let k = (roughness + 1.0) * (roughness + 1.0) / 8.0;
+    let view = n_dot_v / (n_dot_v * (1.0 - k) + k);
+    let light = n_dot_l / (n_dot_l * (1.0 - k) + k);
+    return view * light;
+}
+
+// how much light is reflected rather than refracted, by Schlick's approximation
+fn fresnel_schlick(cos_theta: f32, f0: vec3<f32>) -> vec3<f32> {
+    return f0 + (1.0 - f0) * pow(clamp(1.0 - cos_theta, 0.0, 1.0), 5.0);
+}
+
+const LIGHT_POINT: u32 = 0u;
+const LIGHT_DIRECTIONAL: u32 = 1u;
+const LIGHT_SPOT: u32 = 2u;
+
+// how much of a light with `params` is left `distance` away from it, from 0 to 1
+fn attenuation(params: vec4<f32>, distance: f32) -> f32 {
+    let falloff = params.x + params.y * distance + params.z * distance * distance;
+    var window = 1.0;
+    if (params.w > 0.0) {
+        let ratio = distance / params.w;
+        window = pow(clamp(1.0 - ratio * ratio * ratio * ratio, 0.0, 1.0), 2.0);
+    }
+    if (falloff <= 0.0) {
+        return window;
+    }
+    return min(window / falloff, 1.0);
+}
+
+// how far inside a spot light's cone `light_dir`, pointing back at the light, is, from 0 to 1
+fn spot_cone(params: LightParam, light_dir: vec3<f32>) -> f32 {
+    let cos_angle = dot(-light_dir, normalize(params.direction.xyz));
+    return smoothstep(params.cone.y, params.cone.x, cos_angle);
+}
+
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let visibility = shadow_visibility(in.world_position);
+
     var tex_color = textureSample(t_diffuse, s_diffuse, in.tex_coords);
+    // sampled before anything is discarded, as the derivatives need every pixel around
+    let normal_texel = textureSample(t_normal, s_normal, in.tex_coords).xyz;
+    let metallic_roughness = textureSample(t_metallic_roughness, s_normal, in.tex_coords);
+    let geometric_normal = normalize(in.world_normal);
+    let mapped_normal =
+        apply_normal_map(geometric_normal, in.world_position, in.tex_coords, normal_texel);
+    let normal = select(geometric_normal, mapped_normal, material.normal_map != 0u);
     if (tex_color.a < 0.1) {
         discard;
     }
 
-    let ambient_strength = 0.1; // could be potential value to update???
-
-    // ambient
-    let ambient_color = light.color * ambient_strength;
+    // ambient, which reaches everywhere so unlit surfaces never go fully black
+    let ambient_color = light_params.ambient.rgb;
 
-    let light_dir = normalize(light.position - in.world_position);
+    // the light uniform is the nearest enabled light, whose parameters come first
+    let params = light_params.lights[0];
+    var light_dir = normalize(light.position - in.world_position);
+    let light_distance = distance(light.position, in.world_position);
+    var falloff = attenuation(params.attenuation, light_distance);
+    switch u32(params.direction.w) {
+        case LIGHT_DIRECTIONAL: {
+            // as good as infinitely far away, so it's the same everywhere
+            light_dir = normalize(-params.direction.xyz);
+            falloff = 1.0;
+        }
+        case LIGHT_SPOT: {
+            falloff *= spot_cone(params, light_dir);
+        }
+        default: {}
+    }
 
-    // dihffuse
-    let diffuse_strength = max(dot(in.world_normal, light_dir), 0.0);
-    let diffuse_color = light.color * diffuse_strength;
+    let albedo = tex_color.rgb;
+    let metallic = clamp(material.metallic * metallic_roughness.b, 0.0, 1.0);
+    let roughness = clamp(material.roughness * metallic_roughness.g, 0.04, 1.0);
 
-    // specular
     let view_dir = normalize(camera.view_pos.xyz - in.world_position);
     let half_dir = normalize(view_dir + light_dir);
+    let n_dot_l = max(dot(normal, light_dir), 0.0);
+    let n_dot_v = max(dot(normal, view_dir), 1e-4);
+    let n_dot_h = max(dot(normal, half_dir), 0.0);
+
+    // specular, tinted by the surface for metals and close to white for everything else
+    let f0 = mix(vec3<f32>(0.04), albedo, metallic);
+    let fresnel = fresnel_schlick(max(dot(half_dir, view_dir), 0.0), f0);
+    let specular = distribution_ggx(n_dot_h, roughness)
+        * geometry_smith(n_dot_v, n_dot_l, roughness)
+        * fresnel / max(4.0 * n_dot_v * n_dot_l, 1e-4);
 
-    let specular_strength = pow(max(dot(in.world_normal, half_dir), 0.0), 32.0);
-    let specular_color = specular_strength * light.color;
+    // diffuse, which metals don't have, out of whatever light wasn't reflected
+    let diffuse = (vec3<f32>(1.0) - fresnel) * (1.0 - metallic) * albedo;
 
-    let result = (ambient_color + diffuse_color + specular_color) * tex_color.xyz;
+    // light colours are taken to already carry the rendering equation's pi, so a non-metal's
+    // diffuse matches plain Lambert shading
+    let direct = (diffuse + specular * PI) * light.color * n_dot_l;
+    let result = ambient_color * albedo + direct * visibility * falloff;
 
     return vec4<f32>(result, tex_color.a);
 }
@@ -0,0 +1,37 @@
+// Checking that the scene shaders compile on this device before any scene loads.
+//
+// The scene shaders are compiled once as soon as the runtime gets the device, before the first
+// scene loads. A shader the device's backend can't compile is shown to the player as an error,
+// along with the compiler's log, which says which device and shader the problem is with.
+
+use dropbear_engine::wgpu;
+
+use crate::gpu;
+
+/// The shaders every scene is drawn with, by file name.
+const SCENE_SHADERS: [(&str, &str); 2] = [
+    ("shader.wgsl", include_str!("shader.wgsl")),
+    ("light.wgsl", include_str!("light.wgsl")),
+];
+
+/// Compiles every scene shader on `device`, failing with the log of the first one that doesn't.
+pub fn check(device: &wgpu::Device) -> anyhow::Result<()> {
+    SCENE_SHADERS
+        .iter()
+        .try_for_each(|(name, source)| compile(device, name, source))
+}
+
+/// Compiles the WGSL `source` on `device`, failing with the compiler's log if it doesn't.
+pub fn compile(device: &wgpu::Device, name: &str, source: &str) -> anyhow::Result<()> {
+    device.push_error_scope(wgpu::ErrorFilter::Validation);
+    let _ = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+        label: Some(name),
+        source: wgpu::ShaderSource::Wgsl(source.into()),
+    });
+    // popping the scope doesn't finish straight away on the web, so broken shaders are only
+    // caught there once the scene loads
+    match gpu::ready(device.pop_error_scope()).flatten() {
+        Some(error) => Err(anyhow::anyhow!("{} failed to compile: {}", name, error)),
+        None => Ok(()),
+    }
+}
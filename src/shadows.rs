@@ -0,0 +1,407 @@
+// Shadow mapping for a single light.
+//
+// Before the main pass, every entity is drawn depth-only from the point of view of the shadow
+// casting light into the shadow map. The entity shader then compares each fragment's depth from
+// the light against the map to tell whether anything was in the way. Only one light casts shadows
+// at a time: the lowest entity id among the enabled lights carrying [`CastsShadows`].
+//
+// The light shines from its position towards the world origin, with an orthographic projection
+// like a directional light such as the sun.
+//
+// The entity pipeline already uses the four bind groups every device supports, so the shadow
+// map's group also carries the runtime's per-light parameters from [`crate::lights::LightParams`]
+// and the entity's material from [`crate::materials`]. That makes a bind group for each material
+// rather than just the one.
+
+use dropbear_engine::{
+    entity::{AdoptedEntity, InstanceRaw},
+    graphics::{Graphics, Texture},
+    lighting::{Light, LightComponent},
+    model::{ModelVertex, Vertex},
+    wgpu,
+};
+use glam::{DMat4, DVec3};
+use hecs::World;
+
+use crate::materials::MaterialResources;
+
+/// The width and height of the shadow map, in texels.
+pub const SHADOW_MAP_SIZE: u32 = 2048;
+
+/// Half the width of the area around the world origin that can be shadowed.
+pub const SHADOW_EXTENT: f64 = 50.0;
+
+/// How far from the light the shadow map reaches.
+pub const SHADOW_DISTANCE: f64 = 200.0;
+
+/// Marks a light as casting shadows.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CastsShadows;
+
+/// The shadow map and everything needed to render into and sample from it.
+pub struct ShadowMap {
+    pipeline: wgpu::RenderPipeline,
+    depth_view: wgpu::TextureView,
+    sampler: wgpu::Sampler,
+    uniform: wgpu::Buffer,
+    light_params: wgpu::Buffer,
+    layout: wgpu::BindGroupLayout,
+    /// Bound by the entity pipeline to sample the shadow map, for entities without a material.
+    bind_group: wgpu::BindGroup,
+    /// Bound by the shadow pass, which only needs the light's view projection.
+    pass_bind_group: wgpu::BindGroup,
+    enabled: bool,
+}
+
+impl ShadowMap {
+    /// Creates the shadow map, with `light_params` bound next to it for the entity shader.
+    pub fn new(graphics: &Graphics, light_params: &wgpu::Buffer) -> Self {
+        let texture_entry = |binding, sample_type| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Texture {
+                sample_type,
+                view_dimension: wgpu::TextureViewDimension::D2,
+                multisampled: false,
+            },
+            count: None,
+        };
+        let uniform_entry = |binding| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let device = &graphics.state.device;
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Shadow Map"),
+            size: wgpu::Extent3d {
+                width: SHADOW_MAP_SIZE,
+                height: SHADOW_MAP_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: Texture::DEPTH_FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::TEXTURE_BINDING,
+            view_formats: &[],
+        });
+        let depth_view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Shadow Map Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            compare: Some(wgpu::CompareFunction::LessEqual),
+            ..Default::default()
+        });
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Shadow Uniform"),
+            size: SHADOW_UNIFORM_SIZE,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow Bind Group Layout"),
+            entries: &[
+                uniform_entry(0),
+                texture_entry(1, wgpu::TextureSampleType::Depth),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Comparison),
+                    count: None,
+                },
+                uniform_entry(3),
+                // the material's normal map, the sampler for its maps, its uniform and its
+                // metallic-roughness map
+                texture_entry(4, wgpu::TextureSampleType::Float { filterable: true }),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 5,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+                uniform_entry(6),
+                texture_entry(7, wgpu::TextureSampleType::Float { filterable: true }),
+            ],
+        });
+
+        let pass_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Shadow Pass Bind Group Layout"),
+            entries: &[uniform_entry(0)],
+        });
+        let pass_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shadow Pass Bind Group"),
+            layout: &pass_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Shadow Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("shadow.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Shadow Pipeline Layout"),
+            bind_group_layouts: &[&pass_layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Shadow Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[ModelVertex::desc(), InstanceRaw::desc()],
+                compilation_options: Default::default(),
+            },
+            fragment: None,
+            primitive: wgpu::PrimitiveState {
+                cull_mode: Some(wgpu::Face::Back),
+                ..Default::default()
+            },
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: true,
+                depth_compare: wgpu::CompareFunction::Less,
+                stencil: wgpu::StencilState::default(),
+                // pushes depths back a little to keep surfaces from shadowing themselves
+                bias: wgpu::DepthBiasState {
+                    constant: 2,
+                    slope_scale: 2.0,
+                    clamp: 0.0,
+                },
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let bind_group = create_bind_group(
+            graphics,
+            &layout,
+            [
+                uniform.as_entire_binding(),
+                wgpu::BindingResource::TextureView(&depth_view),
+                wgpu::BindingResource::Sampler(&sampler),
+                light_params.as_entire_binding(),
+            ],
+            &MaterialResources::flat(graphics),
+        );
+
+        Self {
+            pipeline,
+            depth_view,
+            sampler,
+            uniform,
+            light_params: light_params.clone(),
+            layout,
+            bind_group,
+            pass_bind_group,
+            enabled: false,
+        }
+    }
+
+    /// Creates a bind group sampling the shadow map for entities drawn with `material`.
+    pub fn create_bind_group(
+        &self,
+        graphics: &Graphics,
+        material: &MaterialResources,
+    ) -> wgpu::BindGroup {
+        create_bind_group(
+            graphics,
+            &self.layout,
+            [
+                self.uniform.as_entire_binding(),
+                wgpu::BindingResource::TextureView(&self.depth_view),
+                wgpu::BindingResource::Sampler(&self.sampler),
+                self.light_params.as_entire_binding(),
+            ],
+            material,
+        )
+    }
+
+    /// The layout of [`ShadowMap::bind_group`], for creating the entity pipeline.
+    pub fn layout(&self) -> &wgpu::BindGroupLayout {
+        &self.layout
+    }
+
+    pub fn bind_group(&self) -> &wgpu::BindGroup {
+        &self.bind_group
+    }
+
+    /// Points the shadow map at the light at `caster`, or turns shadows off if there isn't one.
+    pub fn update(&mut self, graphics: &Graphics, caster: Option<DVec3>) {
+        self.enabled = caster.is_some();
+        let view_proj = caster.map_or(DMat4::IDENTITY, light_view_proj);
+
+        let mut bytes: Vec<u8> = view_proj
+            .as_mat4()
+            .to_cols_array()
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        bytes.extend_from_slice(&u32::from(self.enabled).to_ne_bytes());
+        bytes.resize(SHADOW_UNIFORM_SIZE as usize, 0);
+        graphics.state.queue.write_buffer(&self.uniform, 0, &bytes);
+    }
+
+    /// Renders the depth of `entities` into the shadow map, submitting it ahead of the frame's
+    /// main pass. Does nothing while shadows are off.
+    pub fn render<'a>(
+        &self,
+        graphics: &Graphics,
+        entities: impl IntoIterator<Item = &'a AdoptedEntity>,
+    ) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut encoder =
+            graphics
+                .state
+                .device
+                .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                    label: Some("Shadow Pass Encoder"),
+                });
+        {
+            let mut render_pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+                label: Some("Shadow Pass"),
+                color_attachments: &[],
+                depth_stencil_attachment: Some(wgpu::RenderPassDepthStencilAttachment {
+                    view: &self.depth_view,
+                    depth_ops: Some(wgpu::Operations {
+                        load: wgpu::LoadOp::Clear(1.0),
+                        store: wgpu::StoreOp::Store,
+                    }),
+                    stencil_ops: None,
+                }),
+                timestamp_writes: None,
+                occlusion_query_set: None,
+            });
+            render_pass.set_pipeline(&self.pipeline);
+            render_pass.set_bind_group(0, &self.pass_bind_group, &[]);
+
+            for entity in entities {
+                let Some(instance_buffer) = entity.instance_buffer.as_ref() else {
+                    continue;
+                };
+                render_pass.set_vertex_buffer(1, instance_buffer.slice(..));
+                for mesh in &entity.model().meshes {
+                    render_pass.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+                    render_pass
+                        .set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
+                    render_pass.draw_indexed(0..mesh.num_elements, 0, 0..1);
+                }
+            }
+        }
+        graphics
+            .state
+            .queue
+            .submit(std::iter::once(encoder.finish()));
+    }
+}
+
+/// The size of the shadow uniform: the view projection plus the enabled flag, padded out to the
+/// 16 byte alignment WGSL gives the struct.
+const SHADOW_UNIFORM_SIZE: u64 = 80;
+
+/// Creates a bind group of the entity pipeline's shadow map group, with the shadow map's own
+/// resources in `shadow` followed by `material`'s.
+fn create_bind_group(
+    graphics: &Graphics,
+    layout: &wgpu::BindGroupLayout,
+    shadow: [wgpu::BindingResource<'_>; 4],
+    material: &MaterialResources,
+) -> wgpu::BindGroup {
+    let material_resources = [
+        wgpu::BindingResource::TextureView(&material.normal_map),
+        wgpu::BindingResource::Sampler(&material.sampler),
+        material.uniform.as_entire_binding(),
+        wgpu::BindingResource::TextureView(&material.metallic_roughness_map),
+    ];
+    let entries: Vec<wgpu::BindGroupEntry<'_>> = shadow
+        .into_iter()
+        .chain(material_resources)
+        .enumerate()
+        .map(|(binding, resource)| wgpu::BindGroupEntry {
+            binding: binding as u32,
+            resource,
+        })
+        .collect();
+    graphics
+        .state
+        .device
+        .create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Shadow Bind Group"),
+            layout,
+            entries: &entries,
+        })
+}
+
+/// The position of the light that casts shadows this frame, if any.
+pub fn shadow_caster(world: &World) -> Option<DVec3> {
+    world
+        .query::<(&Light, &LightComponent, &CastsShadows)>()
+        .iter()
+        .filter(|(_, (_, component, _))| component.enabled)
+        .min_by_key(|(entity, _)| *entity)
+        .map(|(_, (_, component, _))| component.position)
+}
+
+/// The view projection of a light at `position` shining towards the world origin.
+fn light_view_proj(position: DVec3) -> DMat4 {
+    let direction = (DVec3::ZERO - position).normalize_or(DVec3::NEG_Y);
+    // a light straight above or below the origin would leave look_at without an up direction
+    let up = if direction.abs().dot(DVec3::Y) > 0.99 {
+        DVec3::Z
+    } else {
+        DVec3::Y
+    };
+    let view = DMat4::look_to_rh(position, direction, up);
+    let projection = DMat4::orthographic_rh(
+        -SHADOW_EXTENT,
+        SHADOW_EXTENT,
+        -SHADOW_EXTENT,
+        SHADOW_EXTENT,
+        0.1,
+        SHADOW_DISTANCE,
+    );
+    projection * view
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_origin_is_in_the_middle_of_the_shadow_map() {
+        let origin = light_view_proj(DVec3::new(30.0, 40.0, -20.0)).project_point3(DVec3::ZERO);
+        assert!(origin.x.abs() < 1e-9 && origin.y.abs() < 1e-9, "{}", origin);
+        assert!(origin.z > 0.0 && origin.z < 1.0, "{}", origin);
+    }
+
+    #[test]
+    fn lights_straight_overhead_still_have_a_view() {
+        let view_proj = light_view_proj(DVec3::new(0.0, 100.0, 0.0));
+        assert!(view_proj.is_finite());
+        let origin = view_proj.project_point3(DVec3::ZERO);
+        assert!(origin.x.abs() < 1e-9 && origin.y.abs() < 1e-9, "{}", origin);
+    }
+
+    #[test]
+    fn anything_past_the_shadow_distance_is_outside_the_map() {
+        let light = DVec3::new(0.0, 0.0, SHADOW_DISTANCE / 2.0);
+        let far = light_view_proj(light).project_point3(DVec3::new(0.0, 0.0, -SHADOW_DISTANCE));
+        assert!(far.z > 1.0, "{}", far);
+    }
+}
@@ -0,0 +1,83 @@
+// Getting the game ready for the process to end.
+//
+// The game quits from the pause menu, the quit key or the window closing, which reaches the
+// runtime through the engine's `exit`. Quitting from the runtime ends up in `exit` too, so the
+// teardown only runs the first time. The logs are flushed last, so nothing the teardown logs is
+// lost.
+
+use log::Log;
+
+/// Whether the game has shut down.
+#[derive(Debug, Default)]
+pub struct Shutdown {
+    done: bool,
+}
+
+impl Shutdown {
+    /// Runs `teardown` and then flushes `logger`, unless the game has already shut down. Returns
+    /// whether it ran.
+    pub fn run(&mut self, logger: &dyn Log, teardown: impl FnOnce()) -> bool {
+        if std::mem::replace(&mut self.done, true) {
+            return false;
+        }
+        log::info!("Shutting down");
+        teardown();
+        logger.flush();
+        true
+    }
+
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use log::{Metadata, Record};
+
+    use super::*;
+
+    /// A logger keeping track of when it's flushed.
+    #[derive(Default)]
+    struct FlushLog {
+        events: Mutex<Vec<&'static str>>,
+    }
+
+    impl Log for FlushLog {
+        fn enabled(&self, _metadata: &Metadata) -> bool {
+            false
+        }
+
+        fn log(&self, _record: &Record) {}
+
+        fn flush(&self) {
+            self.events.lock().unwrap().push("flush");
+        }
+    }
+
+    #[test]
+    fn the_logs_are_flushed_after_the_teardown() {
+        let logger = FlushLog::default();
+        let mut shutdown = Shutdown::default();
+        assert!(!shutdown.is_done());
+
+        let ran = shutdown.run(&logger, || logger.events.lock().unwrap().push("teardown"));
+        assert!(ran);
+        assert!(shutdown.is_done());
+        assert_eq!(*logger.events.lock().unwrap(), ["teardown", "flush"]);
+    }
+
+    #[test]
+    fn shutting_down_again_does_nothing() {
+        let logger = FlushLog::default();
+        let mut shutdown = Shutdown::default();
+        shutdown.run(&logger, || {});
+
+        let mut torn_down = false;
+        assert!(!shutdown.run(&logger, || torn_down = true));
+        assert!(!torn_down);
+        assert_eq!(*logger.events.lock().unwrap(), ["flush"]);
+    }
+}
@@ -0,0 +1,117 @@
+// Stopping the game from running twice at once, which would leave two copies writing the same
+// preferences and save files over each other.
+//
+// The copy that starts first holds a lock on a file in the app data directory for as long as it
+// runs, and a copy that can't take the lock doesn't start. The OS lets go of the lock when the
+// process ends however it ends, so a crash can't leave the game unable to start again.
+//
+// Bringing the window of the copy already running to the front would mean talking to it, which
+// the runtime has no way to do, so the second copy only tells the player and exits.
+
+use std::{
+    fs::{File, OpenOptions, TryLockError},
+    io::Write,
+    path::{Path, PathBuf},
+};
+
+use app_dirs2::AppDataType;
+
+use crate::{APP_INFO, package::RuntimeError};
+
+/// The name of the lock file inside the app data `lock` directory.
+pub const LOCK_FILE_NAME: &str = "instance.lock";
+
+/// The path of the lock file, inside the user data directory for [`APP_INFO`].
+pub fn lock_file_path() -> anyhow::Result<PathBuf> {
+    Ok(app_dirs2::get_app_dir(AppDataType::UserData, &APP_INFO, "lock")?.join(LOCK_FILE_NAME))
+}
+
+/// The lock held by the running copy of the game, let go of when dropped.
+#[derive(Debug)]
+pub struct InstanceLock {
+    file: File,
+}
+
+impl Drop for InstanceLock {
+    fn drop(&mut self) {
+        if let Err(e) = self.file.unlock() {
+            log::warn!("Unable to release the single instance lock: {}", e);
+        }
+    }
+}
+
+/// Takes the lock at `path`, or gives back `None` if another copy of the game holds it.
+///
+/// The file is left in place afterwards, as removing it could race with a copy starting at the
+/// same time. It holds the process id of whoever has the lock, to help when looking into a
+/// copy that won't start.
+pub fn acquire(path: &Path) -> std::io::Result<Option<InstanceLock>> {
+    if let Some(dir) = path.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    // not truncated until the lock is ours, so the other copy's process id stays readable
+    let mut file = OpenOptions::new()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(path)?;
+    match file.try_lock() {
+        Ok(()) => {}
+        Err(TryLockError::WouldBlock) => return Ok(None),
+        Err(TryLockError::Error(e)) => return Err(e),
+    }
+    file.set_len(0)?;
+    write!(file, "{}", std::process::id())?;
+    Ok(Some(InstanceLock { file }))
+}
+
+/// Makes sure this is the only copy of the game running, returning the lock to hold on to while
+/// it runs. A lock file that can't be used at all is only logged, as that shouldn't stop the
+/// game from starting.
+pub fn guard() -> Result<Option<InstanceLock>, RuntimeError> {
+    let path = match lock_file_path() {
+        Ok(path) => path,
+        Err(e) => {
+            log::warn!("Unable to find the single instance lock directory: {}", e);
+            return Ok(None);
+        }
+    };
+    match acquire(&path) {
+        Ok(Some(lock)) => {
+            log::debug!("Holding the single instance lock at {}", path.display());
+            Ok(Some(lock))
+        }
+        Ok(None) => Err(RuntimeError::AlreadyRunning),
+        Err(e) => {
+            log::warn!(
+                "Unable to take the single instance lock at {}: {}",
+                path.display(),
+                e
+            );
+            Ok(None)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_one_lock_is_held_at_a_time() {
+        let dir = std::env::temp_dir().join(format!("redback-lock-{}", std::process::id()));
+        let path = dir.join("nested").join(LOCK_FILE_NAME);
+
+        let lock = acquire(&path).unwrap().expect("the first lock is free");
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            std::process::id().to_string()
+        );
+        assert!(acquire(&path).unwrap().is_none());
+
+        drop(lock);
+        assert!(acquire(&path).unwrap().is_some());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
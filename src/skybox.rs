@@ -0,0 +1,294 @@
+// A cubemap drawn behind all of a scene's geometry.
+//
+// The skybox follows the active camera's rotation but never its position, so it always looks
+// infinitely far away. It's drawn as a single triangle over the whole screen on the far plane,
+// with the fragment shader working out which way each pixel looks into the cubemap.
+
+use std::path::PathBuf;
+
+use dropbear_engine::{
+    camera::Camera,
+    graphics::{Graphics, Texture},
+    wgpu,
+};
+use glam::{DMat4, DVec3};
+
+use crate::pipeline::PipelineOptions;
+
+/// Draws a scene's skybox.
+pub struct Skybox {
+    pipeline: wgpu::RenderPipeline,
+    bind_group: wgpu::BindGroup,
+    uniform: wgpu::Buffer,
+}
+
+impl Skybox {
+    /// Loads the six faces of the cubemap, in the order +X, -X, +Y, -Y, +Z, -Z, and creates the
+    /// pipeline that draws them into a target set up as in `options`. Every face has to be the
+    /// same square size.
+    pub fn new(
+        graphics: &Graphics,
+        faces: &[PathBuf; 6],
+        options: &PipelineOptions,
+    ) -> anyhow::Result<Self> {
+        let device = &graphics.state.device;
+
+        let mut images = Vec::with_capacity(faces.len());
+        for face in faces {
+            let image = image::open(face)
+                .map_err(|e| {
+                    anyhow::anyhow!("Unable to load skybox face {}: {}", face.display(), e)
+                })?
+                .to_rgba8();
+            if image.width() != image.height() {
+                anyhow::bail!(
+                    "Skybox face {} is {}x{}, but faces have to be square",
+                    face.display(),
+                    image.width(),
+                    image.height()
+                );
+            }
+            images.push(image);
+        }
+
+        let size = images[0].width();
+        if let Some((face, image)) = faces
+            .iter()
+            .zip(&images)
+            .find(|(_, image)| image.width() != size)
+        {
+            anyhow::bail!(
+                "Skybox face {} is {}x{}, but the first face is {}x{}",
+                face.display(),
+                image.width(),
+                image.height(),
+                size,
+                size
+            );
+        }
+
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Skybox Texture"),
+            size: wgpu::Extent3d {
+                width: size,
+                height: size,
+                depth_or_array_layers: 6,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        for (layer, image) in images.iter().enumerate() {
+            graphics.state.queue.write_texture(
+                wgpu::TexelCopyTextureInfo {
+                    texture: &texture,
+                    mip_level: 0,
+                    origin: wgpu::Origin3d {
+                        x: 0,
+                        y: 0,
+                        z: layer as u32,
+                    },
+                    aspect: wgpu::TextureAspect::All,
+                },
+                image,
+                wgpu::TexelCopyBufferLayout {
+                    offset: 0,
+                    bytes_per_row: Some(4 * size),
+                    rows_per_image: Some(size),
+                },
+                wgpu::Extent3d {
+                    width: size,
+                    height: size,
+                    depth_or_array_layers: 1,
+                },
+            );
+        }
+
+        let view = texture.create_view(&wgpu::TextureViewDescriptor {
+            label: Some("Skybox Texture View"),
+            dimension: Some(wgpu::TextureViewDimension::Cube),
+            ..Default::default()
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Skybox Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Skybox Uniform"),
+            size: std::mem::size_of::<[f32; 16]>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Skybox Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Uniform,
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::Cube,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 2,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Skybox Bind Group"),
+            layout: &layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: uniform.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 2,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Skybox Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("skybox.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Skybox Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Skybox Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: options.color_format,
+                    blend: Some(wgpu::BlendState::REPLACE),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // sits on the far plane, so anything drawn afterwards ends up in front of it
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::LessEqual,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState {
+                count: options.sample_count,
+                ..Default::default()
+            },
+            multiview: None,
+            cache: None,
+        });
+
+        Ok(Self {
+            pipeline,
+            bind_group,
+            uniform,
+        })
+    }
+
+    /// Points the skybox the same way as `camera`.
+    pub fn update(&self, graphics: &Graphics, camera: &Camera) {
+        let inv_view_proj =
+            rotation_only_view_proj(camera.build_vp(), camera.eye, camera.target).inverse();
+        let bytes: Vec<u8> = inv_view_proj
+            .as_mat4()
+            .to_cols_array()
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        graphics.state.queue.write_buffer(&self.uniform, 0, &bytes);
+    }
+
+    /// Draws the skybox. This should be the first thing drawn in the pass.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+/// Takes the translation out of a camera's view projection, leaving only which way it faces.
+///
+/// The view matrix is rebuilt from `eye` and `target` so the projection can be separated out of
+/// `view_proj`, then joined back up with a view that sits at the origin.
+fn rotation_only_view_proj(view_proj: DMat4, eye: DVec3, target: DVec3) -> DMat4 {
+    let view = DMat4::look_at_rh(eye, target, DVec3::Y);
+    let rotation = DMat4::look_at_rh(DVec3::ZERO, target - eye, DVec3::Y);
+    view_proj * view.inverse() * rotation
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view_proj(eye: DVec3, target: DVec3) -> DMat4 {
+        let proj = DMat4::perspective_rh(std::f64::consts::FRAC_PI_2, 1.0, 0.1, 100.0);
+        proj * DMat4::look_at_rh(eye, target, DVec3::Y)
+    }
+
+    #[test]
+    fn moving_the_camera_leaves_the_sky_where_it_is() {
+        let here = rotation_only_view_proj(
+            view_proj(DVec3::ZERO, DVec3::NEG_Z),
+            DVec3::ZERO,
+            DVec3::NEG_Z,
+        );
+        let eye = DVec3::new(10.0, 5.0, -3.0);
+        let there =
+            rotation_only_view_proj(view_proj(eye, eye + DVec3::NEG_Z), eye, eye + DVec3::NEG_Z);
+        assert!(here.abs_diff_eq(there, 1e-9), "{} is not {}", there, here);
+    }
+
+    #[test]
+    fn turning_the_camera_turns_the_sky() {
+        let ahead = rotation_only_view_proj(
+            view_proj(DVec3::ZERO, DVec3::NEG_Z),
+            DVec3::ZERO,
+            DVec3::NEG_Z,
+        );
+        let right =
+            rotation_only_view_proj(view_proj(DVec3::ZERO, DVec3::X), DVec3::ZERO, DVec3::X);
+        assert!(!ahead.abs_diff_eq(right, 1e-9));
+    }
+}
@@ -0,0 +1,36 @@
+// Shader for drawing the skybox behind everything else in the scene
+
+struct Sky {
+    // the inverse of the camera's view projection, without its translation
+    inv_view_proj: mat4x4<f32>,
+}
+@group(0) @binding(0)
+var<uniform> sky: Sky;
+@group(0) @binding(1)
+var sky_texture: texture_cube<f32>;
+@group(0) @binding(2)
+var sky_sampler: sampler;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) ndc: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32) -> VertexOutput {
+    // a single triangle that covers the whole screen, sitting on the far plane
+    let uv = vec2<f32>(f32((index << 1u) & 2u), f32(index & 2u));
+    let ndc = uv * 2.0 - 1.0;
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(ndc, 1.0, 1.0);
+    out.ndc = ndc;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let far = sky.inv_view_proj * vec4<f32>(in.ndc, 1.0, 1.0);
+    let direction = normalize(far.xyz / far.w);
+    return textureSample(sky_texture, sky_sampler, direction);
+}
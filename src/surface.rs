@@ -0,0 +1,120 @@
+// Configuration of the window's graphics surface that the runtime manages on top of the engine.
+
+use std::time::{Duration, Instant};
+
+use dropbear_engine::{graphics::Graphics, wgpu::PresentMode};
+use winit::dpi::PhysicalSize;
+
+use crate::config::FramePacing;
+
+/// The present mode used for a frame pacing mode. The `Auto*` modes fall back to whatever the
+/// device does support, so these never fail to configure.
+pub fn present_mode(pacing: FramePacing) -> PresentMode {
+    match pacing {
+        FramePacing::Uncapped | FramePacing::Capped => PresentMode::AutoNoVsync,
+        FramePacing::Vsync => PresentMode::AutoVsync,
+    }
+}
+
+/// Reconfigures the surface to present with `mode`.
+pub fn apply_present_mode(graphics: &mut Graphics, mode: PresentMode) {
+    if graphics.state.config.present_mode == mode {
+        return;
+    }
+
+    log::info!("Switching present mode to {:?}", mode);
+    graphics.state.config.present_mode = mode;
+    graphics
+        .state
+        .surface
+        .configure(&graphics.state.device, &graphics.state.config);
+}
+
+/// How long the window has to keep the same size before the surface is resized to it. Dragging a
+/// window's edge fires a resize every few pixels, and reconfiguring the surface for each of them
+/// would thrash the GPU.
+pub const RESIZE_SETTLE_TIME: Duration = Duration::from_millis(100);
+
+/// Holds back window resizes until they stop coming.
+#[derive(Debug, Default)]
+pub struct ResizeDebounce {
+    /// The window's size the last time it was looked at.
+    last: Option<PhysicalSize<u32>>,
+    pending: Option<(PhysicalSize<u32>, Instant)>,
+}
+
+impl ResizeDebounce {
+    /// Records that the window is `size` at `now`. The engine doesn't pass resizes on, so this
+    /// is called with the window's size every frame, and only a change from the last size seen
+    /// counts as a resize.
+    pub fn watch(&mut self, size: PhysicalSize<u32>, now: Instant) {
+        if self.last.replace(size).is_some_and(|last| last != size) {
+            self.pending = Some((size, now));
+        }
+    }
+
+    /// The size to resize to, once there hasn't been a resize for [`RESIZE_SETTLE_TIME`].
+    pub fn settled(&mut self, now: Instant) -> Option<PhysicalSize<u32>> {
+        let (size, requested_at) = self.pending?;
+        if now.duration_since(requested_at) < RESIZE_SETTLE_TIME {
+            return None;
+        }
+        self.pending = None;
+        Some(size)
+    }
+}
+
+/// Reconfigures the surface, and the engine's depth texture along with it, to `size`. A zero
+/// size, which is what a minimised window reports, is ignored until the window comes back.
+pub fn resize(graphics: &mut Graphics, size: PhysicalSize<u32>) {
+    if size.width == 0 || size.height == 0 {
+        return;
+    }
+    let config = &graphics.state.config;
+    if (config.width, config.height) == (size.width, size.height) {
+        return;
+    }
+
+    log::debug!("Resizing the surface to {}x{}", size.width, size.height);
+    graphics.state.resize(size);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_first_size_seen_isnt_a_resize() {
+        let mut resize = ResizeDebounce::default();
+        let start = Instant::now();
+        resize.watch(PhysicalSize::new(800, 600), start);
+        assert_eq!(resize.settled(start + RESIZE_SETTLE_TIME), None);
+    }
+
+    #[test]
+    fn resizes_wait_until_the_size_settles() {
+        let mut resize = ResizeDebounce::default();
+        let start = Instant::now();
+        resize.watch(PhysicalSize::new(800, 600), start);
+        resize.watch(PhysicalSize::new(810, 600), start);
+        let later = start + RESIZE_SETTLE_TIME / 2;
+        resize.watch(PhysicalSize::new(820, 600), later);
+        // the same size again doesn't restart the wait
+        resize.watch(PhysicalSize::new(820, 600), later + RESIZE_SETTLE_TIME / 2);
+        assert_eq!(resize.settled(later + RESIZE_SETTLE_TIME / 2), None);
+        assert_eq!(
+            resize.settled(later + RESIZE_SETTLE_TIME),
+            Some(PhysicalSize::new(820, 600))
+        );
+        assert_eq!(resize.settled(later + RESIZE_SETTLE_TIME * 2), None);
+    }
+
+    #[test]
+    fn frame_pacing_picks_a_present_mode_every_device_has() {
+        assert_eq!(present_mode(FramePacing::Vsync), PresentMode::AutoVsync);
+        assert_eq!(
+            present_mode(FramePacing::Uncapped),
+            PresentMode::AutoNoVsync
+        );
+    }
+}
@@ -0,0 +1,483 @@
+// Text drawn in screen space, for HUDs, menus and debug output.
+//
+// Scenes list their text in their settings, and each piece is spawned on an entity of its own
+// when the scene loads, to be drawn over the UI elements every frame. Glyphs are rasterised from
+// the package's font the first time they're drawn at a size and kept in an atlas texture from
+// then on. When the atlas fills up it's cleared and filled again with just the glyphs the frame
+// needs.
+//
+// Packages without a font can't draw any text.
+
+use std::{collections::HashMap, path::Path};
+
+use bincode::{Decode, Encode};
+use dropbear_engine::{
+    graphics::{Graphics, Texture},
+    wgpu,
+};
+use hecs::World;
+
+/// The width and height of the glyph atlas, in pixels.
+pub const ATLAS_SIZE: u32 = 1024;
+
+/// The gap left around each glyph in the atlas, so filtering doesn't bleed into its neighbours.
+const ATLAS_PADDING: u32 = 1;
+
+/// The bytes a glyph takes up in the instance buffer: its rectangle on screen, its rectangle in
+/// the atlas, then its colour.
+const GLYPH_SIZE: usize = 12 * std::mem::size_of::<f32>();
+
+/// Text drawn over the HUD.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct TextRequest {
+    pub text: String,
+    /// Where the top left of the text goes, in the surface's physical pixels from the top left.
+    pub position: [f32; 2],
+    /// The font size in pixels, rounded to a whole pixel.
+    pub size: f32,
+    /// The RGBA colour the text is drawn in.
+    pub color: [f32; 4],
+}
+
+/// Every piece of text in `world`.
+pub fn queued(world: &World) -> Vec<TextRequest> {
+    world
+        .query::<&TextRequest>()
+        .iter()
+        .map(|(_, request)| request.clone())
+        .collect()
+}
+
+/// A glyph placed on screen.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PlacedGlyph {
+    pub character: char,
+    /// The font size the glyph is rasterised at, in whole pixels.
+    pub size: u32,
+    /// Where the top left of the glyph's bitmap goes, in pixels from the top left of the screen.
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Lays `request`'s text out in `font`, a line at a time. Characters with nothing to draw, such
+/// as spaces, only move the following ones along.
+pub fn layout(font: &fontdue::Font, request: &TextRequest) -> Vec<PlacedGlyph> {
+    let size = request.size.round().max(1.0) as u32;
+    let px = size as f32;
+    let (ascent, line_height) = font
+        .horizontal_line_metrics(px)
+        .map_or((px * 0.8, px * 1.2), |metrics| {
+            (metrics.ascent, metrics.new_line_size)
+        });
+
+    let mut glyphs = Vec::new();
+    let [left, top] = request.position;
+    for (line, text) in request.text.lines().enumerate() {
+        let baseline = top + ascent + line as f32 * line_height;
+        let mut pen = left;
+        for character in text.chars() {
+            let metrics = font.metrics(character, px);
+            if metrics.width > 0 && metrics.height > 0 {
+                glyphs.push(PlacedGlyph {
+                    character,
+                    size,
+                    x: (pen + metrics.xmin as f32).round(),
+                    y: (baseline - (metrics.ymin + metrics.height as i32) as f32).round(),
+                });
+            }
+            pen += metrics.advance_width;
+        }
+    }
+    glyphs
+}
+
+/// Where a glyph's bitmap is in the atlas, in pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct AtlasRegion {
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+}
+
+/// The glyphs rasterised so far, packed into a single texture in rows.
+struct GlyphAtlas {
+    texture: wgpu::Texture,
+    bind_group: wgpu::BindGroup,
+    glyphs: HashMap<(char, u32), AtlasRegion>,
+    rows: AtlasRows,
+}
+
+/// Where in the atlas the next bitmap goes, filling it a row at a time.
+#[derive(Debug, Clone, Copy, Default)]
+struct AtlasRows {
+    /// Where the next bitmap goes in the current row.
+    cursor: (u32, u32),
+    /// How tall the current row is so far.
+    row_height: u32,
+}
+
+impl AtlasRows {
+    /// Finds room for a `width` by `height` bitmap, starting a new row when the current one is
+    /// full.
+    fn allocate(&mut self, width: u32, height: u32) -> Option<(u32, u32)> {
+        let (width, height) = (width + ATLAS_PADDING, height + ATLAS_PADDING);
+        if width > ATLAS_SIZE {
+            return None;
+        }
+        if self.cursor.0 + width > ATLAS_SIZE {
+            self.cursor = (0, self.cursor.1 + self.row_height);
+            self.row_height = 0;
+        }
+        if self.cursor.1 + height > ATLAS_SIZE {
+            return None;
+        }
+        let position = self.cursor;
+        self.cursor.0 += width;
+        self.row_height = self.row_height.max(height);
+        Some(position)
+    }
+}
+
+impl GlyphAtlas {
+    fn new(graphics: &Graphics, layout: &wgpu::BindGroupLayout) -> Self {
+        let device = &graphics.state.device;
+        let texture = device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("Glyph Atlas"),
+            size: wgpu::Extent3d {
+                width: ATLAS_SIZE,
+                height: ATLAS_SIZE,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::R8Unorm,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("Glyph Atlas Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Glyph Atlas Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(&sampler),
+                },
+            ],
+        });
+
+        Self {
+            texture,
+            bind_group,
+            glyphs: HashMap::new(),
+            rows: AtlasRows::default(),
+        }
+    }
+
+    /// Rasterises `glyph` into the atlas if it isn't there yet, returning `false` if it doesn't
+    /// fit in what's left.
+    fn insert(&mut self, graphics: &Graphics, font: &fontdue::Font, glyph: &PlacedGlyph) -> bool {
+        let key = (glyph.character, glyph.size);
+        if self.glyphs.contains_key(&key) {
+            return true;
+        }
+
+        let (metrics, bitmap) = font.rasterize(glyph.character, glyph.size as f32);
+        let (width, height) = (metrics.width as u32, metrics.height as u32);
+        let Some((x, y)) = self.rows.allocate(width, height) else {
+            return false;
+        };
+        graphics.state.queue.write_texture(
+            wgpu::TexelCopyTextureInfo {
+                texture: &self.texture,
+                mip_level: 0,
+                origin: wgpu::Origin3d { x, y, z: 0 },
+                aspect: wgpu::TextureAspect::All,
+            },
+            &bitmap,
+            wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(width),
+                rows_per_image: Some(height),
+            },
+            wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+        );
+        self.glyphs.insert(
+            key,
+            AtlasRegion {
+                x,
+                y,
+                width,
+                height,
+            },
+        );
+        true
+    }
+
+    /// Forgets every glyph, so the whole atlas can be filled again.
+    fn clear(&mut self) {
+        self.glyphs.clear();
+        self.rows = AtlasRows::default();
+    }
+}
+
+/// Draws the text in the world.
+pub struct TextRenderer {
+    pipeline: wgpu::RenderPipeline,
+    atlas: GlyphAtlas,
+    font: Option<fontdue::Font>,
+    instance_buffer: Option<wgpu::Buffer>,
+    count: u32,
+}
+
+impl TextRenderer {
+    /// Creates the pipeline drawing text over the surface in the font at `font_path`. Without a
+    /// font, or if it can't be loaded, nothing is drawn.
+    pub fn new(graphics: &Graphics, font_path: Option<&Path>) -> Self {
+        let device = &graphics.state.device;
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Text Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Text Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("text.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Text Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Text Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: GLYPH_SIZE as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![
+                        0 => Float32x4,
+                        1 => Float32x4,
+                        2 => Float32x4
+                    ],
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: graphics.state.config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // the surface pass comes with the engine's depth buffer, which this never touches
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let font = font_path.and_then(|path| match load_font(path) {
+            Ok(font) => Some(font),
+            Err(e) => {
+                log::error!("Unable to load font {}: {}", path.display(), e);
+                None
+            }
+        });
+
+        Self {
+            pipeline,
+            atlas: GlyphAtlas::new(graphics, &layout),
+            font,
+            instance_buffer: None,
+            count: 0,
+        }
+    }
+
+    /// Lays out and uploads `requests`, rasterising the glyphs that aren't in the atlas yet.
+    pub fn update(&mut self, graphics: &Graphics, requests: &[TextRequest]) {
+        self.count = 0;
+        let Some(font) = &self.font else {
+            return;
+        };
+
+        let mut glyphs = Vec::new();
+        for request in requests {
+            glyphs.extend(
+                layout(font, request)
+                    .into_iter()
+                    .map(|glyph| (glyph, request.color)),
+            );
+        }
+        if glyphs.is_empty() {
+            return;
+        }
+
+        // a full atlas starts over with just this frame's glyphs, and whatever still doesn't
+        // fit after that isn't drawn
+        if !glyphs
+            .iter()
+            .all(|(glyph, _)| self.atlas.insert(graphics, font, glyph))
+        {
+            log::debug!("The glyph atlas is full, filling it again");
+            self.atlas.clear();
+            for (glyph, _) in &glyphs {
+                self.atlas.insert(graphics, font, glyph);
+            }
+        }
+
+        let (width, height) = (
+            graphics.state.config.width as f32,
+            graphics.state.config.height as f32,
+        );
+        let mut instances = Vec::with_capacity(glyphs.len() * GLYPH_SIZE);
+        for (glyph, color) in &glyphs {
+            let Some(region) = self.atlas.glyphs.get(&(glyph.character, glyph.size)) else {
+                continue;
+            };
+            let rect = [
+                glyph.x / width * 2.0 - 1.0,
+                1.0 - glyph.y / height * 2.0,
+                (glyph.x + region.width as f32) / width * 2.0 - 1.0,
+                1.0 - (glyph.y + region.height as f32) / height * 2.0,
+            ];
+            let atlas_size = ATLAS_SIZE as f32;
+            let uv_rect = [
+                region.x as f32 / atlas_size,
+                region.y as f32 / atlas_size,
+                (region.x + region.width) as f32 / atlas_size,
+                (region.y + region.height) as f32 / atlas_size,
+            ];
+            for value in rect.into_iter().chain(uv_rect).chain(*color) {
+                instances.extend(value.to_ne_bytes());
+            }
+            self.count += 1;
+        }
+        if instances.is_empty() {
+            return;
+        }
+
+        let needed = instances.len() as u64;
+        if self
+            .instance_buffer
+            .as_ref()
+            .is_none_or(|buffer| buffer.size() < needed)
+        {
+            self.instance_buffer = Some(graphics.state.device.create_buffer(
+                &wgpu::BufferDescriptor {
+                    label: Some("Text Instance Buffer"),
+                    size: needed.next_power_of_two(),
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                },
+            ));
+        }
+        if let Some(buffer) = &self.instance_buffer {
+            graphics.state.queue.write_buffer(buffer, 0, &instances);
+        }
+    }
+
+    /// Draws the text uploaded by the last [`Self::update`] over whatever is already in
+    /// `render_pass`, returning whether there was any to draw.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass<'_>) -> bool {
+        let Some(instance_buffer) = self.instance_buffer.as_ref().filter(|_| self.count > 0) else {
+            return false;
+        };
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.atlas.bind_group, &[]);
+        render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+        render_pass.draw(0..6, 0..self.count);
+        true
+    }
+}
+
+fn load_font(path: &Path) -> anyhow::Result<fontdue::Font> {
+    let bytes = std::fs::read(path)?;
+    fontdue::Font::from_bytes(bytes, fontdue::FontSettings::default())
+        .map_err(|e| anyhow::anyhow!("{}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(text: &str) -> TextRequest {
+        TextRequest {
+            text: text.to_string(),
+            position: [10.0, 20.0],
+            size: 16.0,
+            color: [1.0; 4],
+        }
+    }
+
+    #[test]
+    fn text_in_the_world_is_drawn() {
+        let mut world = World::new();
+        assert!(queued(&world).is_empty());
+        world.spawn((request("Score: 0"),));
+        assert_eq!(queued(&world), vec![request("Score: 0")]);
+    }
+
+    #[test]
+    fn glyphs_fill_the_atlas_a_row_at_a_time() {
+        let mut rows = AtlasRows::default();
+        assert_eq!(rows.allocate(10, 20), Some((0, 0)));
+        assert_eq!(rows.allocate(10, 5), Some((11, 0)));
+        // one pixel too wide for what's left of the row
+        assert_eq!(rows.allocate(ATLAS_SIZE - 22, 5), Some((0, 21)));
+    }
+
+    #[test]
+    fn glyphs_too_big_for_the_atlas_are_refused() {
+        let mut rows = AtlasRows::default();
+        assert_eq!(rows.allocate(ATLAS_SIZE, 1), None);
+        assert_eq!(rows.allocate(1, ATLAS_SIZE), None);
+    }
+}
@@ -0,0 +1,46 @@
+// Shader for drawing text a glyph at a time from the font atlas
+
+@group(0) @binding(0)
+var atlas: texture_2d<f32>;
+@group(0) @binding(1)
+var atlas_sampler: sampler;
+
+struct InstanceInput {
+    // the glyph's top left and bottom right corners in clip space
+    @location(0) rect: vec4<f32>,
+    // the same corners of the glyph in the atlas
+    @location(1) uv_rect: vec4<f32>,
+    @location(2) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[index];
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(mix(instance.rect.xy, instance.rect.zw, corner), 0.0, 1.0);
+    out.color = instance.color;
+    out.uv = mix(instance.uv_rect.xy, instance.uv_rect.zw, corner);
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    // the atlas only holds how much of each pixel the glyph covers
+    let coverage = textureSample(atlas, atlas_sampler, in.uv).r;
+    return vec4<f32>(in.color.rgb, in.color.a * coverage);
+}
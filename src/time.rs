@@ -0,0 +1,173 @@
+// Frame timing handed out to scripts and gameplay.
+
+use dropbear_engine::entity::Transform;
+
+/// The longest frame, in seconds, that gameplay is allowed to see. Anything longer (a stall from
+/// dragging the window, a breakpoint or a long load) is cut down to this.
+pub const DEFAULT_MAX_DELTA_TIME: f32 = 0.1;
+
+/// Clamps a frame's delta time to `0..=max_delta_time`.
+pub fn clamp_delta_time(dt: f32, max_delta_time: f32) -> f32 {
+    if dt.is_nan() {
+        return 0.0;
+    }
+    dt.clamp(0.0, max_delta_time)
+}
+
+/// The timing of the current frame, stored on the runtime entity for scripts to read.
+///
+/// The totals carry on across scene switches.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct FrameTime {
+    /// The clamped delta time that scripts are updated with.
+    pub delta: f32,
+    /// The delta time as it was measured, for scripts that want to know about stalls.
+    pub raw_delta: f32,
+    /// With a fixed timestep, how far rendering is between the last two fixed steps, from 0 to
+    /// 1. Always 0 without one.
+    pub interpolation: f32,
+    /// The seconds of gameplay so far, made up of clamped delta times. It stands still while the
+    /// game is paused.
+    pub elapsed: f64,
+    /// The seconds since the game started, as measured, paused or not.
+    pub real_elapsed: f64,
+    /// The number of the current frame, counting from 1 for the first one.
+    pub frame: u64,
+}
+
+impl FrameTime {
+    /// Moves the clock on to the next frame. Gameplay time only moves on if `gameplay` runs
+    /// this frame.
+    pub fn advance(&mut self, delta: f32, raw_delta: f32, gameplay: bool) {
+        self.delta = delta;
+        self.raw_delta = raw_delta;
+        self.interpolation = 0.0;
+        self.frame += 1;
+        self.real_elapsed += raw_delta as f64;
+        if gameplay {
+            self.elapsed += delta as f64;
+        }
+    }
+}
+
+/// The most fixed steps run in a single frame. A frame that would need more than this drops the
+/// rest, so a slow frame can't snowball into ever slower ones.
+pub const MAX_FIXED_STEPS: u32 = 8;
+
+/// Splits variable frame times into a whole number of constant steps, carrying the remainder
+/// over to the next frame.
+#[derive(Debug, Clone, Copy)]
+pub struct FixedTimestep {
+    step: f32,
+    accumulator: f32,
+}
+
+impl FixedTimestep {
+    pub fn new(step: f32) -> Self {
+        Self {
+            step,
+            accumulator: 0.0,
+        }
+    }
+
+    pub fn step(&self) -> f32 {
+        self.step
+    }
+
+    /// Adds a frame's delta time, returning how many steps to run for it.
+    pub fn advance(&mut self, dt: f32) -> u32 {
+        self.accumulator += dt;
+        let steps = (self.accumulator / self.step).floor() as u32;
+        self.accumulator -= steps as f32 * self.step;
+
+        if steps > MAX_FIXED_STEPS {
+            log::debug!(
+                "Frame needed {} fixed steps, dropping all but {}",
+                steps,
+                MAX_FIXED_STEPS
+            );
+            return MAX_FIXED_STEPS;
+        }
+        steps
+    }
+
+    /// How far the leftover time is into the next step, from 0 to 1.
+    pub fn alpha(&self) -> f32 {
+        (self.accumulator / self.step).clamp(0.0, 1.0)
+    }
+}
+
+/// An entity's transform as of the previous fixed step, so rendering can blend from it.
+#[derive(Debug, Clone, Copy)]
+pub struct PreviousTransform(pub Transform);
+
+/// Blends between two transforms, `alpha` of the way from `from` to `to`.
+pub fn interpolate(from: &Transform, to: &Transform, alpha: f64) -> Transform {
+    let mut transform = *to;
+    transform.position = from.position.lerp(to.position, alpha);
+    transform.rotation = from.rotation.slerp(to.rotation, alpha);
+    transform.scale = from.scale.lerp(to.scale, alpha);
+    transform
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gameplay_time_stands_still_while_paused() {
+        let mut time = FrameTime::default();
+        time.advance(0.1, 0.1, true);
+        time.advance(0.1, 0.5, false);
+        assert_eq!(time.frame, 2);
+        assert!((time.elapsed - 0.1).abs() < 1e-6);
+        assert!((time.real_elapsed - 0.6).abs() < 1e-6);
+        assert_eq!(time.raw_delta, 0.5);
+    }
+
+    #[test]
+    fn stalls_are_cut_down_to_the_max_delta_time() {
+        assert_eq!(
+            clamp_delta_time(3.5, DEFAULT_MAX_DELTA_TIME),
+            DEFAULT_MAX_DELTA_TIME
+        );
+        assert_eq!(clamp_delta_time(0.016, DEFAULT_MAX_DELTA_TIME), 0.016);
+    }
+
+    #[test]
+    fn broken_delta_times_are_zero() {
+        assert_eq!(clamp_delta_time(-1.0, DEFAULT_MAX_DELTA_TIME), 0.0);
+        assert_eq!(clamp_delta_time(f32::NAN, DEFAULT_MAX_DELTA_TIME), 0.0);
+    }
+
+    #[test]
+    fn fixed_steps_carry_the_remainder_over() {
+        let mut fixed = FixedTimestep::new(0.25);
+        assert_eq!(fixed.advance(0.6), 2);
+        assert!((fixed.alpha() - 0.4).abs() < 1e-5, "{}", fixed.alpha());
+        // the 0.1 left over makes up a step with the next frame
+        assert_eq!(fixed.advance(0.15), 1);
+        assert_eq!(fixed.advance(0.1), 0);
+        assert!((fixed.alpha() - 0.4).abs() < 1e-5, "{}", fixed.alpha());
+    }
+
+    #[test]
+    fn a_long_frame_runs_at_most_the_max_steps() {
+        let mut fixed = FixedTimestep::new(0.01);
+        assert_eq!(fixed.advance(10.0), MAX_FIXED_STEPS);
+    }
+
+    #[test]
+    fn interpolation_blends_from_the_previous_step() {
+        let from = Transform::default();
+        let to = Transform {
+            position: glam::DVec3::new(4.0, 0.0, -2.0),
+            scale: glam::DVec3::splat(3.0),
+            ..Default::default()
+        };
+        let halfway = interpolate(&from, &to, 0.5);
+        assert_eq!(halfway.position, glam::DVec3::new(2.0, 0.0, -1.0));
+        assert_eq!(halfway.scale, glam::DVec3::splat(2.0));
+        assert_eq!(interpolate(&from, &to, 1.0).position, to.position);
+    }
+}
@@ -0,0 +1,238 @@
+// The fade between scenes.
+//
+// When a scene switch is asked for, the current scene fades out to a solid colour first. Once
+// it's covered, the switch actually happens and the loading screen comes up. After the new scene
+// has loaded it fades back in from the same colour. The fade is drawn over everything else on the
+// surface.
+
+use dropbear_engine::{
+    graphics::{Graphics, Texture},
+    wgpu,
+};
+
+/// How long each half of the fade takes when the package doesn't say, in seconds.
+pub const DEFAULT_FADE_DURATION: f32 = 0.5;
+
+/// Where a scene transition is at.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum Transition {
+    #[default]
+    Idle,
+    /// Fading the current scene out, before switching to `scene`.
+    FadingOut { scene: String, elapsed: f32 },
+    /// Fully faded out, waiting on the new scene to load.
+    Waiting,
+    /// Fading the new scene in.
+    FadingIn { elapsed: f32 },
+}
+
+impl Transition {
+    /// Starts fading out towards `scene`. A transition that's already fading out is pointed at
+    /// the new scene instead, carrying on from where it is.
+    pub fn fade_out(&mut self, scene: String) {
+        let elapsed = match self {
+            Self::FadingOut { elapsed, .. } => *elapsed,
+            _ => 0.0,
+        };
+        *self = Self::FadingOut { scene, elapsed };
+    }
+
+    pub fn is_active(&self) -> bool {
+        *self != Self::Idle
+    }
+
+    /// Moves the transition along by `dt` seconds, with each half of the fade taking
+    /// `duration`. Returns the scene to switch to once the fade out has finished.
+    ///
+    /// Only called while no scene is loading, so a transition waiting on a load starts fading in
+    /// on the first call after it's done.
+    pub fn advance(&mut self, dt: f32, duration: f32) -> Option<String> {
+        match self {
+            Self::Idle => None,
+            Self::FadingOut { scene, elapsed } => {
+                *elapsed += dt;
+                if *elapsed < duration {
+                    return None;
+                }
+                let scene = std::mem::take(scene);
+                *self = Self::Waiting;
+                Some(scene)
+            }
+            Self::Waiting => {
+                *self = Self::FadingIn { elapsed: 0.0 };
+                None
+            }
+            Self::FadingIn { elapsed } => {
+                *elapsed += dt;
+                if *elapsed >= duration {
+                    *self = Self::Idle;
+                }
+                None
+            }
+        }
+    }
+
+    /// How much the fade colour covers the scene, from 0 to 1.
+    pub fn opacity(&self, duration: f32) -> f32 {
+        let progress = |elapsed: f32| {
+            if duration > 0.0 {
+                (elapsed / duration).clamp(0.0, 1.0)
+            } else {
+                1.0
+            }
+        };
+        match self {
+            Self::Idle => 0.0,
+            Self::FadingOut { elapsed, .. } => progress(*elapsed),
+            Self::Waiting => 1.0,
+            Self::FadingIn { elapsed } => 1.0 - progress(*elapsed),
+        }
+    }
+}
+
+/// The pipeline drawing the fade over the surface.
+pub struct FadeOverlay {
+    pipeline: wgpu::RenderPipeline,
+    uniform: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+}
+
+impl FadeOverlay {
+    pub fn new(graphics: &Graphics) -> Self {
+        let device = &graphics.state.device;
+
+        let uniform = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("Fade Uniform"),
+            size: 16,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("Fade Bind Group Layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::FRAGMENT,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("Fade Bind Group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: uniform.as_entire_binding(),
+            }],
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("Fade Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("fade.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("Fade Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("Fade Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: graphics.state.config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // the surface pass comes with the engine's depth buffer, which this never touches
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            pipeline,
+            uniform,
+            bind_group,
+        }
+    }
+
+    /// Sets the colour and opacity the next draw covers the surface with.
+    pub fn update(&self, graphics: &Graphics, color: [f32; 3], opacity: f32) {
+        let bytes: Vec<u8> = [color[0], color[1], color[2], opacity]
+            .iter()
+            .flat_map(|value| value.to_ne_bytes())
+            .collect();
+        graphics.state.queue.write_buffer(&self.uniform, 0, &bytes);
+    }
+
+    /// Draws the fade over whatever is already in `render_pass`.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass<'_>) {
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_bind_group(0, &self.bind_group, &[]);
+        render_pass.draw(0..3, 0..1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fades_out_switches_then_fades_in() {
+        let mut transition = Transition::default();
+        transition.fade_out("level_2".to_string());
+        assert_eq!(transition.advance(0.3, 0.5), None);
+        assert!((transition.opacity(0.5) - 0.6).abs() < 1e-6);
+
+        assert_eq!(transition.advance(0.3, 0.5), Some("level_2".to_string()));
+        assert_eq!(transition, Transition::Waiting);
+        assert_eq!(transition.opacity(0.5), 1.0);
+
+        assert_eq!(transition.advance(0.1, 0.5), None);
+        assert_eq!(transition.opacity(0.5), 1.0);
+        transition.advance(0.25, 0.5);
+        assert!((transition.opacity(0.5) - 0.5).abs() < 1e-6);
+        transition.advance(0.25, 0.5);
+        assert!(!transition.is_active());
+        assert_eq!(transition.opacity(0.5), 0.0);
+    }
+
+    #[test]
+    fn a_new_target_carries_on_the_fade_out() {
+        let mut transition = Transition::default();
+        transition.fade_out("a".to_string());
+        transition.advance(0.4, 0.5);
+        transition.fade_out("b".to_string());
+        assert_eq!(transition.advance(0.1, 0.5), Some("b".to_string()));
+    }
+
+    #[test]
+    fn no_duration_cuts_straight_over() {
+        let mut transition = Transition::default();
+        transition.fade_out("a".to_string());
+        assert_eq!(transition.opacity(0.0), 1.0);
+        assert_eq!(transition.advance(0.0, 0.0), Some("a".to_string()));
+    }
+}
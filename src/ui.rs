@@ -0,0 +1,445 @@
+// HUD elements like health bars and scores, drawn in screen space over the scene.
+//
+// Scenes list their HUD in their settings, and each element is spawned on an entity of its own
+// when the scene loads. A `UiElement` is drawn as a textured quad that doesn't move with the
+// camera. It's placed relative to one of the screen's corners, edges or its centre, in pixels or
+// in fractions of the screen, and worked out again from the surface's size every frame, so
+// elements stay put at their anchor when the window is resized. Elements are drawn after the
+// scene has been resolved to the surface, so post-processing never touches them, but before the
+// scene fade.
+
+use std::{collections::HashMap, path::PathBuf};
+
+use bincode::{Decode, Encode};
+use dropbear_engine::{
+    graphics::{Graphics, Texture},
+    wgpu,
+};
+use hecs::World;
+
+use crate::visibility::{self, Enabled, Visible};
+
+/// The bytes an element takes up in the instance buffer: its rectangle, then its colour.
+const ELEMENT_SIZE: usize = 8 * std::mem::size_of::<f32>();
+
+/// The point of the screen an element is placed relative to, which is also the point of the
+/// element that's placed there. An element anchored to the top right has its own top right
+/// corner in the screen's.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode)]
+pub enum Anchor {
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    #[default]
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// How far across and down the screen the anchor is, from 0 to 1.
+    pub fn fraction(&self) -> (f32, f32) {
+        match self {
+            Self::TopLeft => (0.0, 0.0),
+            Self::Top => (0.5, 0.0),
+            Self::TopRight => (1.0, 0.0),
+            Self::Left => (0.0, 0.5),
+            Self::Center => (0.5, 0.5),
+            Self::Right => (1.0, 0.5),
+            Self::BottomLeft => (0.0, 1.0),
+            Self::Bottom => (0.5, 1.0),
+            Self::BottomRight => (1.0, 1.0),
+        }
+    }
+}
+
+/// A distance on screen.
+#[derive(Debug, Clone, Copy, PartialEq, Encode, Decode)]
+pub enum UiLength {
+    /// In the surface's physical pixels.
+    Pixels(f32),
+    /// A fraction of the screen's width for horizontal distances, or its height for vertical
+    /// ones, so it scales with the window.
+    Fraction(f32),
+}
+
+impl UiLength {
+    /// The length in pixels along a side of the screen `extent` pixels long.
+    pub fn to_pixels(self, extent: f32) -> f32 {
+        match self {
+            Self::Pixels(pixels) => pixels,
+            Self::Fraction(fraction) => fraction * extent,
+        }
+    }
+}
+
+/// A rectangle on screen in pixels, from the top left.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct UiRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+/// A quad drawn in screen space.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub struct UiElement {
+    /// The image drawn on the quad, relative to the working directory like scene models. Without
+    /// one the quad is filled with `color`.
+    pub image: Option<PathBuf>,
+    pub anchor: Anchor,
+    /// How far the element is moved from its anchor, right and down.
+    pub offset: [UiLength; 2],
+    /// The element's width and height.
+    pub size: [UiLength; 2],
+    /// The RGBA colour the image is tinted with.
+    pub color: [f32; 4],
+    /// Elements with a higher order are drawn over those with a lower one.
+    pub order: i32,
+}
+
+impl Default for UiElement {
+    fn default() -> Self {
+        Self {
+            image: None,
+            anchor: Anchor::default(),
+            offset: [UiLength::Pixels(0.0); 2],
+            size: [UiLength::Pixels(100.0); 2],
+            color: [1.0; 4],
+            order: 0,
+        }
+    }
+}
+
+impl UiElement {
+    /// Where the element is on a screen `width` by `height` pixels.
+    pub fn rect(&self, width: u32, height: u32) -> UiRect {
+        let (width, height) = (width as f32, height as f32);
+        let (across, down) = self.anchor.fraction();
+        let element_width = self.size[0].to_pixels(width);
+        let element_height = self.size[1].to_pixels(height);
+        UiRect {
+            x: across * (width - element_width) + self.offset[0].to_pixels(width),
+            y: down * (height - element_height) + self.offset[1].to_pixels(height),
+            width: element_width,
+            height: element_height,
+        }
+    }
+}
+
+/// Draws every visible UI element.
+pub struct UiRenderer {
+    pipeline: wgpu::RenderPipeline,
+    layout: wgpu::BindGroupLayout,
+    sampler: wgpu::Sampler,
+    /// What elements without an image, or whose image couldn't be loaded, are drawn with.
+    blank: wgpu::BindGroup,
+    /// Images by path, or `None` for the ones that failed to load, so they're only tried once.
+    images: HashMap<PathBuf, Option<wgpu::BindGroup>>,
+    instance_buffer: Option<wgpu::Buffer>,
+    /// The image each element uploaded by the last update is drawn with, in draw order.
+    draws: Vec<Option<PathBuf>>,
+}
+
+impl UiRenderer {
+    /// Creates the pipeline drawing UI elements over the surface.
+    pub fn new(graphics: &Graphics) -> Self {
+        let device = &graphics.state.device;
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("UI Bind Group Layout"),
+            entries: &[
+                wgpu::BindGroupLayoutEntry {
+                    binding: 0,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Texture {
+                        sample_type: wgpu::TextureSampleType::Float { filterable: true },
+                        view_dimension: wgpu::TextureViewDimension::D2,
+                        multisampled: false,
+                    },
+                    count: None,
+                },
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::FRAGMENT,
+                    ty: wgpu::BindingType::Sampler(wgpu::SamplerBindingType::Filtering),
+                    count: None,
+                },
+            ],
+        });
+        let sampler = device.create_sampler(&wgpu::SamplerDescriptor {
+            label: Some("UI Sampler"),
+            mag_filter: wgpu::FilterMode::Linear,
+            min_filter: wgpu::FilterMode::Linear,
+            ..Default::default()
+        });
+
+        let shader = device.create_shader_module(wgpu::ShaderModuleDescriptor {
+            label: Some("UI Shader"),
+            source: wgpu::ShaderSource::Wgsl(include_str!("ui.wgsl").into()),
+        });
+        let pipeline_layout = device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+            label: Some("UI Pipeline Layout"),
+            bind_group_layouts: &[&layout],
+            push_constant_ranges: &[],
+        });
+        let pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("UI Pipeline"),
+            layout: Some(&pipeline_layout),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: ELEMENT_SIZE as wgpu::BufferAddress,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+                }],
+                compilation_options: Default::default(),
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                targets: &[Some(wgpu::ColorTargetState {
+                    format: graphics.state.config.format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+                compilation_options: Default::default(),
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            // the surface pass comes with the engine's depth buffer, which this never touches
+            depth_stencil: Some(wgpu::DepthStencilState {
+                format: Texture::DEPTH_FORMAT,
+                depth_write_enabled: false,
+                depth_compare: wgpu::CompareFunction::Always,
+                stencil: wgpu::StencilState::default(),
+                bias: wgpu::DepthBiasState::default(),
+            }),
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        let blank = create_image(
+            graphics,
+            &layout,
+            &sampler,
+            &image::RgbaImage::from_pixel(1, 1, image::Rgba([255; 4])),
+        );
+
+        Self {
+            pipeline,
+            layout,
+            sampler,
+            blank,
+            images: HashMap::new(),
+            instance_buffer: None,
+            draws: Vec::new(),
+        }
+    }
+
+    /// Uploads every visible element in `world` followed by the runtime's own `overlay`, placed
+    /// on the surface as it's sized now, and loads the images they use that haven't been loaded
+    /// yet.
+    pub fn update(&mut self, graphics: &Graphics, world: &World, overlay: &[UiElement]) {
+        let (width, height) = (graphics.state.config.width, graphics.state.config.height);
+        let mut query = world.query::<(&UiElement, Option<&Enabled>, Option<&Visible>)>();
+        let mut elements: Vec<&UiElement> = query
+            .iter()
+            .filter(|(_, (_, enabled, visible))| visibility::is_drawn(*enabled, *visible))
+            .map(|(_, (element, _, _))| element)
+            .collect();
+        // a stable sort, so elements with the same order keep the order they're in the world
+        elements.sort_by_key(|element| element.order);
+        elements.extend(overlay);
+
+        self.draws.clear();
+        let mut instances = Vec::with_capacity(elements.len() * ELEMENT_SIZE);
+        for element in elements {
+            let rect = element.rect(width, height);
+            let left = rect.x / width as f32 * 2.0 - 1.0;
+            let top = 1.0 - rect.y / height as f32 * 2.0;
+            let right = (rect.x + rect.width) / width as f32 * 2.0 - 1.0;
+            let bottom = 1.0 - (rect.y + rect.height) / height as f32 * 2.0;
+            for value in [left, top, right, bottom].into_iter().chain(element.color) {
+                instances.extend(value.to_ne_bytes());
+            }
+
+            if let Some(path) = &element.image
+                && !self.images.contains_key(path)
+            {
+                let image = match image::open(path) {
+                    Ok(image) => Some(create_image(
+                        graphics,
+                        &self.layout,
+                        &self.sampler,
+                        &image.to_rgba8(),
+                    )),
+                    Err(e) => {
+                        log::warn!("Unable to load UI image {}: {}", path.display(), e);
+                        None
+                    }
+                };
+                self.images.insert(path.clone(), image);
+            }
+            self.draws.push(element.image.clone());
+        }
+        if instances.is_empty() {
+            return;
+        }
+
+        let needed = instances.len() as u64;
+        if self
+            .instance_buffer
+            .as_ref()
+            .is_none_or(|buffer| buffer.size() < needed)
+        {
+            self.instance_buffer = Some(graphics.state.device.create_buffer(
+                &wgpu::BufferDescriptor {
+                    label: Some("UI Instance Buffer"),
+                    size: needed.next_power_of_two(),
+                    usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+                    mapped_at_creation: false,
+                },
+            ));
+        }
+        if let Some(buffer) = &self.instance_buffer {
+            graphics.state.queue.write_buffer(buffer, 0, &instances);
+        }
+    }
+
+    /// Draws the elements uploaded by the last [`Self::update`] over whatever is already in
+    /// `render_pass`, returning the amount of draw calls made.
+    pub fn draw(&self, render_pass: &mut wgpu::RenderPass<'_>) -> usize {
+        let Some(instance_buffer) = self
+            .instance_buffer
+            .as_ref()
+            .filter(|_| !self.draws.is_empty())
+        else {
+            return 0;
+        };
+        render_pass.set_pipeline(&self.pipeline);
+        render_pass.set_vertex_buffer(0, instance_buffer.slice(..));
+        for (index, image) in self.draws.iter().enumerate() {
+            let bind_group = image
+                .as_ref()
+                .and_then(|path| self.images.get(path)?.as_ref())
+                .unwrap_or(&self.blank);
+            render_pass.set_bind_group(0, bind_group, &[]);
+            let index = index as u32;
+            render_pass.draw(0..6, index..index + 1);
+        }
+        self.draws.len()
+    }
+}
+
+/// Uploads `image` and creates the bind group the UI shader samples it through.
+fn create_image(
+    graphics: &Graphics,
+    layout: &wgpu::BindGroupLayout,
+    sampler: &wgpu::Sampler,
+    image: &image::RgbaImage,
+) -> wgpu::BindGroup {
+    let size = wgpu::Extent3d {
+        width: image.width(),
+        height: image.height(),
+        depth_or_array_layers: 1,
+    };
+    let texture = graphics
+        .state
+        .device
+        .create_texture(&wgpu::TextureDescriptor {
+            label: Some("UI Texture"),
+            size,
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: wgpu::TextureFormat::Rgba8UnormSrgb,
+            usage: wgpu::TextureUsages::TEXTURE_BINDING | wgpu::TextureUsages::COPY_DST,
+            view_formats: &[],
+        });
+    graphics.state.queue.write_texture(
+        wgpu::TexelCopyTextureInfo {
+            texture: &texture,
+            mip_level: 0,
+            origin: wgpu::Origin3d::ZERO,
+            aspect: wgpu::TextureAspect::All,
+        },
+        image,
+        wgpu::TexelCopyBufferLayout {
+            offset: 0,
+            bytes_per_row: Some(4 * image.width()),
+            rows_per_image: Some(image.height()),
+        },
+        size,
+    );
+
+    let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+    graphics
+        .state
+        .device
+        .create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("UI Bind Group"),
+            layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: wgpu::BindingResource::TextureView(&view),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: wgpu::BindingResource::Sampler(sampler),
+                },
+            ],
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn elements_stay_at_their_anchor_across_a_resize() {
+        let element = UiElement {
+            anchor: Anchor::BottomRight,
+            offset: [UiLength::Pixels(-10.0), UiLength::Pixels(-10.0)],
+            size: [UiLength::Pixels(100.0), UiLength::Pixels(50.0)],
+            ..Default::default()
+        };
+        for (width, height) in [(800, 600), (1920, 1080)] {
+            let rect = element.rect(width, height);
+            assert_eq!(rect.x + rect.width, width as f32 - 10.0);
+            assert_eq!(rect.y + rect.height, height as f32 - 10.0);
+            assert_eq!((rect.width, rect.height), (100.0, 50.0));
+        }
+    }
+
+    #[test]
+    fn fractions_scale_with_the_screen() {
+        let element = UiElement {
+            anchor: Anchor::TopLeft,
+            offset: [UiLength::Fraction(0.1), UiLength::Pixels(0.0)],
+            size: [UiLength::Fraction(0.5), UiLength::Fraction(0.25)],
+            ..Default::default()
+        };
+        assert_eq!(
+            element.rect(1000, 400),
+            UiRect {
+                x: 100.0,
+                y: 0.0,
+                width: 500.0,
+                height: 100.0,
+            }
+        );
+    }
+
+    #[test]
+    fn centred_elements_sit_in_the_middle() {
+        let rect = UiElement::default().rect(800, 600);
+        assert_eq!((rect.x, rect.y), (350.0, 250.0));
+    }
+}
@@ -0,0 +1,43 @@
+// Shader for drawing UI elements as textured quads in screen space
+
+@group(0) @binding(0)
+var ui_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var ui_sampler: sampler;
+
+struct InstanceInput {
+    // the element's top left and bottom right corners in clip space
+    @location(0) rect: vec4<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) color: vec4<f32>,
+    @location(1) uv: vec2<f32>,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) index: u32, instance: InstanceInput) -> VertexOutput {
+    // two triangles making a quad, with the texture's top left at the element's top left
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 0.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(0.0, 1.0),
+    );
+    let corner = corners[index];
+
+    var out: VertexOutput;
+    out.clip_position = vec4<f32>(mix(instance.rect.xy, instance.rect.zw, corner), 0.0, 1.0);
+    out.color = instance.color;
+    out.uv = corner;
+    return out;
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    return textureSample(ui_texture, ui_sampler, in.uv) * in.color;
+}
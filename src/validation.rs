@@ -0,0 +1,213 @@
+// Checks over the decoded package, run before the game window opens so broken packages are
+// reported up front instead of failing somewhere in the middle of a scene load.
+
+use std::{fmt, path::PathBuf};
+
+use eucalyptus_core::states::SceneConfig;
+
+use crate::{config::LimitSettings, loading::scene_asset_paths};
+
+/// A file a scene refers to that doesn't exist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MissingAsset {
+    pub scene: String,
+    pub path: PathBuf,
+}
+
+/// A scene with more entities than the package allows.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OversizedScene {
+    pub scene: String,
+    pub entities: usize,
+    pub limit: usize,
+}
+
+impl fmt::Display for OversizedScene {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "scene '{}' has {} entities, more than the limit of {}",
+            self.scene, self.entities, self.limit
+        )
+    }
+}
+
+impl fmt::Display for MissingAsset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} (used by scene '{}')",
+            self.path.display(),
+            self.scene
+        )
+    }
+}
+
+/// Collects every asset referenced by `scenes` that can't be found, rather than stopping at
+/// the first one.
+pub fn find_missing_assets<'a>(
+    scenes: impl IntoIterator<Item = &'a SceneConfig>,
+) -> Vec<MissingAsset> {
+    scenes
+        .into_iter()
+        .flat_map(|scene| missing_from(&scene.scene_name, scene_asset_paths(scene)))
+        .collect()
+}
+
+/// The files out of `paths`, used by the scene `scene_name`, that don't exist.
+fn missing_from(scene_name: &str, paths: Vec<PathBuf>) -> Vec<MissingAsset> {
+    paths
+        .into_iter()
+        .filter(|path| !path.exists())
+        .map(|path| MissingAsset {
+            scene: scene_name.to_string(),
+            path,
+        })
+        .collect()
+}
+
+/// How a scene's number of entities compares to the package's limits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum SceneSize {
+    Fine,
+    /// Over the number of entities scenes are expected to have, but still loaded.
+    Large,
+    TooLarge(OversizedScene),
+}
+
+/// Compares the `entities` of the scene `scene_name` to `limits`.
+fn scene_size(scene_name: &str, entities: usize, limits: &LimitSettings) -> SceneSize {
+    if entities > limits.max_entities() {
+        SceneSize::TooLarge(OversizedScene {
+            scene: scene_name.to_string(),
+            entities,
+            limit: limits.max_entities(),
+        })
+    } else if entities > limits.entity_warning() {
+        SceneSize::Large
+    } else {
+        SceneSize::Fine
+    }
+}
+
+/// Collects every scene in `scenes` with more entities than the hard limit, warning about the
+/// ones that are only over the soft limit.
+pub fn find_oversized_scenes<'a>(
+    scenes: impl IntoIterator<Item = &'a SceneConfig>,
+    limits: &LimitSettings,
+) -> Vec<OversizedScene> {
+    let mut oversized = Vec::new();
+    for scene in scenes {
+        let entities = scene.entities.len();
+        match scene_size(&scene.scene_name, entities, limits) {
+            SceneSize::Fine => {}
+            SceneSize::Large => log::warn!(
+                "Scene '{}' has {} entities, which is more than the {} it's expected to have and \
+                may run slowly or run out of memory",
+                scene.scene_name,
+                entities,
+                limits.entity_warning()
+            ),
+            SceneSize::TooLarge(scene) => oversized.push(scene),
+        }
+    }
+    oversized
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::package::{missing_assets_message, too_many_entities_message};
+
+    #[test]
+    fn every_missing_asset_is_reported() {
+        let present = std::env::temp_dir().join(format!("redback-asset-{}", std::process::id()));
+        std::fs::write(&present, b"model").unwrap();
+        let paths = vec![
+            PathBuf::from("models/missing-tree.glb"),
+            present.clone(),
+            PathBuf::from("scripts/missing-player.script"),
+        ];
+        let missing = missing_from("Forest", paths);
+        std::fs::remove_file(&present).unwrap();
+
+        assert_eq!(
+            missing,
+            [
+                MissingAsset {
+                    scene: "Forest".to_string(),
+                    path: PathBuf::from("models/missing-tree.glb"),
+                },
+                MissingAsset {
+                    scene: "Forest".to_string(),
+                    path: PathBuf::from("scripts/missing-player.script"),
+                },
+            ]
+        );
+        let message = missing_assets_message(&missing, "logs");
+        assert!(message.contains("missing-tree.glb (used by scene 'Forest')"));
+        assert!(message.contains("missing-player.script (used by scene 'Forest')"));
+    }
+
+    fn limits() -> LimitSettings {
+        LimitSettings {
+            entity_warning: Some(10),
+            max_entities: Some(20),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn scenes_under_the_warning_limit_are_fine() {
+        assert_eq!(scene_size("Hut", 0, &limits()), SceneSize::Fine);
+        assert_eq!(scene_size("Hut", 10, &limits()), SceneSize::Fine);
+    }
+
+    #[test]
+    fn scenes_between_the_limits_are_only_warned_about() {
+        assert_eq!(scene_size("Village", 11, &limits()), SceneSize::Large);
+        assert_eq!(scene_size("Village", 20, &limits()), SceneSize::Large);
+    }
+
+    #[test]
+    fn scenes_over_the_hard_limit_are_too_large() {
+        assert_eq!(
+            scene_size("City", 21, &limits()),
+            SceneSize::TooLarge(OversizedScene {
+                scene: "City".to_string(),
+                entities: 21,
+                limit: 20,
+            })
+        );
+    }
+
+    #[test]
+    fn oversized_scenes_are_listed_for_the_player() {
+        let oversized = [
+            OversizedScene {
+                scene: "City".to_string(),
+                entities: 21,
+                limit: 20,
+            },
+            OversizedScene {
+                scene: "Forest".to_string(),
+                entities: 300,
+                limit: 20,
+            },
+        ];
+        let expected = [
+            "Your game has scenes too big to load:",
+            "",
+            "  - scene 'City' has 21 entities, more than the limit of 20",
+            "  - scene 'Forest' has 300 entities, more than the limit of 20",
+            "",
+            "Please report this issue to the developer. ",
+            "",
+            "Logs are attached in logs/redback-runtime.log, so send that to them too!",
+        ];
+        assert_eq!(
+            too_many_entities_message(&oversized, "logs/redback-runtime.log"),
+            expected.join("\n")
+        );
+    }
+}
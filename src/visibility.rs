@@ -0,0 +1,48 @@
+// Hiding or freezing entities without despawning them.
+//
+// Both components are optional: an entity without them is visible and enabled. Packages can start
+// entities out hidden or disabled through their model settings, and flipping them back takes
+// effect from the next frame, with the entity's state left just as it was.
+
+/// Whether an entity is drawn. Hidden entities still run their scripts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Visible(pub bool);
+
+/// Whether an entity takes part in the game at all. Disabled entities are neither drawn nor have
+/// their scripts updated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Enabled(pub bool);
+
+/// Whether an entity with these components is drawn.
+pub fn is_drawn(enabled: Option<&Enabled>, visible: Option<&Visible>) -> bool {
+    is_updated(enabled) && visible.is_none_or(|visible| visible.0)
+}
+
+/// Whether an entity with this component has its script updated.
+pub fn is_updated(enabled: Option<&Enabled>) -> bool {
+    enabled.is_none_or(|enabled| enabled.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn entities_without_either_are_drawn_and_updated() {
+        assert!(is_drawn(None, None));
+        assert!(is_updated(None));
+    }
+
+    #[test]
+    fn hidden_entities_are_still_updated() {
+        assert!(!is_drawn(None, Some(&Visible(false))));
+        assert!(is_updated(None));
+    }
+
+    #[test]
+    fn disabled_entities_are_neither_drawn_nor_updated() {
+        assert!(!is_drawn(Some(&Enabled(false)), Some(&Visible(true))));
+        assert!(!is_updated(Some(&Enabled(false))));
+        assert!(is_drawn(Some(&Enabled(true)), Some(&Visible(true))));
+    }
+}
@@ -0,0 +1,96 @@
+// Switching the window between windowed, maximised and fullscreen while the game is running.
+//
+// The engine picks up the new size through the window's resize event and reconfigures the
+// surface for it, so nothing here has to touch the surface itself.
+
+use winit::{
+    dpi::PhysicalSize,
+    window::{Fullscreen, Window},
+};
+
+use crate::config::WindowMode;
+
+/// The window's current mode, and the mode fullscreen goes back to when toggled off.
+#[derive(Debug, Clone, Copy)]
+pub struct WindowModeState {
+    current: WindowMode,
+    restore: WindowMode,
+}
+
+impl WindowModeState {
+    pub fn new(mode: WindowMode) -> Self {
+        Self {
+            current: mode,
+            restore: if mode == WindowMode::Fullscreen {
+                WindowMode::Maximised
+            } else {
+                mode
+            },
+        }
+    }
+
+    pub fn current(&self) -> WindowMode {
+        self.current
+    }
+
+    /// Records that the window is now in `mode`.
+    pub fn set(&mut self, mode: WindowMode) {
+        if mode != WindowMode::Fullscreen {
+            self.restore = mode;
+        }
+        self.current = mode;
+    }
+
+    /// The mode the fullscreen toggle switches to from the current one.
+    pub fn toggled(&self) -> WindowMode {
+        if self.current == WindowMode::Fullscreen {
+            self.restore
+        } else {
+            WindowMode::Fullscreen
+        }
+    }
+}
+
+/// Puts `window` into `mode`. `resolution` is the inner size used in windowed mode.
+pub fn apply(window: &Window, mode: WindowMode, resolution: (u32, u32)) {
+    log::info!("Switching the window to {:?}", mode);
+    match mode {
+        WindowMode::Windowed => {
+            window.set_fullscreen(None);
+            window.set_maximized(false);
+            let _ = window.request_inner_size(PhysicalSize::new(resolution.0, resolution.1));
+        }
+        WindowMode::Maximised => {
+            window.set_fullscreen(None);
+            window.set_maximized(true);
+        }
+        WindowMode::Fullscreen => {
+            // borderless, so switching in and out is quick and doesn't change the display mode
+            window.set_fullscreen(Some(Fullscreen::Borderless(None)));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fullscreen_toggles_back_to_the_last_mode() {
+        let mut state = WindowModeState::new(WindowMode::Windowed);
+        assert_eq!(state.toggled(), WindowMode::Fullscreen);
+        state.set(state.toggled());
+        assert_eq!(state.current(), WindowMode::Fullscreen);
+        assert_eq!(state.toggled(), WindowMode::Windowed);
+
+        state.set(WindowMode::Maximised);
+        state.set(WindowMode::Fullscreen);
+        assert_eq!(state.toggled(), WindowMode::Maximised);
+    }
+
+    #[test]
+    fn starting_in_fullscreen_toggles_to_maximised() {
+        let state = WindowModeState::new(WindowMode::Fullscreen);
+        assert_eq!(state.toggled(), WindowMode::Maximised);
+    }
+}